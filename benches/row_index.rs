@@ -0,0 +1,156 @@
+// Benchmarks for mapping between rows and lines on large documents
+//
+// Run with: cargo bench --bench row_index
+
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::time::Instant;
+use textchen::document::Document;
+use textchen::row_index::RowIndex;
+
+const NUM_LINES: usize = 100_000;
+const EDITOR_WIDTH: usize = 80;
+const ITERATIONS: usize = 10_000;
+
+fn bench(name: &str, iterations: usize, mut f: impl FnMut(usize)) {
+    let start = Instant::now();
+
+    for i in 0..iterations {
+        f(i);
+    }
+
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:<40} {:>10} iterations {:>12.1} ns/iter",
+        name,
+        iterations,
+        elapsed.as_nanos() as f64 / iterations as f64
+    );
+}
+
+fn spread(i: usize, len: usize) -> usize {
+    //! Spreads the iterations over the whole range so the lookups do not all hit the same lines
+    (i.wrapping_mul(7919)) % len
+}
+
+fn row_counts() -> Vec<usize> {
+    // A mix of short lines and lines long enough to wrap over several rows
+    (0..NUM_LINES).map(|i| 1 + (i % 7) / 3).collect()
+}
+
+fn bench_row_index() {
+    let counts = row_counts();
+    let total: usize = counts.iter().sum();
+
+    bench("row_index/from_counts", 100, |_| {
+        black_box(RowIndex::from_counts(counts.clone()));
+    });
+
+    let mut index = RowIndex::from_counts(counts.clone());
+
+    bench("row_index/line_at_row", ITERATIONS, |i| {
+        black_box(index.line_at_row(spread(i, total)));
+    });
+
+    bench("row_index/first_row_of", ITERATIONS, |i| {
+        black_box(index.first_row_of(spread(i, NUM_LINES)));
+    });
+
+    bench("row_index/set", ITERATIONS, |i| {
+        index.set(spread(i, NUM_LINES), 1 + i % 3);
+    });
+
+    bench("row_index/insert_middle", 1_000, |i| {
+        index.insert(NUM_LINES / 2, 1 + i % 3);
+    });
+
+    bench("row_index/remove_middle", 1_000, |_| {
+        black_box(index.remove(NUM_LINES / 2));
+    });
+
+    // The linear scan the row index replaced, for comparison
+    bench("linear_scan/line_at_row", 1_000, |i| {
+        let row = spread(i, total);
+        let mut rows_passed = 0;
+
+        for (ind, rows) in counts.iter().enumerate() {
+            rows_passed += rows;
+
+            if rows_passed > row {
+                black_box(ind);
+                break;
+            }
+        }
+    });
+}
+
+fn bench_document() {
+    let path = env::temp_dir().join("textchen_row_index_bench.txt");
+
+    let contents = (0..NUM_LINES)
+        .map(|i| "word ".repeat(1 + (i % 7) * 10))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    fs::write(&path, contents).unwrap();
+
+    bench("document/new", 10, |_| {
+        black_box(Document::new(path.to_str().unwrap(), (40, EDITOR_WIDTH)));
+    });
+
+    let mut document = Document::new(path.to_str().unwrap(), (40, EDITOR_WIDTH));
+
+    let total = document.num_rows();
+
+    bench("document/get_index_at_cursor", ITERATIONS, |i| {
+        black_box(document.get_index_at_cursor(spread(i, total)));
+    });
+
+    bench("document/get_line_at_cursor", ITERATIONS, |i| {
+        black_box(document.get_line_at_cursor(spread(i, total)));
+    });
+
+    bench("document/edit_insert", ITERATIONS, |i| {
+        let row = spread(i, document.num_rows());
+
+        document.begin_edit(row, 0);
+
+        for c in "edit ".chars().cycle().take(i % 40) {
+            document.edit_insert(c);
+        }
+
+        document.end_edit();
+    });
+
+    bench("document/add_line_at_row", 1_000, |i| {
+        let row = spread(i, document.num_rows());
+
+        document.add_line_at_row("added".to_string(), row);
+    });
+
+    bench("document/remove_line_from_doc", 1_000, |i| {
+        let row = spread(i, document.num_rows());
+
+        document.remove_line_from_doc(row, EDITOR_WIDTH);
+    });
+
+    bench("document/rows_from (one screen)", ITERATIONS, |i| {
+        let first = spread(i, document.num_rows());
+
+        black_box(document.rows_from(first, EDITOR_WIDTH).take(40).count());
+    });
+
+    fs::remove_file(&path).unwrap();
+}
+
+fn main() {
+    println!("{NUM_LINES} lines, editor width {EDITOR_WIDTH}\n");
+
+    bench_row_index();
+
+    println!();
+
+    bench_document();
+}
@@ -11,8 +11,8 @@ fn main() {
     }
 
     Command::new("gcc")
-        .args(&["src/termc.c", "-c", "-fPIC", "-o"])
-        .arg(&format!("{}termc.o", out_dir))
+        .args(["src/termc.c", "-c", "-fPIC", "-o"])
+        .arg(format!("{}termc.o", out_dir))
         .status()
         .unwrap();
 
@@ -21,8 +21,8 @@ fn main() {
 
     #[cfg(target_os = "linux")]
     Command::new("ar")
-        .args(&["crus", "libtermc.a", "termc.o"])
-        .current_dir(&Path::new(out_dir))
+        .args(["crus", "libtermc.a", "termc.o"])
+        .current_dir(Path::new(out_dir))
         .status()
         .unwrap();
 
@@ -0,0 +1,318 @@
+use crate::window::Direction;
+use std::path::{Path, PathBuf};
+
+// The command line is a list of files to open, any of which can be given as file:line or
+// file:line:column to start with the cursor there, mixed with flags. A file name that starts with -
+// can still be opened by putting it after --.
+
+pub const USAGE: &str = "\
+Usage: textchen [options] [file ...]
+
+Files:
+  file              Open the file
+  file:line[:col]   Open the file with the cursor on the line and column, counted from 1
+  -                 Read the text to edit from stdin
+
+Options:
+  +N                Start with the cursor on line N of the first file, + alone for the last line
+  +cmd, -c cmd      Run the command as if typed after : once the files are open
+  -R, --view        Read only view, q quits, reads stdin when no file is given
+  -u file           Use the file as the config file, NONE to skip the config file
+  -d                Show the differences between the first two files side by side
+  -o                Open every file in its own window, stacked
+  -O                Open every file in its own window, side by side
+  --                Take everything after as a file name
+  -h, --help        Show this help and exit
+  --version         Show the version and exit
+";
+
+/// A place in a file, counted from 1 as it is given on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// usize::MAX for the last line
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileArg {
+    /// - for stdin
+    pub name: String,
+    pub position: Option<Position>,
+}
+
+/// Which config file is run at startup
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ConfigFile {
+    /// The one found by Editor::config_path, if it is there
+    #[default]
+    Default,
+    /// Given with -u NONE
+    Skip,
+    Path(PathBuf),
+}
+
+/// What the editor was asked to do on startup
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    pub files: Vec<FileArg>,
+    /// Run in order once the files are open
+    pub commands: Vec<String>,
+    pub view: bool,
+    pub config: ConfigFile,
+    pub diff: bool,
+    /// Set by -o and -O, and by -d when neither is given
+    pub split: Option<Direction>,
+}
+
+/// What running textchen with the command line leads to
+#[derive(Debug)]
+pub enum Invocation {
+    Edit(Args),
+    Help,
+    Version,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Invocation, String> {
+        //! Reads the command line without the program name, an error describes what was wrong with
+        //! it
+
+        let mut parsed = Args::default();
+
+        // +N is for the first file, which may come after it
+        let mut first_position = None;
+
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--" => {
+                    parsed.files.extend(args.by_ref().map(|name| FileArg {
+                        name,
+                        position: None,
+                    }));
+                }
+                "-h" | "--help" => return Ok(Invocation::Help),
+                "--version" => return Ok(Invocation::Version),
+                "-R" | "--view" => parsed.view = true,
+                "-d" => parsed.diff = true,
+                "-o" => parsed.split = Some(Direction::Stacked),
+                "-O" => parsed.split = Some(Direction::SideBySide),
+                "-c" => parsed.commands.push(value_of(&mut args, "-c")?),
+                "-u" => {
+                    let file = value_of(&mut args, "-u")?;
+
+                    parsed.config = if file == "NONE" {
+                        ConfigFile::Skip
+                    } else {
+                        ConfigFile::Path(PathBuf::from(file))
+                    };
+                }
+                "-" => parsed.files.push(FileArg {
+                    name: arg,
+                    position: None,
+                }),
+                "+" => {
+                    first_position = Some(Position {
+                        line: usize::MAX,
+                        column: None,
+                    })
+                }
+                _ if arg.starts_with('+') => match arg[1..].parse::<usize>() {
+                    Ok(line) => {
+                        first_position = Some(Position {
+                            line: line.max(1),
+                            column: None,
+                        })
+                    }
+                    Err(_) => parsed.commands.push(arg[1..].to_string()),
+                },
+                _ if arg.starts_with('-') => return Err(format!("Unknown option: {arg}")),
+                _ => parsed.files.push(file_arg(arg)),
+            }
+        }
+
+        if let (Some(position), Some(first)) = (first_position, parsed.files.first_mut()) {
+            first.position.get_or_insert(position);
+        }
+
+        if parsed.diff {
+            if parsed.files.len() < 2 {
+                return Err("-d needs two files to compare".to_string());
+            }
+
+            parsed.split.get_or_insert(Direction::SideBySide);
+        }
+
+        Ok(Invocation::Edit(parsed))
+    }
+}
+
+fn value_of(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{flag} needs an argument"))
+}
+
+fn file_arg(arg: String) -> FileArg {
+    //! Splits a trailing :line or :line:column off the file name, unless a file by the whole name
+    //! is there, a colon after them is left out as well so that file:line:column: from a
+    //! compiler's output can be used as it is
+
+    if Path::new(&arg).exists() {
+        return FileArg {
+            name: arg,
+            position: None,
+        };
+    }
+
+    let mut name = arg.strip_suffix(':').unwrap_or(&arg);
+    let mut numbers = Vec::new();
+
+    while numbers.len() < 2 {
+        let Some((rest, number)) = name.rsplit_once(':') else {
+            break;
+        };
+
+        match number.parse::<usize>() {
+            Ok(number) if number > 0 && !rest.is_empty() => {
+                numbers.insert(0, number);
+                name = rest;
+            }
+            _ => break,
+        }
+    }
+
+    let position = match numbers[..] {
+        [line] => Some(Position { line, column: None }),
+        [line, column] => Some(Position {
+            line,
+            column: Some(column),
+        }),
+        _ => None,
+    };
+
+    FileArg {
+        name: match position {
+            Some(_) => name.to_string(),
+            None => arg,
+        },
+        position,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Invocation, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parse_edit(args: &[&str]) -> Args {
+        match parse(args) {
+            Ok(Invocation::Edit(args)) => args,
+            other => panic!("not an edit: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_options_are_errors() {
+        assert_eq!(
+            parse(&["file", "-x"]).unwrap_err(),
+            "Unknown option: -x".to_string()
+        );
+        assert_eq!(
+            parse(&["--wrap"]).unwrap_err(),
+            "Unknown option: --wrap".to_string()
+        );
+    }
+
+    #[test]
+    fn options_without_their_argument_are_errors() {
+        assert_eq!(
+            parse(&["file", "-c"]).unwrap_err(),
+            "-c needs an argument".to_string()
+        );
+        assert_eq!(
+            parse(&["-u"]).unwrap_err(),
+            "-u needs an argument".to_string()
+        );
+    }
+
+    #[test]
+    fn diff_needs_two_files() {
+        assert_eq!(
+            parse(&["-d", "file"]).unwrap_err(),
+            "-d needs two files to compare".to_string()
+        );
+        assert_eq!(
+            parse_edit(&["-d", "one", "two"]).split,
+            Some(Direction::SideBySide)
+        );
+        assert_eq!(
+            parse_edit(&["-o", "-d", "one", "two"]).split,
+            Some(Direction::Stacked)
+        );
+    }
+
+    #[test]
+    fn help_and_version_win_over_errors_after_them() {
+        assert!(matches!(parse(&["--help", "-x"]), Ok(Invocation::Help)));
+        assert!(matches!(
+            parse(&["--version", "-c"]),
+            Ok(Invocation::Version)
+        ));
+    }
+
+    #[test]
+    fn anything_after_dashes_is_a_file() {
+        let args = parse_edit(&["--", "-x", "-c"]);
+
+        assert_eq!(
+            args.files
+                .iter()
+                .map(|file| file.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["-x", "-c"]
+        );
+        assert!(args.commands.is_empty());
+    }
+
+    #[test]
+    fn positions_are_split_off_file_names() {
+        let args = parse_edit(&["+7", "no/such/file.rs:3:4:", "other:2", "+set nowrap"]);
+
+        assert_eq!(
+            args.files,
+            vec![
+                FileArg {
+                    name: "no/such/file.rs".to_string(),
+                    position: Some(Position {
+                        line: 3,
+                        column: Some(4),
+                    }),
+                },
+                FileArg {
+                    name: "other".to_string(),
+                    position: Some(Position {
+                        line: 2,
+                        column: None,
+                    }),
+                },
+            ]
+        );
+        assert_eq!(args.commands, vec!["set nowrap".to_string()]);
+
+        let args = parse_edit(&["+", "-u", "NONE", "file"]);
+
+        assert_eq!(
+            args.files[0].position,
+            Some(Position {
+                line: usize::MAX,
+                column: None,
+            })
+        );
+        assert_eq!(args.config, ConfigFile::Skip);
+    }
+}
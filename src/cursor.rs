@@ -1,6 +1,14 @@
 use crate::editor::Editor;
 use std::rc::Rc;
 
+pub fn plain_row_of(pos: usize, wrap_width: usize) -> usize {
+    //! The row of a plainly wrapped line the cursor is on at pos, the cursor hangs off the right
+    //! side of a full row instead of going onto the first character of the next row, so the first
+    //! character of any row but the first can not be under the cursor
+
+    pos.saturating_sub(1) / wrap_width.max(1)
+}
+
 pub struct Cursor {
     pub doc_row: usize,
     pub doc_column: usize,
@@ -10,6 +18,12 @@ pub struct Cursor {
     pub prev_col: Vec<usize>,
 }
 
+impl Default for Cursor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Cursor {
     pub fn new() -> Self {
         Self {
@@ -136,6 +150,21 @@ impl Cursor {
 
         let mut move_str = String::new();
 
+        // Where the cursor goes within the last row of the line, the column in the document and the
+        // column in the document display window, which also counts what is drawn before the text of
+        // a row the line wraps onto
+        let (end_col, end_vis_col) = if binding.wraps_plainly() {
+            let col = curr_line.1.len() % editor.wrap_width();
+
+            (col, col)
+        } else {
+            let layout = binding.row_layout(binding.get_index_at_cursor(self.doc_row));
+            let last_row = layout.num_rows() - 1;
+            let col = curr_line.1.len() - layout.starts[last_row];
+
+            (col, layout.column_of(&curr_line.1, curr_line.1.len()))
+        };
+
         // The cursor's position mod the editor width is the distance from the left edge, adding the left
         // edge to the result gets the distance from the terminal's left edge
         // (curr_line.1.len() % editor_width) + editor_left_edge;
@@ -146,7 +175,7 @@ impl Cursor {
         if self.get_position_in_line(editor) != curr_line.1.len() {
             // If the cursor is not already at the end of the line
 
-            if (document.borrow().visible_rows.0..(document.borrow().visible_rows.1 - 1))
+            if (document.borrow().viewport.top()..(document.borrow().viewport.bottom() - 1))
                 .contains(&curr_line_final_row)
             {
                 // If the last row of the current line is within the visible rows exclusive of the document and the last visible row is strictly
@@ -154,33 +183,30 @@ impl Cursor {
 
                 move_str += self
                     .move_to(
-                        (curr_line_final_row - document.borrow().visible_rows.0)
+                        (curr_line_final_row - document.borrow().viewport.top())
                             + editor.doc_disp_home_row(),
-                        (curr_line.1.len() % editor.doc_disp_width()) + editor.doc_disp_left_edge(),
+                        end_vis_col + editor.doc_disp_left_edge(),
                     )
                     .as_str();
 
-                self.move_doc_to(
-                    curr_line_final_row,
-                    curr_line.1.len() % editor.doc_disp_width(),
-                );
+                self.move_doc_to(curr_line_final_row, end_col);
             } else {
                 // If the last row of the current line is within the visible rows inclusive of the document and the last row of the current line
                 // is greater than or equal to the last visible row
 
                 move_str += self
                     .move_to(
-                        editor.doc_disp_height(),
-                        curr_line.1.len() % editor.doc_disp_width() + editor.doc_disp_left_edge(),
+                        editor.doc_disp_bottom() - 1,
+                        end_vis_col + editor.doc_disp_left_edge(),
                     )
                     .as_str();
 
-                self.move_doc_to(
-                    curr_line_final_row,
-                    curr_line.1.len() % editor.doc_disp_width(),
-                );
+                self.move_doc_to(curr_line_final_row, end_col);
+
+                let current_last_vis_row = binding.viewport.bottom();
 
-                let current_last_vis_row = document.borrow().visible_rows.1;
+                // The document is about to be scrolled, so let go of the line that was read from it
+                drop(binding);
 
                 while current_last_vis_row > curr_line_final_row {
                     document.borrow_mut().push_vis_down();
@@ -199,13 +225,20 @@ impl Cursor {
         let curr_line = binding.get_line_at_cursor(self.doc_row);
         let cursor_pos = self.get_position_in_line(editor);
 
+        // The row of the line the cursor is on
+        let row_in_line = if binding.wraps_plainly() {
+            cursor_pos / editor.wrap_width()
+        } else {
+            self.doc_row - curr_line.0[0]
+        };
+
         let mut move_str = String::new();
 
-        if cursor_pos / editor.doc_disp_width() != 0 {
+        if row_in_line != 0 {
             // If the cursor is not in the first row of the line
 
-            if document.borrow().visible_rows.0 < curr_line.0[0]
-                || document.borrow().visible_rows.0 == 0
+            if document.borrow().viewport.top() < curr_line.0[0]
+                || document.borrow().viewport.top() == 0
             {
                 // If the current line's first index is strictly less than the first visible row, or the first visible row is the
                 // first row of the document, i.e. the first row of the current line is visible or the first row of the document is visible
@@ -214,12 +247,7 @@ impl Cursor {
                     .move_to_editor_left(editor.doc_disp_left_edge())
                     .as_str();
 
-                move_str += self
-                    .move_to(
-                        self.row - (cursor_pos / editor.doc_disp_width()),
-                        self.column,
-                    )
-                    .as_str();
+                move_str += self.move_to(self.row - row_in_line, self.column).as_str();
 
                 self.move_doc_to(curr_line.0[0], 0);
             } else {
@@ -229,7 +257,7 @@ impl Cursor {
 
                 self.move_doc_to_editor_left();
 
-                let current_first_vis_row = document.borrow().visible_rows.0;
+                let current_first_vis_row = document.borrow().viewport.top();
 
                 let mut curr_line_first_row = curr_line.0[0];
 
@@ -259,32 +287,48 @@ impl Cursor {
     pub fn move_to_pos(&mut self, new_pos: usize, editor: &Editor) -> String {
         //! visible_range : Expected to be the last visible row minus the first
 
-        // If the new position is 0, just set it to 0, otherwise, the new column will be equal
-        // to the new position mod the editor's width plus 1 (to allow for the cursor to hang
-        // on the right side) plus the editor's left edge to start the counting from within
-        // the editor's window, and then add the "row" to give the necessary bump in movement
+        // The row is found with plain_row_of, which lets the cursor hang off the right side of a
+        // row, the column is what is left of the new position after the rows before it, plus the
+        // editor's left edge to start the counting from within the editor's window
         let document = editor.current_buffer();
 
-        let new_column = if new_pos == 0 {
-            editor.doc_disp_left_edge()
+        let new_column = if !document.borrow().wraps_plainly() {
+            // The rows of the line do not all have the same width, so find the row holding
+            // new_pos and count what is drawn before its text
+            let (first_row, layout, line) = {
+                let document = document.borrow();
+                let line_ind = document.get_index_at_cursor(self.doc_row);
+
+                (
+                    document.first_row_of_line(line_ind),
+                    document.row_layout(line_ind),
+                    document.get_line(line_ind).1,
+                )
+            };
+
+            let row_in_line = layout.row_of(new_pos);
+            let doc_column = new_pos - layout.starts[row_in_line];
+
+            self.move_doc_to(first_row + row_in_line, doc_column);
+
+            editor.doc_disp_left_edge() + layout.column_of(&line, new_pos)
         } else {
-            new_pos % (editor.doc_disp_width() + 1)
-                + editor.doc_disp_left_edge()
-                + (new_pos / (editor.doc_disp_width() + 1))
+            let row_in_line = plain_row_of(new_pos, editor.wrap_width());
+            let doc_column = new_pos - row_in_line * editor.wrap_width();
+
+            // For calculating the cursor's position within the document, use the current line to
+            // grab the first row index in the current line, using this as the starting point
+            // add the calculated row to that index to get the new document row
+            self.move_doc_to(
+                document.borrow().get_line_at_cursor(self.doc_row).0[0] + row_in_line,
+                doc_column,
+            );
+
+            editor.doc_disp_left_edge() + doc_column
         };
 
-        // For calculating the cursor's position within the document, use the current line to
-        // grab the first row index in the current line, using this as the starting point
-        // add the calculated row to that index to get the new document row, then use the
-        // above calculated new column value minus the editor's left edge
-        self.move_doc_to(
-            document.borrow().get_line_at_cursor(self.doc_row).0[0]
-                + (new_pos / (editor.doc_disp_width() + 1)),
-            new_column - editor.doc_disp_left_edge(),
-        );
-
         let new_row =
-            (self.doc_row - document.borrow().visible_rows.0) + editor.doc_disp_home_row();
+            (self.doc_row - document.borrow().viewport.top()) + editor.doc_disp_home_row();
 
         let safe_row = if new_row >= editor.doc_disp_bottom() - 1 {
             editor.doc_disp_bottom() - 1
@@ -329,13 +373,19 @@ impl Cursor {
         // /\ * editor_width : The above times the editor's width will give the amount of spaces to skip given the row of the cursor in relation to the document
         // /\ + doc_column : This will be the position of the cursor within the line
 
-        ((self.doc_row
-            - editor
-                .current_buffer()
-                .borrow()
-                .get_line_at_cursor(self.doc_row)
-                .0[0])
-            * editor.doc_disp_width())
+        let document = editor.current_buffer();
+        let document = document.borrow();
+
+        if !document.wraps_plainly() {
+            // Rows do not all hold the same number of characters, so skip to where the row starts
+            let line_ind = document.get_index_at_cursor(self.doc_row);
+
+            return document.row_layout(line_ind).starts
+                [self.doc_row - document.first_row_of_line(line_ind)]
+                + self.doc_column;
+        }
+
+        ((self.doc_row - document.get_line_at_cursor(self.doc_row).0[0]) * editor.wrap_width())
             + self.doc_column
     }
 
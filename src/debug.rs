@@ -8,22 +8,24 @@ use std::io::Write;
 
 #[allow(dead_code)]
 pub fn debug_log_message(message: impl AsRef<str>, log_file: &mut File) {
-    log_file.write(message.as_ref().as_bytes()).unwrap();
+    log_file.write_all(message.as_ref().as_bytes()).unwrap();
 }
 
 #[allow(dead_code)]
 pub fn debug_log_document(document: &Document, log_file: &mut File) {
-    document.lines.iter().for_each(|l| {
-        log_file
-            .write(format!("Line indices: {:?}, String content: {}\n", l.0, l.1).as_bytes())
-            .unwrap();
-    });
+    (0..document.get_number_lines())
+        .map(|ind| document.get_line(ind))
+        .for_each(|l| {
+            log_file
+                .write_all(format!("Line indices: {:?}, String content: {}\n", l.0, l.1).as_bytes())
+                .unwrap();
+        });
 
     log_file
-        .write(
+        .write_all(
             format!(
                 "Visible lines: {:?}, Number of lines: {}\n",
-                document.visible_rows,
+                document.viewport,
                 document.num_rows()
             )
             .as_bytes(),
@@ -34,7 +36,7 @@ pub fn debug_log_document(document: &Document, log_file: &mut File) {
 #[allow(dead_code)]
 pub fn debug_log_dimensions(dimensions: &Wh, editor_dim: &Editor, log_file: &mut File) {
     log_file
-        .write(
+        .write_all(
             format!(
                 "Terminal width: {}, Terminal height: {}\nEditor bottom: {}, Editor width: {}, Editor height: {}, Mode row: {}, Command row: {}\n",
                 dimensions.width, dimensions.height, editor_dim.doc_disp_bottom(), editor_dim.doc_disp_width(), editor_dim.doc_disp_height(), editor_dim.mode_row(), editor_dim.command_row()
@@ -47,7 +49,7 @@ pub fn debug_log_dimensions(dimensions: &Wh, editor_dim: &Editor, log_file: &mut
 #[allow(dead_code)]
 pub fn debug_log_cursor(cursor: &Cursor, log_file: &mut File) {
     log_file
-        .write(
+        .write_all(
             format!(
                 "Cursor row: {}, Cursor column: {}\nCursor row in doc: {}, Cursor column in doc: {}\n",
                 cursor.row,
@@ -63,6 +65,6 @@ pub fn debug_log_cursor(cursor: &Cursor, log_file: &mut File) {
 #[allow(dead_code)]
 pub fn debug_log_gapbuffer(gap_buf: &GapBuf, log_file: &mut File) {
     log_file
-        .write(format!("Lhs: {:?}, Rhs: {:?}\n", gap_buf.lhs, gap_buf.rhs).as_bytes())
+        .write_all(format!("{:?}\n", gap_buf).as_bytes())
         .unwrap();
 }
@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+
+// Finds how two lists of lines differ, for showing the differences between two buffers with -d.
+//
+// The lines both lists keep in the same order are found with Myers' algorithm, which takes time in
+// proportion to the length of the lists times the number of differences, so lists that are mostly
+// the same are compared quickly, and memory in proportion to the length of the lists alone by way of
+// the linear space variant from the same paper. Lines the two lists start and end with are taken out
+// first.
+
+/// Glyph for a line that is in one list and not the other
+pub const ADDED: char = '+';
+/// Glyph for a line that was changed, it takes the place of lines in the other list
+pub const CHANGED: char = '~';
+/// Glyph for the line under where lines of the other list were left out
+pub const REMOVED: char = '-';
+
+pub fn line_changes<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+) -> (BTreeMap<usize, char>, BTreeMap<usize, char>) {
+    //! The glyph for every line of a and of b that differs, keyed by the index of the line
+
+    let (kept_a, kept_b) = kept(a, b);
+
+    let mut changes = (BTreeMap::new(), BTreeMap::new());
+
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && kept_a[i] && kept_b[j] {
+            i += 1;
+            j += 1;
+
+            continue;
+        }
+
+        // A run of lines that differ on either side, up to the next line both keep
+        let end_a = (i..a.len()).find(|ind| kept_a[*ind]).unwrap_or(a.len());
+        let end_b = (j..b.len()).find(|ind| kept_b[*ind]).unwrap_or(b.len());
+
+        mark_run(&mut changes.0, i..end_a, a.len(), end_b > j);
+        mark_run(&mut changes.1, j..end_b, b.len(), end_a > i);
+
+        (i, j) = (end_a, end_b);
+    }
+
+    changes
+}
+
+fn mark_run(
+    changes: &mut BTreeMap<usize, char>,
+    run: std::ops::Range<usize>,
+    len: usize,
+    other_differs: bool,
+) {
+    //! Puts the glyphs on one side of a run of differing lines, a side without lines in the run has
+    //! a line of the other left out of it
+
+    if run.is_empty() {
+        if len > 0 {
+            changes.entry(run.start.min(len - 1)).or_insert(REMOVED);
+        }
+
+        return;
+    }
+
+    let glyph = if other_differs { CHANGED } else { ADDED };
+
+    for ind in run {
+        changes.insert(ind, glyph);
+    }
+}
+
+fn kept<T: PartialEq>(a: &[T], b: &[T]) -> (Vec<bool>, Vec<bool>) {
+    //! Whether each line of a and of b is one of the lines both of them keep
+
+    let mut kept = (vec![false; a.len()], vec![false; b.len()]);
+
+    keep(a, b, (0, 0), &mut kept);
+
+    kept
+}
+
+fn keep<T: PartialEq>(a: &[T], b: &[T], start: (usize, usize), kept: &mut (Vec<bool>, Vec<bool>)) {
+    //! Marks the lines of a and b that make up a longest list of lines both keep in order, a and b
+    //! being the parts of the whole lists that begin at start
+    //!
+    //! Rather than keeping every step of the search to find the way back, which takes memory in
+    //! proportion to the length of the lists times the number of differences, the search is split
+    //! at a snake in the middle of the way through and both halves are searched again
+
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    for offset in (0..prefix).chain(a.len() - suffix..a.len()) {
+        kept.0[start.0 + offset] = true;
+    }
+
+    for offset in (0..prefix).chain(b.len() - suffix..b.len()) {
+        kept.1[start.1 + offset] = true;
+    }
+
+    let a = &a[prefix..a.len() - suffix];
+    let b = &b[prefix..b.len() - suffix];
+
+    // With the lines at both ends differing, a side being empty leaves nothing else to keep, and
+    // both having lines means there are at least two differences so both halves are smaller
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+
+    let start = (start.0 + prefix, start.1 + prefix);
+
+    let ((x, y), (u, v)) = middle_snake(a, b);
+
+    keep(&a[..x], &b[..y], start, kept);
+
+    for offset in 0..u - x {
+        kept.0[start.0 + x + offset] = true;
+        kept.1[start.1 + y + offset] = true;
+    }
+
+    keep(&a[u..], &b[v..], (start.0 + u, start.1 + v), kept);
+}
+
+fn middle_snake<T: PartialEq>(a: &[T], b: &[T]) -> ((usize, usize), (usize, usize)) {
+    //! Where the run of matching lines in the middle of a shortest way through a and b begins and
+    //! ends, which may be empty
+    //!
+    //! The search goes forwards from the start and backwards from the end at the same time, one
+    //! difference at a time each, until the two meet on a diagonal
+
+    let (n, m) = (a.len() as isize, b.len() as isize);
+
+    // The difference between the diagonal x - y the forward search ends on and the one the
+    // backward search starts on
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+
+    let max = (n + m + 1) / 2;
+
+    // forward[k + offset] is the furthest x reached forwards on diagonal k = x - y, and
+    // backward[c + offset] the furthest y reached backwards on diagonal c = k - delta
+    let offset = max + 1;
+    let mut forward = vec![0isize; 2 * max as usize + 3];
+    let mut backward = vec![0isize; 2 * max as usize + 3];
+
+    backward[offset as usize + 1] = m;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let ind = (k + offset) as usize;
+
+            let mut x = if k == -d || (k != d && forward[ind - 1] < forward[ind + 1]) {
+                forward[ind + 1]
+            } else {
+                forward[ind - 1] + 1
+            };
+
+            let mut y = x - k;
+
+            let snake_start = (x, y);
+
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            forward[ind] = x;
+
+            let c = k - delta;
+
+            if odd && (-(d - 1)..=d - 1).contains(&c) && y >= backward[(c + offset) as usize] {
+                return (
+                    (snake_start.0 as usize, snake_start.1 as usize),
+                    (x as usize, y as usize),
+                );
+            }
+        }
+
+        for c in (-d..=d).step_by(2) {
+            let ind = (c + offset) as usize;
+
+            let mut y = if c == -d || (c != d && backward[ind - 1] > backward[ind + 1]) {
+                backward[ind + 1]
+            } else {
+                backward[ind - 1] - 1
+            };
+
+            let k = c + delta;
+
+            let mut x = y + k;
+
+            let snake_end = (x, y);
+
+            while x > 0 && y > 0 && a[x as usize - 1] == b[y as usize - 1] {
+                x -= 1;
+                y -= 1;
+            }
+
+            backward[ind] = y;
+
+            if !odd && (-d..=d).contains(&k) && x <= forward[(k + offset) as usize] {
+                return (
+                    (x as usize, y as usize),
+                    (snake_end.0 as usize, snake_end.1 as usize),
+                );
+            }
+        }
+    }
+
+    unreachable!("the searches meet within half of the lines")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The glyphs on one side, in the order of the lines
+    type Glyphs = Vec<(usize, char)>;
+
+    fn changes(a: &str, b: &str) -> (Glyphs, Glyphs) {
+        //! The glyphs for two lists of lines given a character to a line
+
+        let a = a.chars().collect::<Vec<_>>();
+        let b = b.chars().collect::<Vec<_>>();
+
+        let (changes_a, changes_b) = line_changes(&a, &b);
+
+        (
+            changes_a.into_iter().collect(),
+            changes_b.into_iter().collect(),
+        )
+    }
+
+    #[test]
+    fn same_lines_have_no_glyphs() {
+        assert_eq!(changes("abc", "abc"), (vec![], vec![]));
+        assert_eq!(changes("", ""), (vec![], vec![]));
+    }
+
+    #[test]
+    fn added_lines_leave_a_removed_glyph_on_the_other_side() {
+        assert_eq!(changes("ac", "abc"), (vec![(1, REMOVED)], vec![(1, ADDED)]));
+        assert_eq!(changes("abc", "ac"), (vec![(1, ADDED)], vec![(1, REMOVED)]));
+    }
+
+    #[test]
+    fn lines_left_out_at_the_end_are_marked_on_the_last_line() {
+        assert_eq!(changes("ab", "a"), (vec![(1, ADDED)], vec![(0, REMOVED)]));
+        assert_eq!(
+            changes("a", "abc"),
+            (vec![(0, REMOVED)], vec![(1, ADDED), (2, ADDED)])
+        );
+    }
+
+    #[test]
+    fn changed_lines_are_marked_on_both_sides() {
+        assert_eq!(
+            changes("abcd", "axyd"),
+            (
+                vec![(1, CHANGED), (2, CHANGED)],
+                vec![(1, CHANGED), (2, CHANGED)]
+            )
+        );
+        assert_eq!(
+            changes("abc", "axyc"),
+            (vec![(1, CHANGED)], vec![(1, CHANGED), (2, CHANGED)])
+        );
+    }
+
+    #[test]
+    fn an_empty_side_has_no_glyphs() {
+        assert_eq!(changes("", "ab"), (vec![], vec![(0, ADDED), (1, ADDED)]));
+        assert_eq!(changes("ab", ""), (vec![(0, ADDED), (1, ADDED)], vec![]));
+    }
+
+    #[test]
+    fn lines_kept_between_differences_are_found() {
+        // Past the lines both start and end with, 3 and 4 are kept in between
+        assert_eq!(
+            changes("12345", "13465"),
+            (
+                vec![(1, ADDED), (4, REMOVED)],
+                vec![(1, REMOVED), (3, ADDED)]
+            )
+        );
+
+        // Myers' own example, where four of the seven lines are kept
+        let (changes_a, changes_b) = changes("abcabba", "cbabac");
+
+        assert_eq!(
+            7 - changes_a.iter().filter(|(_, c)| *c != REMOVED).count(),
+            4
+        );
+        assert_eq!(
+            6 - changes_b.iter().filter(|(_, c)| *c != REMOVED).count(),
+            4
+        );
+    }
+
+    #[test]
+    fn as_many_lines_are_kept_as_the_longest_common_list_has() {
+        //! Checks the kept lines against the longest common subsequence worked out the slow way
+
+        let mut rng = crate::treap::Rng::new(7);
+
+        for _ in 0..500 {
+            let a: Vec<usize> = (0..rng.below(16)).map(|_| rng.below(3)).collect();
+            let b: Vec<usize> = (0..rng.below(16)).map(|_| rng.below(3)).collect();
+
+            let (kept_a, kept_b) = kept(&a, &b);
+
+            let kept_a: Vec<usize> = (0..a.len()).filter(|i| kept_a[*i]).map(|i| a[i]).collect();
+            let kept_b: Vec<usize> = (0..b.len()).filter(|j| kept_b[*j]).map(|j| b[j]).collect();
+
+            assert_eq!(kept_a, kept_b, "{a:?} {b:?}");
+
+            let mut longest = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+            for i in 0..a.len() {
+                for j in 0..b.len() {
+                    longest[i + 1][j + 1] = if a[i] == b[j] {
+                        longest[i][j] + 1
+                    } else {
+                        longest[i][j + 1].max(longest[i + 1][j])
+                    };
+                }
+            }
+
+            assert_eq!(kept_a.len(), longest[a.len()][b.len()], "{a:?} {b:?}");
+        }
+    }
+}
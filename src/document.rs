@@ -1,277 +1,661 @@
+use crate::editorconfig::{IndentStyle, Properties};
+use crate::gapbuf::GapBuf;
+use crate::indent::IndentSettings;
+use crate::mapped::MappedLines;
+use crate::rope::LineRope;
+use crate::row_index::RowIndex;
+use crate::signs::SignTable;
+use crate::storage::TextStorage;
+use crate::viewport::Viewport;
+use crate::wrap::{self, RowLayout, WrapStyle};
+use std::borrow::Cow;
 use std::fmt::Display;
-use std::fs::File;
-use std::io::Read;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
 use std::iter::Iterator;
+use std::path::Path;
 
+/// A line of the document together with the indices of the rows it spans in the editor
+///
+/// Lines are not stored this way, they are put together from the document's storage when asked for
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Line(pub Vec<usize>, pub String);
 
+impl Default for Line {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Line {
     pub fn new() -> Self {
         Self(Vec::new(), "".to_string())
     }
+}
 
-    pub fn from_str(src: String, ind_counter: &mut usize, editor_width: usize) -> Self {
-        //! src is assumed to be a single line string, not containing any new line characters, creation of multiple Lines is to be done outside of this function
-        //! so will to the insertion of indices
-        let mut new = Self(Vec::new(), src.clone());
+pub struct DocRows<'a> {
+    lines: Box<dyn Iterator<Item = Cow<'a, str>> + 'a>,
+    curr: Option<(Cow<'a, str>, RowLayout)>,
+    /// The row of the current line that comes next
+    row_in_line: usize,
+    row: usize,
+    editor_width: usize,
+    style: &'a WrapStyle,
+}
 
-        if src.len() <= editor_width {
-            new.0 = vec![*ind_counter];
+impl<'a> Iterator for DocRows<'a> {
+    type Item = (usize, String);
 
-            *ind_counter += 1;
-        } else {
-            let overflow = if src.len() % editor_width == 0 {
-                (src.len() / editor_width) - 1
-            } else {
-                src.len() / editor_width
-            };
+    fn next(&mut self) -> Option<Self::Item> {
+        //! Lines are only fetched from the storage and cut into rows as the iterator reaches them,
+        //! so only the rows actually asked for are ever computed
 
-            for i in 0..=overflow {
-                new.0.push(*ind_counter + i);
-            }
+        if self.curr.is_none() {
+            let line = self.lines.next()?;
+            let layout = self.style.layout(&line, self.editor_width);
 
-            *ind_counter += overflow;
-            *ind_counter += 1;
+            self.curr = Some((line, layout));
+            self.row_in_line = 0;
         }
 
-        new
-    }
+        let (line, layout) = self.curr.as_ref().unwrap();
 
-    pub fn from_existing(original: Line, editor_width: usize, cursor_row: usize) -> Line {
-        //! cursor_row is provided if the "original" line has not yet received any indices
-        let mut new = Self(Vec::new(), original.1.clone());
+        let res = (self.row, layout.row_display(line, self.row_in_line));
 
-        let mut ind_counter = cursor_row;
+        self.row += 1;
+        self.row_in_line += 1;
 
-        if let Some(index) = original.0.first() {
-            ind_counter = *index;
+        if self.row_in_line >= layout.num_rows() {
+            self.curr = None;
         }
 
-        if original.1.len() <= editor_width {
-            new.0 = vec![ind_counter];
-        } else {
-            let overflow = original.1.len() / editor_width;
+        Some(res)
+    }
+}
 
-            for i in 0..=overflow {
-                new.0.push(ind_counter + i);
-            }
-        }
+fn rows_spanned(len: usize, editor_width: usize) -> usize {
+    //! The number of rows a line of len columns takes up, an empty line still takes up one row
 
-        new
+    if len <= editor_width || editor_width == 0 {
+        1
+    } else {
+        len.div_ceil(editor_width)
     }
+}
 
-    pub fn rows(&self, editor_width: usize) -> Rows {
-        if self.1.len() > 0 {
-            let mut sub_rows: Vec<_> = Vec::new();
+fn line_rows(line: &str, editor_width: usize, style: &WrapStyle) -> usize {
+    //! The number of rows line takes up when it is cut into rows the way style says
 
-            let mut beg: usize = 0;
+    if style.is_plain() && !line.contains(wrap::is_uneven) {
+        rows_spanned(line.len(), editor_width)
+    } else {
+        style.layout(line, editor_width).num_rows()
+    }
+}
 
-            while beg < self.1.len() {
-                if beg + editor_width >= self.1.len() {
-                    sub_rows.push(&self.1[beg..self.1.len()]);
-                } else {
-                    sub_rows.push(&self.1[beg..(beg + editor_width)]);
-                }
+fn gap_line_rows(buf: &GapBuf, editor_width: usize, style: &WrapStyle) -> usize {
+    //! The number of rows the line the gap is on takes up, its text is only put together when the
+    //! rows can not be worked out from its length
 
-                beg += editor_width;
-            }
+    if style.is_plain() && !buf.gap_line_contains(wrap::is_uneven) {
+        rows_spanned(buf.gap_line_len(), editor_width)
+    } else {
+        line_rows(&buf.gap_line_str(), editor_width, style)
+    }
+}
 
-            let mut rows: Vec<(usize, &str)> = Vec::new();
+/// The width lines are cut into rows at while wrapping is off, no line is ever this long so each
+/// line is kept on a single row
+pub const NO_WRAP_WIDTH: usize = usize::MAX / 4;
 
-            for row in self.0.iter().zip(sub_rows.into_iter()) {
-                rows.push((*row.0, row.1));
-            }
+/// Files at least this many bytes big are mapped into memory rather than read into it when they are
+/// opened read only, see Document::open_view
+pub const MAP_THRESHOLD: u64 = 64 * 1024 * 1024;
 
-            Rows::new(Some(rows))
-        } else {
-            Rows::new(Some(vec![(self.0[0], "")]))
+/// How the lines of a file are ended, kept so that a file is written back the way it was read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn detect(text: &[u8]) -> Self {
+        //! Goes by the first line of text, a file with only one line is taken to end lines with \n
+
+        match text.iter().position(|b| *b == b'\n') {
+            Some(end) if end > 0 && text[end - 1] == b'\r' => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        //! The name vim gives the file format
+        match self {
+            LineEnding::Lf => "unix",
+            LineEnding::CrLf => "dos",
         }
     }
 }
 
-pub struct Rows<'a> {
-    rows: Vec<(usize, &'a str)>,
-    curr_ind: usize,
-    curr: Option<(usize, &'a str)>,
+/// The character encoding a file is read and written in, text that cannot be read in it is replaced
+/// rather than refused, and the buffer is marked as lossy so that it is not written back over it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    Latin1,
+    Utf16Be,
+    Utf16Le,
 }
 
-impl<'a> Rows<'a> {
-    pub fn new(opt_rows: Option<Vec<(usize, &'a str)>>) -> Self {
-        if let Some(rows) = opt_rows {
-            let curr = Some(rows[0].clone());
-            Self {
-                rows,
-                curr_ind: 0,
-                curr,
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+impl Charset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        //! The charset going by the name .editorconfig files give it
+
+        Some(match name {
+            "utf-8" => Charset::Utf8,
+            "utf-8-bom" => Charset::Utf8Bom,
+            "latin1" => Charset::Latin1,
+            "utf-16be" => Charset::Utf16Be,
+            "utf-16le" => Charset::Utf16Le,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Charset::Utf8 => "utf-8",
+            Charset::Utf8Bom => "utf-8-bom",
+            Charset::Latin1 => "latin1",
+            Charset::Utf16Be => "utf-16be",
+            Charset::Utf16Le => "utf-16le",
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        //! The byte order mark at the start is left out, except for utf-8 which has none
+
+        match self {
+            Charset::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Charset::Utf8Bom => {
+                String::from_utf8_lossy(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)).into_owned()
             }
-        } else {
-            Self {
-                rows: Vec::new(),
-                curr_ind: 0,
-                curr: None,
+            Charset::Latin1 => bytes.iter().map(|b| *b as char).collect(),
+            Charset::Utf16Be | Charset::Utf16Le => {
+                let units = bytes.chunks_exact(2).map(|pair| match self {
+                    Charset::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+                    _ => u16::from_le_bytes([pair[0], pair[1]]),
+                });
+
+                let text = char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect::<String>();
+
+                text.strip_prefix('\u{FEFF}').unwrap_or(&text).to_string()
             }
         }
     }
-}
 
-impl<'a> Iterator for Rows<'a> {
-    type Item = (usize, &'a str);
+    pub fn decodes(&self, bytes: &[u8]) -> bool {
+        //! Whether decode reads every one of the bytes, rather than replacing some of them
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let res = match &self.curr {
-            Some(item) => Some(item.clone()),
-            None => None,
-        };
-
-        if self.curr_ind + 1 < self.rows.len() {
-            self.curr_ind += 1;
-            self.curr = Some(self.rows[self.curr_ind].clone());
-        } else {
-            self.curr = None;
+        match self {
+            Charset::Utf8 => std::str::from_utf8(bytes).is_ok(),
+            Charset::Utf8Bom => {
+                std::str::from_utf8(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)).is_ok()
+            }
+            Charset::Latin1 => true,
+            Charset::Utf16Be | Charset::Utf16Le => {
+                let units = bytes.chunks_exact(2).map(|pair| match self {
+                    Charset::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+                    _ => u16::from_le_bytes([pair[0], pair[1]]),
+                });
+
+                bytes.len().is_multiple_of(2) && char::decode_utf16(units).all(|c| c.is_ok())
+            }
         }
+    }
 
-        res
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        //! Characters latin1 does not have are written as ?
+
+        match self {
+            Charset::Utf8 => text.as_bytes().to_vec(),
+            Charset::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            Charset::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(c).unwrap_or(b'?'))
+                .collect(),
+            Charset::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            Charset::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        }
     }
 }
 
+// The most lines an edit is allowed to span before it is written back to storage and started again
+// on the current line, keeps the gap buffer small when a lot of text is typed or pasted at once
+const MAX_EDIT_LINES: usize = 64;
+
+/// Lines being edited in insert mode, held in a gap buffer until the edit ends so that each
+/// keystroke only changes the gap buffer instead of the line in storage
+#[derive(Debug)]
+struct ActiveEdit {
+    /// Index of the first line covered by the edit
+    first_line: usize,
+    /// Number of lines in storage the edit takes the place of
+    replaced: usize,
+    buf: GapBuf,
+}
+
+/// Whether a buffer belongs to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BufferKind {
+    /// Read from and written to the file named by file_name
+    #[default]
+    File,
+    /// Has no file yet, it is given one by writing it with :w name or :saveas
+    Unnamed,
+    /// Holds throwaway text that is never counted as changed, :w name writes a copy of it and only
+    /// :saveas gives it a file
+    Scratch,
+}
+
+/// Where the cursor and the view were in a buffer, kept by each window for the buffer it shows and
+/// by each buffer for when it was last switched away from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BufferView {
+    /// The cursor's line and its position in the line
+    pub cursor: (usize, usize),
+    /// The line at the top of the view, along with the number of its rows scrolled up out of view
+    pub top: (usize, usize),
+    /// The columns scrolled sideways while the buffer is not wrapped
+    pub left_col: usize,
+}
+
 #[derive(Debug)]
 pub struct Document {
+    /// Empty for a buffer that does not belong to a file
     pub file_name: String,
-    pub lines: Vec<Line>,
-    pub visible_rows: (usize, usize),
+    pub kind: BufferKind,
+    storage: Box<dyn TextStorage>,
+    /// The number of rows each line spans, in the same order as the lines in storage
+    line_rows: RowIndex,
+    /// The width line_rows was calculated for
+    wrap_width: usize,
+    /// The rows shown in the document display window
+    pub viewport: Viewport,
+    /// Set whenever the contents are changed, cleared when written to disk
+    pub dirty: bool,
+    /// Counts the changes made to the contents, so that what is worked out from them can tell
+    /// whether it has to be worked out again
+    changes: usize,
+    edit: Option<ActiveEdit>,
+    /// Signs placed on the lines, kept in step with the lines as they are added and removed
+    signs: SignTable,
+    /// Whether lines too long for the document display window carry on onto the rows below, or
+    /// stay on one row that is scrolled sideways
+    pub wrap: bool,
+    /// The number of columns the document is scrolled sideways while wrapping is off
+    pub left_col: usize,
+    /// Where the rows of a wrapped line are cut, the rows have to be recalculated after it changes
+    pub wrap_style: WrapStyle,
+    /// Put between the lines when the document is written
+    pub line_ending: LineEnding,
+    /// Where the buffer was left when it was last switched away from
+    pub last_view: BufferView,
+    /// Set when the file cannot be written, or by :view and -R, the buffer is not changed and only
+    /// written to its own file with :w!
+    pub read_only: bool,
+    /// How new lines are indented and how far >> and << shift lines
+    pub indent: IndentSettings,
+    /// The column typed text is carried onto a new line after, 0 for never
+    pub textwidth: usize,
+    /// What the file is read and written in
+    pub charset: Charset,
+    /// Whether the lines are written without the whitespace at their ends
+    pub trim_trailing_whitespace: bool,
+    /// Whether the last line is written with a line ending after it
+    pub final_newline: bool,
+    /// Whether the file held bytes the charset could not read, which were replaced and so would be
+    /// lost by writing the buffer back over the file
+    pub lossy: bool,
+    /// Whether any line may hold a tab or a character of more than one byte, the rows of such a line
+    /// can not be worked out from its length alone
+    has_uneven: bool,
 }
 
 impl Document {
     pub fn new(file_name: &str, editor_dim: (usize, usize)) -> Self {
         //! editor_dim: (height, width)
-        if let Ok(mut src) = File::open(file_name) {
-            let mut curr_ind: usize = 0;
-            let mut lines: Vec<Line> = Vec::new();
 
-            let mut buf = String::new();
-            src.read_to_string(&mut buf).unwrap();
+        let properties = Properties::for_file(file_name);
+        let charset = properties.charset.unwrap_or_default();
 
-            for line in buf.lines() {
-                let new_line = Line::from_str(line.to_string(), &mut curr_ind, editor_dim.1);
+        let mut lines: Vec<String> = Vec::new();
+        let mut line_ending = LineEnding::default();
+        let mut lossy = false;
 
-                lines.push(new_line);
-            }
+        if let Ok(bytes) = fs::read(file_name) {
+            let buf = charset.decode(&bytes);
 
-            return Self {
-                file_name: file_name.to_string(),
-                lines,
-                visible_rows: (0, editor_dim.0),
-            };
-        } else {
-            let mut line = Line::new();
+            lossy = !charset.decodes(&bytes);
+
+            line_ending = LineEnding::detect(buf.as_bytes());
+
+            lines = buf.lines().map(|l| l.to_string()).collect();
+        }
+
+        let has_uneven = lines.iter().any(|line| line.contains(wrap::is_uneven));
 
-            line.0.push(0);
+        let mut new =
+            Self::from_storage(file_name, Box::new(LineRope::from_lines(lines)), editor_dim);
 
-            return Self {
-                file_name: file_name.to_string(),
-                lines: vec![line],
-                visible_rows: (0, editor_dim.0),
-            };
+        new.line_ending = line_ending;
+        new.charset = charset;
+        new.lossy = lossy;
+        new.apply_editorconfig(&properties);
+        new.note_uneven(has_uneven);
+        new.read_only = !is_writable(file_name);
+
+        new
+    }
+
+    pub fn from_stdin(editor_dim: (usize, usize)) -> io::Result<Self> {
+        //! Reads everything piped into the editor into a buffer without a file, text that is not
+        //! valid UTF-8 is replaced rather than refused
+
+        let mut bytes = Vec::new();
+
+        io::stdin().lock().read_to_end(&mut bytes)?;
+
+        let text = String::from_utf8_lossy(&bytes);
+
+        let lines = text.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        let has_uneven = text.contains(wrap::is_uneven);
+
+        let mut new = Self::from_storage("", Box::new(LineRope::from_lines(lines)), editor_dim);
+
+        new.note_uneven(has_uneven);
+
+        new.kind = BufferKind::Unnamed;
+        new.line_ending = LineEnding::detect(&bytes);
+
+        Ok(new)
+    }
+
+    pub fn open_view(file_name: &str, editor_dim: (usize, usize)) -> Self {
+        //! Opens the file read only, files of MAP_THRESHOLD bytes or more are mapped into memory
+        //! through open_mapped so that they are shown straight away however big they are
+
+        if fs::metadata(file_name).is_ok_and(|m| m.len() >= MAP_THRESHOLD) {
+            if let Ok(mut new) = Self::open_mapped(file_name, editor_dim) {
+                new.apply_editorconfig(&Properties::for_file(file_name));
+
+                return new;
+            }
         }
+
+        let mut new = Self::new(file_name, editor_dim);
+
+        new.read_only = true;
+
+        new
+    }
+
+    pub fn open_mapped(file_name: &str, editor_dim: (usize, usize)) -> io::Result<Self> {
+        //! Opens the file as a read only view mapped into memory, its lines are indexed in the
+        //! background and show up as poll_indexing is called
+
+        let storage = MappedLines::open(file_name)?;
+
+        let mut new = Self::from_storage(file_name, Box::new(storage), editor_dim);
+
+        // The first line is all that is needed to tell how lines end, there is no need to wait for
+        // the whole file to be indexed
+        let mut head = Vec::new();
+        File::open(file_name)?
+            .take(64 * 1024)
+            .read_to_end(&mut head)?;
+
+        new.line_ending = LineEnding::detect(&head);
+
+        // Looking for uneven characters would mean reading the whole file, so the lines are taken to
+        // have them. Their rows are estimated from their lengths, and a line is only read for them
+        // once it is laid out
+        new.note_uneven(true);
+
+        Ok(new)
+    }
+
+    pub fn new_unnamed(doc_disp_height: usize) -> Self {
+        //! An empty buffer without a file
+        Self::new_empty(BufferKind::Unnamed, doc_disp_height)
     }
 
     pub fn new_scratch(doc_disp_height: usize) -> Self {
-        let mut line = Line::new();
+        Self::new_empty(BufferKind::Scratch, doc_disp_height)
+    }
+
+    fn new_empty(kind: BufferKind, doc_disp_height: usize) -> Self {
+        let mut new = Self::from_storage("", Box::new(LineRope::new()), (doc_disp_height, 0));
+
+        new.kind = kind;
+
+        new
+    }
+
+    fn from_storage(
+        file_name: &str,
+        storage: Box<dyn TextStorage>,
+        editor_dim: (usize, usize),
+    ) -> Self {
+        let mut new = Self {
+            file_name: file_name.to_string(),
+            kind: BufferKind::File,
+            storage,
+            line_rows: RowIndex::new(),
+            wrap_width: editor_dim.1,
+            viewport: Viewport::with_height(0, editor_dim.0),
+            dirty: false,
+            changes: 0,
+            edit: None,
+            signs: SignTable::new(),
+            wrap: true,
+            left_col: 0,
+            wrap_style: WrapStyle::default(),
+            line_ending: LineEnding::default(),
+            last_view: BufferView::default(),
+            read_only: false,
+            indent: IndentSettings::default(),
+            textwidth: 0,
+            charset: Charset::default(),
+            trim_trailing_whitespace: false,
+            final_newline: false,
+            lossy: false,
+            has_uneven: false,
+        };
+
+        new.recalculate_indices(editor_dim.1);
+
+        new
+    }
+
+    fn apply_editorconfig(&mut self, properties: &Properties) {
+        //! Takes on the settings the .editorconfig files above the file give it, its charset is
+        //! left to the one reading the file
+
+        if let Some(style) = properties.indent_style {
+            self.indent.expandtab = style == IndentStyle::Space;
+        }
+
+        if let Some(width) = properties.indent_width() {
+            self.indent.shiftwidth = width;
+            self.indent.softtabstop = width;
+        }
+
+        if let Some(tabstop) = properties.tab_width() {
+            self.indent.tabstop = tabstop;
+
+            if tabstop != self.wrap_style.tabstop {
+                self.wrap_style.tabstop = tabstop;
+
+                self.recalculate_indices(self.wrap_width);
+            }
+        }
+
+        if let Some(line_ending) = properties.end_of_line {
+            self.line_ending = line_ending;
+        }
 
-        line.0.push(0);
+        if let Some(trim) = properties.trim_trailing_whitespace {
+            self.trim_trailing_whitespace = trim;
+        }
 
-        Self {
-            file_name: "scratch".to_string(),
-            lines: vec![line],
-            visible_rows: (0, doc_disp_height),
+        if let Some(final_newline) = properties.insert_final_newline {
+            self.final_newline = final_newline;
+        }
+
+        if let Some(length) = properties.max_line_length {
+            self.textwidth = length;
         }
     }
 
-    pub fn get_str_at_cursor(&self, cursor_doc_row: usize) -> &str {
+    pub fn get_str_at_cursor(&self, cursor_doc_row: usize) -> String {
         //! Returns the string content of the line which is located at the cursor's row relative to the document
 
-        &self.lines[self.get_index_at_cursor(cursor_doc_row)].1
+        self.line(self.get_index_at_cursor(cursor_doc_row))
+            .into_owned()
     }
 
-    pub fn get_line_at_cursor(&self, cursor_doc_row: usize) -> &Line {
+    pub fn get_line_at_cursor(&self, cursor_doc_row: usize) -> Line {
         //! Returns the entire line which is located at the cursor's row relative to the document
 
-        &self.lines[self.get_index_at_cursor(cursor_doc_row)]
+        self.get_line(self.get_index_at_cursor(cursor_doc_row))
+    }
+
+    pub fn get_line(&self, line_ind: usize) -> Line {
+        //! Puts together the line at line_ind along with the rows it spans
+
+        let first_row = self.first_row_of_line(line_ind);
+
+        Line(
+            (first_row..first_row + self.line_rows.rows_of(line_ind)).collect(),
+            self.line(line_ind).into_owned(),
+        )
     }
 
     pub fn get_index_at_cursor(&self, cursor_doc_row: usize) -> usize {
-        //! Returns the index of the line within the Document's line vector which is located at the cursor's row
-        //! relative to the document
+        //! Returns the index of the line within the Document's storage which is located at the cursor's row
+        //! relative to the document, rows past the end of the document give the last line
 
-        // Beginning of the search area
-        let mut beg = 0;
-        // End of the search area
-        let mut end = self.lines.len() - 1;
+        self.line_rows.line_at_row(cursor_doc_row)
+    }
 
-        let mut mid = beg + ((end - beg) / 2);
+    pub fn first_row_of_line(&self, line_ind: usize) -> usize {
+        //! The row index at which the line at line_ind begins
 
-        // While the beginning is not equal to the end (the search area is 0)
-        while beg != end {
-            // mid is the index current element being compared
+        self.line_rows.first_row_of(line_ind)
+    }
 
-            if *self.lines[mid].0.first().unwrap() > cursor_doc_row {
-                // If the cursor's row in the document is less than the first row index of this element
+    pub fn line_row_count(&self, line_ind: usize) -> usize {
+        //! The number of rows the line at line_ind spans
 
-                // Make the current element the last element in the area
-                end = mid - 1;
-            } else if *self.lines[mid].0.last().unwrap() < cursor_doc_row {
-                // If the cursor's row in the document is greater than the last row index of this element
+        self.line_rows.rows_of(line_ind)
+    }
 
-                // Make the current elemenent thte last element in the area
-                beg = mid + 1;
-            } else {
-                // The cursor's row in the document is within the rows spanned by the current element
+    pub fn view_at(&self, line_ind: usize, column: Option<usize>) -> BufferView {
+        //! A view with the cursor on the line, at the column counted in characters from 1, both
+        //! kept within the buffer
 
-                // Return this element's index
-                break;
-            }
+        let line_ind = line_ind.min(self.get_number_lines() - 1);
+
+        let line = self.line(line_ind);
+
+        let pos = column.map_or(0, |column| {
+            line.char_indices()
+                .nth(column.saturating_sub(1))
+                .map_or(line.len(), |(pos, _)| pos)
+        });
 
-            mid = beg + ((end - beg) / 2);
+        BufferView {
+            cursor: (line_ind, pos),
+            ..BufferView::default()
         }
+    }
+
+    pub fn clamp_view(&self, view: BufferView) -> BufferView {
+        //! Keeps the cursor and the top of the view within the buffer, which may have shrunk since
+        //! the view was stored
+
+        let last_line = self.get_number_lines() - 1;
 
-        mid
+        let line_ind = view.cursor.0.min(last_line);
+        let top_line = view.top.0.min(last_line);
+
+        BufferView {
+            cursor: (line_ind, view.cursor.1.min(self.get_line(line_ind).1.len())),
+            top: (top_line, view.top.1.min(self.line_row_count(top_line) - 1)),
+            left_col: view.left_col,
+        }
     }
 
-    pub fn set_line_at_cursor(
-        &mut self,
-        cursor_doc_row: usize,
-        new_str: String,
-        editor_width: usize,
-    ) {
-        //! Sets the line's string value at cursor_row to new_line and recalculates the line's indices
-        //! as well as the following lines according to the editor_width
+    pub fn row_layout(&self, line_ind: usize) -> RowLayout {
+        //! Where each of the rows the line at line_ind spans starts
 
-        let line_ind = self.get_index_at_cursor(cursor_doc_row);
+        self.wrap_style
+            .layout(&self.line(line_ind), self.wrap_width)
+    }
 
-        self.lines[line_ind].1 = new_str;
+    pub fn wraps_plainly(&self) -> bool {
+        //! Whether the rows of every line start a whole number of widths into it and every column
+        //! holds a byte of the line, as they do unless the lines are wrapped in the style of
+        //! linebreak, showbreak or breakindent or hold tabs or characters of more than one byte
 
-        self.recalculate_indices(editor_width);
+        (!self.wrap || self.wrap_style.is_plain()) && !self.has_uneven
     }
 
-    pub fn append_to_line(&mut self, cursor_doc_row: usize, suffix: &str, editor_width: usize) {
-        let line_ind = self.get_index_at_cursor(cursor_doc_row);
+    fn note_uneven(&mut self, has_uneven: bool) {
+        //! Marks the document as holding uneven characters once it does, from then on its rows are
+        //! always worked out from the text of its lines
 
-        self.lines[line_ind].1 += suffix;
+        if has_uneven && !self.has_uneven {
+            self.has_uneven = true;
 
-        self.recalculate_indices(editor_width);
+            // Only plainly wrapped rows were counted from the lengths of the lines as they are
+            if self.wrap_style.is_plain() {
+                self.recalculate_indices(self.wrap_width);
+            }
+        }
     }
 
-    pub fn to_string(&self) -> String {
-        self.lines
-            .iter()
-            .map(|e| e.1.clone())
-            .collect::<Vec<String>>()
-            .join("\n")
+    pub fn wrap_width(&self, doc_disp_width: usize) -> usize {
+        //! The width lines are cut into rows at when shown doc_disp_width columns wide
+        if self.wrap {
+            doc_disp_width
+        } else {
+            NO_WRAP_WIDTH
+        }
     }
 
     pub fn get_number_lines(&self) -> usize {
-        self.lines.len()
+        match &self.edit {
+            Some(edit) => self.storage.len_lines() - edit.replaced + edit.buf.line_count(),
+            None => self.storage.len_lines(),
+        }
     }
 
     pub fn num_rows(&self) -> usize {
@@ -282,124 +666,606 @@ impl Document {
         //! editor.
         //! The number of rows is similar to getting the length of a line
 
-        match self.lines.last() {
-            Some(line) => {
-                if line.0.len() > 1 {
-                    line.0[line.0.len() - 1] + 1
-                } else {
-                    line.0[0] + 1
-                }
-            }
-            None => 0,
-        }
+        self.line_rows.total()
     }
 
-    pub fn num_above_rows(&self, editor_width: usize, cursor_doc_row: usize) -> usize {
-        self.rows(editor_width)
-            .take_while(|row| row.0 != cursor_doc_row)
-            .count()
+    pub fn last_row(&self) -> usize {
+        //! The index of the last row of the document
+
+        self.num_rows() - 1
+    }
+
+    pub fn num_above_rows(&self, cursor_doc_row: usize) -> usize {
+        cursor_doc_row.min(self.num_rows())
     }
 
     pub fn remove_line_from_doc(&mut self, cursor_doc_row: usize, editor_width: usize) {
-        self.lines.remove(self.get_index_at_cursor(cursor_doc_row));
+        self.end_edit();
+        self.check_wrap_width(editor_width);
 
-        self.recalculate_indices(editor_width);
+        let line_ind = self.get_index_at_cursor(cursor_doc_row);
+
+        self.storage.remove_line(line_ind);
+
+        // The storage empties the only line rather than removing it, so that the cursor always has
+        // a line to be on
+        if self.storage.len_lines() < self.line_rows.len() {
+            self.line_rows.remove(line_ind);
+        } else {
+            self.line_rows.set(line_ind, 1);
+        }
+
+        self.signs.line_removed(line_ind);
+
+        self.mark_changed();
     }
 
-    pub fn add_line_at_row(&mut self, new_line: Line, cursor_doc_row: usize) {
-        let mut insert_ind = 0;
+    pub fn replace_line(&mut self, line_ind: usize, new_line: String) {
+        //! Puts new_line in the place of the line at line_ind, the buffer is only marked as changed
+        //! if the line is different
 
-        for line in self.lines.iter() {
-            if line.0.contains(&(cursor_doc_row)) {
-                break;
-            }
+        self.end_edit();
 
-            insert_ind += 1;
+        if self.storage.line(line_ind) == new_line {
+            return;
         }
 
-        self.lines.insert(insert_ind, new_line);
+        self.note_uneven(new_line.contains(wrap::is_uneven));
 
-        // insert_ind will now be the position that the new line was inserted, so to iterate over the elements after it, add 1
-        for i in (insert_ind + 1)..self.lines.len() {
-            self.lines[i].0 = self.lines[i].0.iter().map(|inds| inds + 1).collect();
-        }
+        self.line_rows.set(
+            line_ind,
+            line_rows(&new_line, self.wrap_width, &self.wrap_style),
+        );
+
+        self.storage.set_line(line_ind, new_line);
+
+        self.mark_changed();
+    }
+
+    pub fn add_line_at_row(&mut self, new_line: String, cursor_doc_row: usize) {
+        //! Inserts new_line so that it begins at cursor_doc_row, pushing the line already there down,
+        //! a row past the end of the document appends the line instead
+
+        self.end_edit();
+
+        let insert_ind = if cursor_doc_row < self.num_rows() {
+            self.get_index_at_cursor(cursor_doc_row)
+        } else {
+            self.get_number_lines()
+        };
+
+        self.note_uneven(new_line.contains(wrap::is_uneven));
+
+        let rows = line_rows(&new_line, self.wrap_width, &self.wrap_style);
+
+        self.storage.insert_line(insert_ind, new_line);
+
+        self.line_rows.insert(insert_ind, rows);
+
+        self.signs.lines_inserted(insert_ind, 1);
+
+        self.mark_changed();
     }
 
     pub fn add_scratch_line(&mut self) {
         //! This function is to be used to add a new line when there are no lines at all present in the document
         //! Possibly generalized in coming while
-        let mut blank = Line::new();
-
-        blank.0.push(0);
 
-        self.lines.push(blank);
+        self.add_line_at_row(String::new(), self.num_rows());
     }
 
     pub fn recalculate_indices(&mut self, editor_width: usize) {
-        let mut ind_counter = 0;
+        //! Recounts the rows spanned by every line, only needed when the width of the editor changes
+        //!
+        //! The rows are counted from the lengths of the lines. Unless that is exactly what they
+        //! span, the counts are only kept as estimates, and each line is laid out by settle_rows
+        //! once it is needed, rather than every line of the document being laid out up front
 
-        for i in 0..self.lines.len() {
-            if self.lines[i].1.len() <= editor_width {
-                self.lines[i].0 = vec![ind_counter];
+        self.wrap_width = editor_width;
 
-                ind_counter += 1;
-            } else {
-                let overflow = if self.lines[i].1.len() % editor_width == 0 {
-                    (self.lines[i].1.len() / editor_width) - 1
-                } else {
-                    self.lines[i].1.len() / editor_width
-                };
+        let counts = self
+            .line_lens_from(0)
+            .map(|len| rows_spanned(len, editor_width))
+            .collect();
+
+        self.line_rows = if self.wrap_style.is_plain() && !self.has_uneven {
+            RowIndex::from_counts(counts)
+        } else {
+            RowIndex::from_estimates(counts)
+        };
+    }
+
+    pub fn has_estimated_rows(&self) -> bool {
+        //! Whether the rows of some line are only estimated, see recalculate_indices
+        self.line_rows.has_estimates()
+    }
 
-                let mut new_inds = Vec::new();
+    pub fn settle_rows(&mut self, line_ind: usize, num_rows: usize) -> bool {
+        //! Lays out the lines from line_ind on whose rows were only estimated, until the lines
+        //! looked at span num_rows rows, returns whether any of them spans other rows than estimated
+        //!
+        //! The rows of every line after a line that changed move along with it, the viewport is
+        //! kept on the same row of the line at its top
 
-                for j in 0..=overflow {
-                    new_inds.push(ind_counter + j);
-                }
+        let top = self.viewport.top();
+        let top_line = self.get_index_at_cursor(top);
+        let top_in_line = top - self.first_row_of_line(top_line);
 
-                self.lines[i].0 = new_inds;
+        let mut changed = false;
+        let mut rows = 0;
 
-                ind_counter += overflow;
-                ind_counter += 1;
+        for ind in line_ind..self.get_number_lines() {
+            if rows >= num_rows {
+                break;
+            }
+
+            if self.line_rows.is_estimated(ind) {
+                let estimate = self.line_rows.rows_of(ind);
+                let exact = line_rows(&self.line(ind), self.wrap_width, &self.wrap_style);
+
+                self.line_rows.set(ind, exact);
+
+                changed |= exact != estimate;
             }
+
+            rows += self.line_rows.rows_of(ind);
+        }
+
+        if changed {
+            let top = self.first_row_of_line(top_line)
+                + top_in_line.min(self.line_row_count(top_line) - 1);
+
+            self.viewport.shift_to(top);
+        }
+
+        changed
+    }
+
+    pub fn check_wrap_width(&mut self, editor_width: usize) {
+        //! Recounts the rows spanned by every line if they were counted for another width
+        if editor_width != self.wrap_width {
+            self.recalculate_indices(editor_width);
         }
     }
 
-    pub fn rows(&self, editor_width: usize) -> Rows {
-        let mut rows: Vec<(usize, &str)> = Vec::new();
+    pub fn rows(&self, editor_width: usize) -> DocRows<'_> {
+        self.rows_from(0, editor_width)
+    }
+
+    pub fn rows_from(&self, first_row: usize, editor_width: usize) -> DocRows<'_> {
+        //! Iterates over the rows of the document starting from first_row, the rows are cut from the
+        //! lines lazily so only the ones taken are worked out
+
+        let line_ind = self.get_index_at_cursor(first_row);
+        let skipped = first_row.saturating_sub(self.first_row_of_line(line_ind));
+
+        let mut lines = self.lines_from(line_ind);
+
+        let curr = lines
+            .next()
+            .map(|line| {
+                let layout = self.wrap_style.layout(&line, editor_width);
+                (line, layout)
+            })
+            .filter(|(_, layout)| skipped < layout.num_rows());
+
+        DocRows {
+            lines,
+            curr,
+            row_in_line: skipped,
+            row: first_row,
+            editor_width,
+            style: &self.wrap_style,
+        }
+    }
 
-        if self.lines.len() > 0 {
-            for line in self.lines.iter() {
-                if line.1.len() > 0 {
-                    // If line is not empty, this guard needs to be here due to a graphical bug I encountered
+    pub fn display_name(&self) -> &str {
+        //! The name the buffer is shown by, buffers without a file are named after their kind
 
-                    let mut sub_rows: Vec<_> = Vec::new();
+        match self.kind {
+            BufferKind::File => &self.file_name,
+            BufferKind::Unnamed => "[No Name]",
+            BufferKind::Scratch => "[Scratch]",
+        }
+    }
 
-                    let mut beg: usize = 0;
+    fn mark_changed(&mut self) {
+        self.dirty = true;
+        self.changes += 1;
+    }
 
-                    while beg < line.1.len() {
-                        if beg + editor_width >= line.1.len() {
-                            sub_rows.push(&line.1[beg..line.1.len()]);
-                        } else {
-                            sub_rows.push(&line.1[beg..(beg + editor_width)]);
-                        }
+    pub fn changes(&self) -> usize {
+        //! The number of changes made to the contents since the buffer was opened
+        self.changes
+    }
 
-                        beg += editor_width;
-                    }
+    pub fn is_modified(&self) -> bool {
+        //! Whether the buffer has changes that would be lost without writing it, never true for a
+        //! scratch buffer
+        self.dirty && self.kind != BufferKind::Scratch
+    }
 
-                    line.0
-                        .iter()
-                        .zip(sub_rows.into_iter())
-                        .map(|e| (*e.0, e.1))
-                        .for_each(|e| rows.push(e));
-                } else {
-                    // If line is empty
+    pub fn flags(&self) -> String {
+        //! The markers put after the buffer's name, [+] for changes that have not been written and
+        //! [RO] for a read only buffer
 
-                    rows.push((line.0[0], ""));
+        format!(
+            "{}{}",
+            if self.is_modified() { " [+]" } else { "" },
+            if self.is_read_only() { " [RO]" } else { "" },
+        )
+    }
+
+    pub fn set_file(&mut self, file_name: &str) {
+        //! Makes the buffer belong to the file, after it has been written there
+
+        self.file_name = file_name.to_string();
+        self.kind = BufferKind::File;
+        self.dirty = false;
+    }
+
+    pub fn file_type(&self) -> &'static str {
+        //! The kind of file the document is, worked out from the extension of its name, empty when
+        //! it is not known
+
+        let extension = Path::new(&self.file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("");
+
+        match extension {
+            "rs" => "rust",
+            "c" | "h" => "c",
+            "cc" | "cpp" | "cxx" | "hh" | "hpp" => "cpp",
+            "py" => "python",
+            "js" => "javascript",
+            "ts" => "typescript",
+            "go" => "go",
+            "java" => "java",
+            "sh" | "bash" => "sh",
+            "md" | "markdown" => "markdown",
+            "toml" => "toml",
+            "json" => "json",
+            "yaml" | "yml" => "yaml",
+            "html" | "htm" => "html",
+            "css" => "css",
+            "txt" => "text",
+            _ => "",
+        }
+    }
+
+    // ==================== READ ONLY VIEWS ====================
+
+    pub fn is_read_only(&self) -> bool {
+        //! Whether the buffer cannot be changed, because it was marked read only or because its
+        //! lines are a view of the file
+        self.read_only || self.storage.is_read_only()
+    }
+
+    pub fn is_view_of_file(&self) -> bool {
+        //! Whether the lines are read from the file itself, which then must not be written over
+        self.storage.is_read_only()
+    }
+
+    pub fn poll_indexing(&mut self) -> usize {
+        //! Adds the lines the storage has loaded in the background since the last call, returns the
+        //! number of lines added
+        //!
+        //! Only the lengths of the lines are read, unless they tell exactly how many rows a line
+        //! spans the rows are estimated and the line is laid out once it comes into view
+
+        let first_new = self.storage.len_lines();
+        let added = self.storage.poll_lines();
+
+        let exact = self.wrap_style.is_plain() && !self.has_uneven;
+
+        for (offset, len) in self.storage.line_lens_from(first_new).enumerate() {
+            let rows = rows_spanned(len, self.wrap_width);
+
+            if exact {
+                self.line_rows.insert(first_new + offset, rows);
+            } else {
+                self.line_rows.insert_estimate(first_new + offset, rows);
+            }
+        }
+
+        added
+    }
+
+    pub fn index_progress(&self) -> Option<usize> {
+        //! Percentage of the file indexed so far, None once it is done or if it was never needed
+
+        self.storage.index_progress()
+    }
+
+    // ==================== SEARCH ====================
+
+    pub fn find(&self, pattern: &str, line_ind: usize, pos: usize) -> Option<(usize, usize)> {
+        //! Finds the first match of pattern at or after pos in the line at line_ind, wrapping
+        //! around to the start of the document, returns the line index and position of the match
+        //!
+        //! Lines are looked at one by one so the document is never collected into a single string
+
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let first_line = self.line(line_ind);
+
+        if let Some(found) = first_line.get(pos..).and_then(|rest| rest.find(pattern)) {
+            return Some((line_ind, pos + found));
+        }
+
+        let after = self
+            .lines_from(line_ind + 1)
+            .enumerate()
+            .map(|(offset, line)| (line_ind + 1 + offset, line));
+
+        let before = self.lines_from(0).take(line_ind + 1).enumerate();
+
+        after
+            .chain(before)
+            .find_map(|(ind, line)| line.find(pattern).map(|found| (ind, found)))
+    }
+
+    // ==================== SIGNS ====================
+
+    pub fn signs(&self) -> &SignTable {
+        &self.signs
+    }
+
+    pub fn signs_mut(&mut self) -> &mut SignTable {
+        //! Signs placed through here follow their lines as the document is edited
+        &mut self.signs
+    }
+
+    // ==================== EDITING IN INSERT MODE ====================
+
+    pub fn begin_edit(&mut self, cursor_doc_row: usize, pos: usize) {
+        //! Starts editing the line at the cursor's row with the gap placed at pos, any edit already
+        //! going on is written back first
+
+        self.end_edit();
+
+        self.begin_edit_at(self.get_index_at_cursor(cursor_doc_row), pos);
+    }
+
+    fn begin_edit_at(&mut self, line_ind: usize, pos: usize) {
+        let buf = GapBuf::from_str(self.storage.line(line_ind).into_owned(), pos);
+
+        self.edit = Some(ActiveEdit {
+            first_line: line_ind,
+            replaced: 1,
+            buf,
+        });
+    }
+
+    pub fn end_edit(&mut self) {
+        //! Writes the lines held in the gap buffer back into storage
+
+        if let Some(edit) = self.edit.take() {
+            let mut lines = edit.buf.lines().into_iter();
+
+            for ind in edit.first_line..(edit.first_line + edit.replaced) {
+                match lines.next() {
+                    Some(line) => self.storage.set_line(ind, line),
+                    None => {
+                        self.storage
+                            .remove_line(edit.first_line + edit.buf.line_count());
+                    }
                 }
             }
-            Rows::new(Some(rows))
+
+            for (offset, line) in lines.enumerate() {
+                self.storage
+                    .insert_line(edit.first_line + edit.replaced + offset, line);
+            }
+        }
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.edit.is_some()
+    }
+
+    pub fn edit_insert(&mut self, c: char) {
+        //! Inserts c at the gap of the current edit, a new line character splits the line in two
+
+        let width = self.wrap_width;
+
+        if self.edit.is_none() {
+            return;
+        }
+
+        self.mark_changed();
+
+        self.note_uneven(wrap::is_uneven(c));
+
+        let edit = self.edit.as_mut().unwrap();
+
+        edit.buf.insert(c);
+
+        let gap_line = edit.first_line + edit.buf.gap_line();
+
+        if c == '\n' {
+            // The line before the gap is now a line of its own
+            let prev_line = edit.buf.line(edit.buf.gap_line() - 1);
+
+            self.line_rows
+                .set(gap_line - 1, line_rows(&prev_line, width, &self.wrap_style));
+            self.line_rows
+                .insert(gap_line, gap_line_rows(&edit.buf, width, &self.wrap_style));
+
+            // Splitting a line at its start pushes all of its text down, so its signs go with it
+            if prev_line.is_empty() {
+                self.signs.lines_inserted(gap_line - 1, 1);
+            } else {
+                self.signs.lines_inserted(gap_line, 1);
+            }
+        } else {
+            self.line_rows
+                .set(gap_line, gap_line_rows(&edit.buf, width, &self.wrap_style));
+        }
+
+        if c == '\n' && edit.buf.line_count() > MAX_EDIT_LINES {
+            // Keep only the line being typed on in the gap buffer
+            let pos = edit.buf.gap_line_pieces().0.len();
+
+            self.end_edit();
+            self.begin_edit_at(gap_line, pos);
+        }
+    }
+
+    pub fn edit_insert_str(&mut self, s: &str) {
+        s.chars().for_each(|c| self.edit_insert(c));
+    }
+
+    pub fn edit_pop(&mut self) {
+        //! Removes the character before the gap of the current edit, at the start of the line this
+        //! joins the line onto the end of the line above it
+
+        let width = self.wrap_width;
+
+        let Some(edit) = self.edit.as_ref() else {
+            return;
+        };
+
+        if edit.buf.gap_pos() == 0 {
+            if edit.first_line == 0 {
+                return;
+            }
+
+            // Take the line above into the edit so that the new line character between the two
+            // lines sits just before the gap
+            let prev_line = self.storage.line(edit.first_line - 1).into_owned();
+
+            let edit = self.edit.as_mut().unwrap();
+
+            edit.buf.move_gap(0);
+            edit.buf.insert_str(&prev_line);
+            edit.buf.insert('\n');
+
+            edit.first_line -= 1;
+            edit.replaced += 1;
+        }
+
+        let edit = self.edit.as_mut().unwrap();
+
+        if edit.buf.pop() == Some('\n') {
+            let gap_line = edit.first_line + edit.buf.gap_line();
+
+            self.line_rows.remove(gap_line + 1);
+            self.line_rows
+                .set(gap_line, gap_line_rows(&edit.buf, width, &self.wrap_style));
+
+            self.signs.lines_joined(gap_line);
         } else {
-            Rows::new(None)
+            let gap_line = edit.first_line + edit.buf.gap_line();
+
+            self.line_rows
+                .set(gap_line, gap_line_rows(&edit.buf, width, &self.wrap_style));
+        }
+
+        self.mark_changed();
+    }
+
+    // ==================== LINE ACCESS ====================
+
+    fn line(&self, line_ind: usize) -> Cow<'_, str> {
+        //! The content of the line at line_ind, taken from the gap buffer if it is being edited
+
+        match &self.edit {
+            Some(edit) if line_ind < edit.first_line => self.storage.line(line_ind),
+            Some(edit) if line_ind < edit.first_line + edit.buf.line_count() => {
+                Cow::Owned(edit.buf.line(line_ind - edit.first_line))
+            }
+            Some(edit) => self
+                .storage
+                .line(line_ind - edit.buf.line_count() + edit.replaced),
+            None => self.storage.line(line_ind),
+        }
+    }
+
+    pub fn file_contents(&self) -> Vec<u8> {
+        //! What the buffer is written to a file as, in its charset and with its line endings
+
+        let mut text = String::new();
+
+        for (ind, line) in self.lines_from(0).enumerate() {
+            if ind != 0 {
+                text.push_str(self.line_ending.as_str());
+            }
+
+            text.push_str(if self.trim_trailing_whitespace {
+                line.trim_end()
+            } else {
+                &line
+            });
+        }
+
+        // An empty buffer is written as an empty file
+        if self.final_newline && !text.is_empty() {
+            text.push_str(self.line_ending.as_str());
+        }
+
+        self.charset.encode(&text)
+    }
+
+    pub fn lines_from(&self, line_ind: usize) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        //! Iterates over the lines starting at line_ind, going through the gap buffer for the lines
+        //! being edited
+
+        match &self.edit {
+            Some(edit) => {
+                let edit_end = edit.first_line + edit.buf.line_count();
+
+                let before = self
+                    .storage
+                    .lines_from(line_ind.min(edit.first_line))
+                    .take(edit.first_line.saturating_sub(line_ind));
+
+                let during = edit
+                    .buf
+                    .lines()
+                    .into_iter()
+                    .skip(line_ind.saturating_sub(edit.first_line))
+                    .map(Cow::Owned);
+
+                let after = self.storage.lines_from(
+                    line_ind.max(edit_end) - edit_end + edit.first_line + edit.replaced,
+                );
+
+                Box::new(before.chain(during).chain(after))
+            }
+            None => self.storage.lines_from(line_ind),
+        }
+    }
+
+    fn line_lens_from(&self, line_ind: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        //! Like lines_from for the lengths of the lines in bytes, which storage keeps track of so
+        //! the lines are not built to measure them
+
+        match &self.edit {
+            Some(edit) => {
+                let edit_end = edit.first_line + edit.buf.line_count();
+
+                let before = self
+                    .storage
+                    .line_lens_from(line_ind.min(edit.first_line))
+                    .take(edit.first_line.saturating_sub(line_ind));
+
+                let during = edit
+                    .buf
+                    .line_lens()
+                    .into_iter()
+                    .skip(line_ind.saturating_sub(edit.first_line));
+
+                let after = self.storage.line_lens_from(
+                    line_ind.max(edit_end) - edit_end + edit.first_line + edit.replaced,
+                );
+
+                Box::new(before.chain(during).chain(after))
+            }
+            None => self.storage.line_lens_from(line_ind),
         }
     }
 
@@ -407,37 +1273,35 @@ impl Document {
         //! Manipulate the visible rows of the document in such a way as to give the appearance of
         //! pushing the view down
 
-        if self.visible_rows.1 < self.num_rows() {
-            self.visible_rows.0 += 1;
-            self.visible_rows.1 += 1;
-        } else if self.visible_rows.1 == self.num_rows() {
-            self.visible_rows.0 += 1;
-        }
+        let num_rows = self.num_rows();
+
+        self.viewport.push_down(num_rows);
     }
 
     pub fn push_vis_up(&mut self, doc_disp_height: usize) {
         //! Manipulate the visible rows of the document in such a way as to give the appearance of
         //! pushing the view up
 
-        if doc_disp_height > (self.visible_rows.1 - self.visible_rows.0) {
-            self.visible_rows.0 -= 1;
-        } else if self.visible_rows.0 > 0 {
-            self.visible_rows.0 -= 1;
-            self.visible_rows.1 -= 1;
-        }
+        self.viewport.push_up(doc_disp_height);
     }
 }
 
 impl Display for Document {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            self.lines
-                .iter()
-                .map(|e| e.1.clone())
-                .collect::<Vec<String>>()
-                .join("\n")
-        )
+        for (ind, line) in self.lines_from(0).enumerate() {
+            if ind != 0 {
+                write!(f, "{}", self.line_ending.as_str())?;
+            }
+
+            write!(f, "{}", line)?;
+        }
+
+        Ok(())
     }
 }
+
+fn is_writable(file_name: &str) -> bool {
+    //! Whether the file can be opened for writing, a file that is not there yet can be created
+
+    !Path::new(file_name).exists() || OpenOptions::new().write(true).open(file_name).is_ok()
+}
@@ -1,13 +1,33 @@
+use crate::args::{Args, ConfigFile, FileArg};
+use crate::gutter::{Gutter, LineNumbers};
+use crate::indent::{self, IndentSettings};
+use crate::message::{Level, Message, MessageHistory};
+use crate::screen::{Clip, Screen, ScrollWindow};
+use crate::signs::{
+    self, Bookmarks, DiffChanges, GitChanges, PlacedSign, SearchHits, SignProvider, SignProviderId,
+    SIGN_COLUMN_WIDTH,
+};
+use crate::status::{Segment, StatusLayout};
+use crate::tab::TabPages;
 use crate::term::{
-    get_char, kbhit, return_to_normal_buf, set_cooked, set_raw, switch_to_alt_buf, term_size, Wh,
+    cursor_shape_bar, cursor_shape_block, cursor_shape_default, get_char, kbhit,
+    return_to_normal_buf, set_cooked, set_raw, supports_synchronized_output, switch_to_alt_buf,
+    term_size, use_controlling_terminal, Wh,
 };
 use crate::term_color::{Theme, ThemeBuilder};
+use crate::viewport::Align;
+use crate::window::{Direction, Heading, Rect, Separator, Window, WindowId, Windows};
+use crate::wrap::{self, WrapStyle};
 use crate::{cursor::*, document::*};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::env;
-use std::fs::File;
-use std::io::{self, BufWriter, Stdout, Write};
-use std::rc::Rc;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Stdout, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process;
+use std::rc::{Rc, Weak};
 use std::sync::mpsc::{self, Receiver};
 use std::thread;
 
@@ -19,6 +39,29 @@ pub enum Modes {
     Insert,
     Command,
     MoveTo,
+    Search,
+}
+
+impl Modes {
+    pub fn name(&self) -> &'static str {
+        //! How the mode is shown in the status line
+        match self {
+            Modes::Normal => "NOR",
+            Modes::Insert => "INS",
+            Modes::Command => "COM",
+            Modes::MoveTo => "MOV",
+            Modes::Search => "SCH",
+        }
+    }
+
+    pub fn cursor_shape(&self) -> &'static str {
+        //! A block over the character in Normal mode, a bar between characters wherever text is
+        //! being typed
+        match self {
+            Modes::Normal | Modes::MoveTo => cursor_shape_block(),
+            Modes::Insert | Modes::Command | Modes::Search => cursor_shape_bar(),
+        }
+    }
 }
 
 pub struct Editor {
@@ -35,17 +78,72 @@ pub struct Editor {
     /// The buffer for user entered commands
     pub command_buf: RefCell<String>,
     writer: RefCell<Cursor>,
-    draw_buffer: RefCell<BufWriter<Stdout>>,
+    /// Everything drawn goes through here, only the cells that changed since the last flush are
+    /// sent to the terminal
+    draw_buffer: RefCell<Screen<BufWriter<Stdout>>>,
     file_buffers: Vec<Rc<RefCell<Document>>>,
-    active_buffer: usize,
+    /// The tab pages, each with the windows the buffers are shown in, the current buffer is the one
+    /// in the active window of the current tab page
+    tabs: TabPages,
+    /// The pattern last searched for, searched for again with n, shared with the search hits
+    /// sign provider
+    last_search: Rc<RefCell<String>>,
+    /// Whether the editor is in Insert mode, shared with the diff sign provider
+    inserting: Rc<Cell<bool>>,
+    /// Draws line numbers to the left of the document
+    gutter: Gutter,
+    /// The width of the gutter as last drawn, only changed through update_gutter so that the
+    /// document display window stays put while a key is being handled
+    gutter_width: Cell<usize>,
+    /// The line the cursor was on when the gutter was last drawn
+    gutter_cursor_line: Cell<usize>,
+    /// Everything that can put signs in the sign column, a SignProviderId is an index into this
+    sign_providers: Vec<Box<dyn SignProvider>>,
+    /// The provider the letters placed with m belong to
+    bookmarks: SignProviderId,
+    /// The width of the sign column as last drawn, zero while no signs are shown
+    sign_width: Cell<usize>,
+    /// The part of the screen scrolled sideways while the current buffer is not wrapped
+    scroll_window: Cell<Option<ScrollWindow>>,
+    /// The fewest rows kept in view above and below the cursor's row while scrolling
+    scrolloff: usize,
+    /// What the status line shows and where
+    status_layout: StatusLayout,
+    /// The keys typed so far of a command that is waiting for its next key
+    pending_keys: RefCell<String>,
+    /// Every message shown so far, listed again by :messages
+    messages: RefCell<MessageHistory>,
+    /// The buffer shown before the current one, Ctrl-^ goes back to it
+    alternate_buffer: Weak<RefCell<Document>>,
+    /// Set by -R, no buffer can be changed or written and q quits like in a pager
+    view_mode: bool,
+    /// What each window other than the active one was last drawn from, see update_windows
+    drawn_windows: RefCell<BTreeMap<WindowId, WindowLook>>,
+}
+
+/// Everything a window that is not active is drawn from, it is only drawn again once some of it
+/// changes
+#[derive(PartialEq)]
+struct WindowLook {
+    rect: Rect,
+    view: BufferView,
+    buffer: *const RefCell<Document>,
+    changes: usize,
+    num_lines: usize,
+    wrap: bool,
+    wrap_style: WrapStyle,
+    line_numbers: LineNumbers,
+    has_placed_signs: bool,
+    signs: BTreeMap<usize, (u32, char)>,
 }
 
 impl Editor {
-    pub fn new(left_edge_offset: usize, right_edge_offset: usize) -> Self {
+    pub fn new(left_edge_offset: usize, right_edge_offset: usize, args: &Args) -> Self {
         //! left_edge_offset - The index of the column at which the document will start
         //! to be displayed in the document display window
         //! right_edge_offset - The amount of spaces from the right side of the terminal
         //! that the document will be displayed
+        //! args - The command line, its files are opened in buffers
 
         let theme = ThemeBuilder::new()
             .title_line("31;35;53")
@@ -53,29 +151,87 @@ impl Editor {
             .font_accents("169;177;214")
             .font_body("122;162;247")
             .editor_background("36;40;59")
+            .gutter_font("59;66;97")
+            .gutter_current_font("169;177;214")
+            .gutter_background("36;40;59")
+            .sign_font("224;175;104")
+            .info_font("169;177;214")
+            .warning_font("224;175;104")
+            .error_font("247;118;142")
             .build();
 
+        let view_mode = args.view;
+        let mut input_files = args.files.clone();
+
+        // A pager is given what to show on stdin without being told to read it
+        let read_stdin = input_files.iter().any(|file| file.name == "-")
+            || (view_mode && input_files.is_empty() && !io::stdin().is_terminal());
+
+        if read_stdin {
+            if let Err(e) = use_controlling_terminal() {
+                eprintln!("textchen: cannot open the terminal: {e}");
+                process::exit(1);
+            }
+
+            if input_files.is_empty() {
+                input_files.push(FileArg {
+                    name: "-".to_string(),
+                    position: None,
+                });
+            }
+        }
+
         let dimensions = term_size();
 
-        let mut input_files = env::args().skip(1);
+        let editor_dim = (
+            dimensions.height - 3,
+            (dimensions.width - right_edge_offset) - left_edge_offset,
+        );
 
         let mut file_buffers = Vec::new();
 
-        while let Some(file_name) = input_files.next() {
-            file_buffers.push(Rc::new(RefCell::new(Document::new(
-                &file_name,
-                (
-                    dimensions.height - 3,
-                    (dimensions.width - right_edge_offset) - left_edge_offset,
-                ),
-            ))));
-        }
+        for file in input_files {
+            let mut document = if file.name == "-" {
+                Document::from_stdin(editor_dim).unwrap_or_else(|e| {
+                    eprintln!("textchen: cannot read stdin: {e}");
+                    process::exit(1);
+                })
+            } else if view_mode {
+                Document::open_view(&file.name, editor_dim)
+            } else {
+                Document::new(&file.name, editor_dim)
+            };
+
+            if let Some(position) = file.position {
+                document.last_view = document.view_at(position.line - 1, position.column);
+            }
 
-        if file_buffers.len() == 0 {
-            Rc::new(RefCell::new(Document::new_scratch(dimensions.height - 3)));
+            // -R opens every file read only, files opened later are not
+            document.read_only |= view_mode;
+
+            file_buffers.push(Rc::new(RefCell::new(document)));
         }
 
-        Self {
+        let first_buffer = match file_buffers.first() {
+            Some(buffer) => Rc::clone(buffer),
+            None => {
+                let buffer = Rc::new(RefCell::new(Document::new_unnamed(dimensions.height - 3)));
+
+                file_buffers.push(Rc::clone(&buffer));
+
+                buffer
+            }
+        };
+
+        let last_search = Rc::new(RefCell::new(String::new()));
+
+        let draw_buffer = Screen::new(
+            BufWriter::new(io::stdout()),
+            dimensions.width,
+            dimensions.height,
+        );
+
+        let mut new = Self {
             left_edge_offset,
             right_edge_offset,
             curr_mode: Modes::Normal,
@@ -83,11 +239,53 @@ impl Editor {
             theme,
             command_buf: RefCell::new(String::new()),
             term_dimensions: dimensions,
-            draw_buffer: RefCell::new(BufWriter::new(io::stdout())),
+            draw_buffer: RefCell::new(draw_buffer),
             writer: RefCell::new(Cursor::new()),
             file_buffers,
-            active_buffer: 0,
+            tabs: TabPages::new(Windows::new(Rc::clone(&first_buffer))),
+            last_search: Rc::clone(&last_search),
+            inserting: Rc::new(Cell::new(false)),
+            gutter: Gutter::new(),
+            gutter_width: Cell::new(0),
+            gutter_cursor_line: Cell::new(0),
+            sign_providers: Vec::new(),
+            bookmarks: SignProviderId(0),
+            sign_width: Cell::new(0),
+            scroll_window: Cell::new(None),
+            scrolloff: 0,
+            status_layout: StatusLayout::default(),
+            pending_keys: RefCell::new(String::new()),
+            messages: RefCell::new(MessageHistory::new()),
+            alternate_buffer: Weak::new(),
+            view_mode,
+            drawn_windows: RefCell::new(BTreeMap::new()),
+        };
+
+        new.windows_mut().active_mut().view = first_buffer.borrow().last_view;
+
+        new.arrange_windows();
+
+        new.bookmarks = new.register_sign_provider(Box::new(Bookmarks));
+        new.register_sign_provider(Box::new(SearchHits {
+            pattern: last_search,
+        }));
+
+        let inserting = Rc::clone(&new.inserting);
+
+        // Both mark changed lines, so with -d the changes against the other buffer take the place
+        // of the changes since the last commit
+        if args.diff {
+            let buffers = (
+                Rc::clone(&new.file_buffers[0]),
+                Rc::clone(&new.file_buffers[1]),
+            );
+
+            new.register_sign_provider(Box::new(DiffChanges::new(buffers, inserting)));
+        } else {
+            new.register_sign_provider(Box::new(GitChanges::new(inserting)));
         }
+
+        new
     }
 
     // ==================== DISPLAY METHODS FOR EDITOR ====================
@@ -108,7 +306,7 @@ impl Editor {
         self.add_to_draw_buf(format!("{}{}", color.as_ref(), message.as_ref()));
     }
 
-    fn print_title(&self) {
+    pub fn print_title(&self) {
         let document = Rc::clone(&self.current_buffer());
 
         self.save_cursor_vis_pos();
@@ -117,9 +315,27 @@ impl Editor {
 
         self.apply_line_color(self.theme.title_line_color());
 
+        if self.tabs.len() > 1 {
+            self.print_tab_bar();
+
+            self.apply_reset_color();
+
+            self.revert_cursor_vis_pos();
+
+            return;
+        }
+
         self.print_text_w_color(
             self.theme.title_text_color(),
-            format!(" {}", &document.borrow().file_name),
+            format!(
+                " {}{}{}",
+                document.borrow().display_name(),
+                document.borrow().flags(),
+                match document.borrow().index_progress() {
+                    Some(percent) => format!(" [indexing {percent}%]"),
+                    None => String::new(),
+                }
+            ),
         );
 
         self.apply_reset_color();
@@ -127,25 +343,52 @@ impl Editor {
         self.revert_cursor_vis_pos();
     }
 
-    fn print_mode_row(&self) {
+    pub fn print_status_line(&self) {
+        //! Draws the row under the document display window, laid out by the statusline option
+
+        let document = Rc::clone(&self.current_buffer());
+
+        let line_ind = self.get_cursor_line_ind();
+        let pos = self.get_cursor_pos_in_line();
+
+        let status = self
+            .status_layout
+            .render(self.term_dimensions.width, |segment| {
+                let document = document.borrow();
+
+                match segment {
+                    Segment::Mode => self.curr_mode.name().to_string(),
+                    Segment::FileName => document.display_name().to_string(),
+                    Segment::Dirty => if document.is_modified() { "[+]" } else { "" }.to_string(),
+                    Segment::Position => {
+                        let column = document.indent.width(&document.get_line(line_ind).1[..pos]);
+
+                        // Where tabs are drawn wider than they are, the column they are drawn at
+                        // is shown after the position in the line
+                        if column == pos {
+                            format!("{}:{}", line_ind + 1, pos + 1)
+                        } else {
+                            format!("{}:{}-{}", line_ind + 1, pos + 1, column + 1)
+                        }
+                    }
+                    Segment::Percent => format!(
+                        "{}%",
+                        (line_ind + 1) * 100 / document.get_number_lines().max(1)
+                    ),
+                    Segment::FileType => document.file_type().to_string(),
+                    Segment::Encoding => document.charset.name().to_string(),
+                    Segment::LineEnding => document.line_ending.name().to_string(),
+                    Segment::PendingKeys => self.pending_keys.borrow().clone(),
+                }
+            });
+
         self.save_cursor_vis_pos();
 
         self.move_cursor_vis_to(self.mode_row(), 0);
 
         self.apply_line_color(self.theme.mode_line_color());
 
-        self.print_text_w_color(
-            self.theme.title_text_color(),
-            format!(
-                " {}",
-                match self.curr_mode {
-                    Modes::Normal => "NOR",
-                    Modes::Insert => "INS",
-                    Modes::Command => "COM",
-                    Modes::MoveTo => "MOV",
-                }
-            ),
-        );
+        self.print_text_w_color(self.theme.title_text_color(), status);
 
         self.apply_reset_color();
 
@@ -165,24 +408,26 @@ impl Editor {
     }
 
     fn print_document(&self) {
+        self.settle_rows_in_view();
+
         let document = Rc::clone(&self.current_buffer());
 
         self.save_cursor_vis_pos();
 
-        self.move_cursor_vis_to(2, self.doc_disp_left_edge());
+        self.move_cursor_vis_to(self.doc_disp_home_row(), self.doc_disp_left_edge());
 
         self.apply_line_color(self.theme.background_color());
 
-        if document.borrow().visible_rows.0 == 0
-            && document.borrow().visible_rows.1 < self.doc_disp_bottom()
+        if document.borrow().viewport.top() == 0
+            && document.borrow().viewport.bottom() < self.doc_disp_bottom()
         {
             // Number of lines in document does not exceed editor height
             for row in document
                 .borrow()
-                .rows(self.doc_disp_width())
-                .take(document.borrow().visible_rows.1)
+                .rows(self.wrap_width())
+                .take(document.borrow().viewport.bottom())
             {
-                self.print_text_w_color(self.theme.body_text_color(), row.1);
+                self.print_doc_row(self.theme.body_text_color(), &row.1);
 
                 self.move_cursor_vis_down();
                 self.move_cursor_vis_editor_left();
@@ -196,11 +441,10 @@ impl Editor {
             // Number of lines in document does exceed editor height
             for row in document
                 .borrow()
-                .rows(self.doc_disp_width())
-                .skip(document.borrow().visible_rows.0)
-                .take(document.borrow().visible_rows.1 - document.borrow().visible_rows.0)
+                .rows_from(document.borrow().viewport.top(), self.wrap_width())
+                .take(document.borrow().viewport.bottom() - document.borrow().viewport.top())
             {
-                self.print_text_w_color(self.theme.body_text_color(), row.1);
+                self.print_doc_row(self.theme.body_text_color(), &row.1);
 
                 self.move_cursor_vis_down();
                 self.move_cursor_vis_editor_left();
@@ -210,6 +454,206 @@ impl Editor {
         self.apply_reset_color();
 
         self.revert_cursor_vis_pos();
+
+        self.print_gutter();
+    }
+
+    fn print_doc_row(&self, color: impl AsRef<str>, row: &str) {
+        //! Prints a row of the document from the cursor onwards, while wrapping is off only the
+        //! part of the row scrolled into view is printed, at the column it is drawn at before
+        //! scrolling
+
+        let (wrap, left_col) = {
+            let document = self.current_buffer();
+            let document = document.borrow();
+            (document.wrap, document.left_col)
+        };
+
+        if wrap {
+            self.print_text_w_color(color, row);
+            return;
+        }
+
+        let len = row.chars().count();
+        let start = left_col.min(len);
+        let end = (left_col + self.doc_disp_width()).min(len);
+
+        self.move_cursor_vis_to(self.get_cursor_vis_row(), self.doc_disp_left_edge() + start);
+
+        self.print_text_w_color(color, wrap::columns(row, start..end));
+    }
+
+    fn print_gutter(&self) {
+        //! Draws the signs and line numbers for every row of the document display window
+
+        let width = self.gutter_width.get();
+        let sign_width = self.sign_width.get();
+
+        if width + sign_width == 0 {
+            return;
+        }
+
+        let binding = Rc::clone(&self.current_buffer());
+
+        let document = binding.borrow();
+
+        let cursor_line = document.get_index_at_cursor(self.get_cursor_doc_row());
+
+        self.gutter_cursor_line.set(cursor_line);
+
+        let signs = if sign_width > 0 {
+            self.signs_in_view(&document)
+        } else {
+            BTreeMap::new()
+        };
+
+        self.save_cursor_vis_pos();
+
+        let (top, bottom) = (document.viewport.top(), document.viewport.bottom());
+
+        for (offset, row) in (top..bottom).take(self.doc_disp_height()).enumerate() {
+            // Rows past the end of the document and rows a line wrapped onto get no number
+            let line_ind = Some(document.get_index_at_cursor(row))
+                .filter(|ind| row < document.num_rows() && document.first_row_of_line(*ind) == row);
+
+            let color = if line_ind == Some(cursor_line) {
+                self.theme.gutter_current_text_color()
+            } else {
+                self.theme.gutter_text_color()
+            };
+
+            self.move_cursor_vis_to(self.doc_disp_home_row() + offset, self.active_rect().left);
+
+            if sign_width > 0 {
+                let glyph = line_ind
+                    .and_then(|ind| signs.get(&ind))
+                    .map_or(' ', |(_, glyph)| *glyph);
+
+                self.print_text_w_color(
+                    self.theme.sign_text_color(),
+                    format!("{glyph:<sign_width$}"),
+                );
+            }
+
+            if width > 0 {
+                self.print_text_w_color(color, self.gutter.label(line_ind, cursor_line, width));
+            }
+        }
+
+        self.apply_reset_color();
+
+        self.revert_cursor_vis_pos();
+    }
+
+    pub fn update_gutter(&self) {
+        //! Redraws whatever the gutter needs after the document or the cursor changed, if the
+        //! gutter changed width the whole screen is redrawn around the document's new position
+
+        let document = self.current_buffer();
+
+        let new_width = self.gutter.width(document.borrow().get_number_lines());
+        let new_sign_width = self.sign_column_width(&document.borrow());
+
+        if new_width != self.gutter_width.get() || new_sign_width != self.sign_width.get() {
+            let line_ind = document
+                .borrow()
+                .get_index_at_cursor(self.get_cursor_doc_row());
+            let pos = self.get_cursor_pos_in_line();
+
+            self.gutter_width.set(new_width);
+            self.sign_width.set(new_sign_width);
+
+            self.redraw_screen();
+
+            self.move_cursor_to_line(line_ind, pos);
+        } else if self.gutter.line_numbers() != LineNumbers::Off
+            && self.gutter.line_numbers() != LineNumbers::Absolute
+            && document
+                .borrow()
+                .get_index_at_cursor(self.get_cursor_doc_row())
+                != self.gutter_cursor_line.get()
+        {
+            // Relative numbers are counted from the cursor's line
+            self.print_gutter();
+        }
+    }
+
+    pub fn update_horizontal_scroll(&self) {
+        //! While the current buffer is not wrapped, scrolls it sideways so that the cursor stays
+        //! within the document display window, redrawing the document if the scroll changed
+
+        let document = self.current_buffer();
+
+        let window = if document.borrow().wrap {
+            None
+        } else {
+            let column = self.get_cursor_column_in_doc_disp();
+            let width = self.doc_disp_width();
+
+            let mut document = document.borrow_mut();
+
+            if column < document.left_col {
+                document.left_col = column;
+            } else if column >= document.left_col + width {
+                document.left_col = column + 1 - width;
+            }
+
+            Some(ScrollWindow {
+                top: self.doc_disp_home_row() - 1,
+                bottom: self.doc_disp_bottom() - 1,
+                left: self.doc_disp_left_edge() - 1,
+                width,
+                offset: document.left_col,
+            })
+        };
+
+        if window != self.scroll_window.get() {
+            self.scroll_window.set(window);
+
+            self.draw_buffer.borrow_mut().set_scroll_window(window);
+
+            self.reset_editor_view();
+        }
+    }
+
+    pub fn scroll_sideways_to(&self, left_col: usize) {
+        //! Scrolls the current buffer so that left_col is the first column shown, moving the cursor
+        //! along if it would be scrolled out of view, does nothing while the buffer is wrapped
+        //!
+        //! The buffer is not scrolled past the end of the cursor's line
+
+        let document = self.current_buffer();
+
+        if document.borrow().wrap {
+            return;
+        }
+
+        let line_ind = self.get_cursor_line_ind();
+
+        let (line, layout) = {
+            let document = document.borrow();
+
+            (document.get_line(line_ind).1, document.row_layout(line_ind))
+        };
+
+        let line_width = layout.column_of(&line, line.len());
+
+        let left_col = left_col.min(line_width);
+
+        document.borrow_mut().left_col = left_col;
+
+        let column = self.get_cursor_column_in_doc_disp();
+        let new_column = column
+            .clamp(left_col, left_col + self.doc_disp_width() - 1)
+            .min(line_width);
+
+        if new_column != column {
+            self.move_cursor_to_pos(layout.pos_at_column(&line, 0, new_column));
+        }
+    }
+
+    pub fn left_col(&self) -> usize {
+        self.current_buffer().borrow().left_col
     }
 
     pub fn print_line(&self) {
@@ -219,9 +663,11 @@ impl Editor {
 
         let document = binding.borrow();
 
-        let curr_line_rows: Vec<(usize, &str)> = document
-            .get_line_at_cursor(self.get_cursor_doc_row())
-            .rows(self.doc_disp_width())
+        let curr_line = document.get_line_at_cursor(self.get_cursor_doc_row());
+
+        let curr_line_rows: Vec<(usize, String)> = document
+            .rows_from(curr_line.0[0], self.wrap_width())
+            .take(curr_line.0.len())
             .collect();
 
         // Get the row number of the first row in the Line, subtract the cursor's position
@@ -238,9 +684,9 @@ impl Editor {
 
         self.save_cursor_vis_pos();
 
-        self.apply_line_color(self.theme.background_color());
-
+        // Clear every row of the line, words moved between the rows can leave any of them shorter
         for _ in 0..curr_line_rows.len() {
+            self.apply_line_color(self.theme.background_color());
             self.move_cursor_vis_down();
         }
 
@@ -249,8 +695,9 @@ impl Editor {
         self.save_cursor_vis_pos();
 
         for (_, s) in curr_line_rows {
-            self.print_text_w_color(self.theme.command_text_color(), s);
+            self.print_doc_row(self.theme.command_text_color(), &s);
             self.move_cursor_vis_down();
+            self.move_cursor_vis_editor_left();
         }
 
         self.revert_cursor_vis_pos();
@@ -258,14 +705,20 @@ impl Editor {
         self.revert_cursor_vis_pos();
 
         self.apply_reset_color();
+
+        // Clearing the line wiped out its number
+        self.print_gutter();
     }
 
     fn initialize_display(&self) {
+        self.drawn_windows.borrow_mut().clear();
+
         self.add_to_draw_buf(switch_to_alt_buf());
         self.clear_doc_disp_window();
+        self.update_windows();
         self.print_title();
         self.print_document();
-        self.print_mode_row();
+        self.print_status_line();
         self.print_command_row();
         self.move_cursor_vis_to(self.doc_disp_home_row(), self.doc_disp_left_edge());
         self.flush_pen();
@@ -273,7 +726,16 @@ impl Editor {
 
     pub fn initialize(&mut self) {
         #[cfg(target_os = "linux")]
-        set_raw();
+        {
+            set_raw();
+
+            // Asked before any input is read so the answer is not taken for key presses
+            self.draw_buffer
+                .borrow_mut()
+                .set_synchronized(supports_synchronized_output());
+        }
+
+        self.add_to_draw_buf(self.curr_mode.cursor_shape());
 
         self.initialize_display();
     }
@@ -290,7 +752,7 @@ impl Editor {
 
         self.save_cursor_vis_pos();
 
-        self.move_cursor_vis_to(2, 1);
+        self.move_cursor_vis_to(self.doc_disp_home_row(), self.active_rect().left);
 
         for _ in 0..self.doc_disp_height() {
             self.clear_line(self.theme.background_color());
@@ -325,24 +787,105 @@ impl Editor {
                 }
                 self.print_text_w_color(self.theme.command_text_color(), c.to_string());
             }
+            Modes::Search => {
+                self.command_buf.borrow_mut().push(c);
+                self.print_text_w_color(self.theme.command_text_color(), c.to_string());
+            }
             Modes::Normal | Modes::MoveTo => unreachable!("Not scientifically possible!"),
         }
     }
 
     pub fn redraw_screen(&self) {
-        //! dimensions - The new dimensions of the terminal screen after resize
-        //! self - The old dimensions of the editor screen
+        //! Works the rows out again for the size of the document display window and draws the
+        //! whole screen, the cursor is kept on the same character and the same row of the same line
+        //! is kept at the top
 
         let document = Rc::clone(&self.current_buffer());
 
-        document
-            .borrow_mut()
-            .recalculate_indices(self.doc_disp_width());
+        let line_ind = self.get_cursor_line_ind();
+        let pos = self.get_cursor_pos_in_line();
+        let (top_line, top_in_line) = {
+            let document = document.borrow();
+            let top = document.viewport.top();
+            let top_line = document.get_index_at_cursor(top);
 
-        document.borrow_mut().visible_rows.1 =
-            document.borrow().visible_rows.0 + self.doc_disp_height();
+            (top_line, top - document.first_row_of_line(top_line))
+        };
+
+        let wrap_width = self.wrap_width();
+
+        document.borrow_mut().recalculate_indices(wrap_width);
+
+        {
+            let mut document = document.borrow_mut();
+
+            let top = document.first_row_of_line(top_line)
+                + top_in_line.min(document.line_row_count(top_line) - 1);
+
+            document.viewport.scroll_to(top, self.doc_disp_height());
+        }
+
+        self.move_cursor_doc_to(document.borrow().first_row_of_line(line_ind), 0);
+        self.move_cursor_to_pos(pos);
 
         self.initialize_display();
+
+        self.move_cursor_into_view(line_ind, pos);
+    }
+
+    fn settle_rows_in_view(&self) {
+        //! Lays out the cursor's line and the lines in view whose rows were only estimated, see
+        //! Document::recalculate_indices, keeping the cursor on the same character when the rows
+        //! before it move
+        //!
+        //! Should the cursor be moved out of view, the document is scrolled after it, which brings
+        //! other lines into view to be laid out in turn
+
+        let document = self.current_buffer();
+
+        if !document.borrow().has_estimated_rows() {
+            return;
+        }
+
+        let line_ind = self.get_cursor_line_ind();
+        let pos = self.get_cursor_pos_in_line();
+
+        loop {
+            let changed = {
+                let mut document = document.borrow_mut();
+
+                let top = document.viewport.top();
+                let top_line = document.get_index_at_cursor(top);
+                let num_rows = top - document.first_row_of_line(top_line) + self.doc_disp_height();
+
+                document.settle_rows(line_ind, 1) | document.settle_rows(top_line, num_rows)
+            };
+
+            if !changed {
+                return;
+            }
+
+            self.move_cursor_doc_to(document.borrow().first_row_of_line(line_ind), 0);
+            self.move_cursor_to_pos(pos);
+
+            let scrolled = {
+                let mut document = document.borrow_mut();
+                let num_rows = document.num_rows();
+
+                document.viewport.follow(
+                    self.get_cursor_doc_row(),
+                    0,
+                    self.doc_disp_height(),
+                    num_rows,
+                )
+            };
+
+            if !scrolled {
+                return;
+            }
+
+            self.move_cursor_to_pos(pos);
+        }
     }
 
     // -------------------- PRINT BUFFER MANIPULATION ---------------------
@@ -350,7 +893,7 @@ impl Editor {
     pub fn add_to_draw_buf<S: AsRef<str>>(&self, content: S) {
         self.draw_buffer
             .borrow_mut()
-            .write(content.as_ref().as_bytes())
+            .write_all(content.as_ref().as_bytes())
             .unwrap();
     }
 
@@ -382,6 +925,41 @@ impl Editor {
         self.writer.borrow().doc_column
     }
 
+    pub fn get_cursor_line_ind(&self) -> usize {
+        //! The index of the line the cursor is on
+        self.current_buffer()
+            .borrow()
+            .get_index_at_cursor(self.get_cursor_doc_row())
+    }
+
+    pub fn pos_before_cursor(&self) -> Option<usize> {
+        //! The position in the cursor's line of the character just before the cursor, None at the
+        //! start of the line
+
+        let line = self
+            .current_buffer()
+            .borrow()
+            .get_line(self.get_cursor_line_ind())
+            .1;
+        let pos = self.get_cursor_pos_in_line().min(line.len());
+
+        line[..pos].chars().next_back().map(|c| pos - c.len_utf8())
+    }
+
+    pub fn pos_after_cursor(&self) -> Option<usize> {
+        //! The position in the cursor's line just after the character the cursor is on, None when
+        //! the cursor is past the end of the line
+
+        let line = self
+            .current_buffer()
+            .borrow()
+            .get_line(self.get_cursor_line_ind())
+            .1;
+        let pos = self.get_cursor_pos_in_line();
+
+        line.get(pos..)?.chars().next().map(|c| pos + c.len_utf8())
+    }
+
     // -------------------- CURSOR HISTORY MANIPULATION -------------------
 
     pub fn save_cursor_vis_pos(&self) {
@@ -421,22 +999,6 @@ impl Editor {
             .move_doc_to(new_doc_row, new_doc_col);
     }
 
-    pub fn move_cursor_down(&self) {
-        self.add_to_draw_buf(self.writer.borrow_mut().move_down());
-    }
-
-    pub fn move_cursor_up(&self) {
-        self.add_to_draw_buf(self.writer.borrow_mut().move_up());
-    }
-
-    pub fn move_cursor_right(&self) {
-        self.add_to_draw_buf(self.writer.borrow_mut().move_right());
-    }
-
-    pub fn move_cursor_left(&self) {
-        self.add_to_draw_buf(self.writer.borrow_mut().move_left());
-    }
-
     pub fn move_cursor_vis_down(&self) {
         self.add_to_draw_buf(self.writer.borrow_mut().move_vis_down());
     }
@@ -461,24 +1023,10 @@ impl Editor {
         self.writer.borrow_mut().move_doc_up()
     }
 
-    pub fn move_cursor_doc_right(&self) {
-        self.writer.borrow_mut().move_doc_right()
-    }
-
     pub fn move_cursor_doc_left(&self) {
         self.writer.borrow_mut().move_doc_left()
     }
 
-    pub fn move_cursor_doc_to_editor_right(&self) {
-        self.writer.borrow_mut().doc_column = self.doc_disp_width();
-    }
-
-    pub fn move_cursor_vis_to_editor_right(&self) {
-        let curr_row = self.get_cursor_vis_row();
-
-        self.move_cursor_vis_to(curr_row, self.doc_disp_right_edge());
-    }
-
     pub fn move_cursor_vis_editor_left(&self) {
         self.add_to_draw_buf(
             self.writer
@@ -491,77 +1039,207 @@ impl Editor {
         self.writer.borrow_mut().move_doc_to_editor_left();
     }
 
-    pub fn multi_row_bump(&self) {
-        //! cursor_pos : This position is the position before having moved the cursor
-        //! curr_line : This is the line before moving
-        //! next_line : This is the line *AFTER* moving
-        //!
-        //! This function is used to move a cursor to the appropriate position within a line when moving vertically
-        //! "Appropriate" here means that if the cursor is in a row of a line other than the beginning line, the very first position the
-        //! cursor should be able to take is on top of the second character of the row
+    pub fn move_cursor_to_line(&self, line_ind: usize, pos: usize) {
+        //! Moves the cursor to pos within the line at line_ind, scrolling the document so that the
+        //! line starts at the top of the document display window if it is not already visible
 
-        if self.get_cursor_pos_in_line() / self.doc_disp_width() == 0 {
-            // If after the cursor moved it is at the first row in the line
+        let document = self.current_buffer();
 
-            if self.get_cursor_doc_col() == 1 {
-                // The cursor is now at index 1 of the line, so it needs to be moved to the left to give the
-                // proper bump effect
+        let first_row = document.borrow().first_row_of_line(line_ind);
 
-                self.move_cursor_left();
-            }
-        } else {
-            // If after the cursor moved it is on any other row
+        let viewport = document.borrow().viewport;
 
-            if self.get_cursor_doc_col() == 0 {
-                // The cursor is now touching the left edge of the doc display area and in any of the rows
-                // that are not the first row, so we need to bump it to the right
+        if !viewport.contains(first_row) {
+            let num_rows = document.borrow().num_rows();
 
-                self.move_cursor_right();
-            }
+            let new_top = first_row.min(num_rows.saturating_sub(self.doc_disp_height()));
+
+            document
+                .borrow_mut()
+                .viewport
+                .scroll_to(new_top, self.doc_disp_height());
         }
-    }
 
-    // ==================== DIMENSIONS ====================================
+        let top = document.borrow().viewport.top();
 
-    // -------------------- DIMENSION INFORMATION -------------------------
+        self.move_cursor_vis_to(
+            self.doc_disp_home_row() + (first_row - top),
+            self.doc_disp_left_edge(),
+        );
+        self.move_cursor_doc_to(first_row, 0);
 
-    pub fn doc_disp_home_row(&self) -> usize {
-        //! The first row on which the document will be displayed
-        2
-    }
+        self.move_cursor_to_pos(pos);
 
-    pub fn doc_disp_height(&self) -> usize {
-        //! The height spanned from the first possible row to the last where
-        //! the document is displayed
-        self.term_dimensions.height - 3
+        self.reset_editor_view();
     }
 
-    pub fn doc_disp_bottom(&self) -> usize {
-        //! The last row on which the document will be displayed
-        self.term_dimensions.height - 2
-    }
+    pub fn move_cursor_into_view(&self, line_ind: usize, pos: usize) {
+        //! Moves the cursor to pos within the line at line_ind, scrolling the document only as far
+        //! as it takes to bring the row the cursor lands on into view
 
-    pub fn doc_disp_right_edge(&self) -> usize {
-        //! The offset from the right side of the terminal, last column
-        //! the document will be displayed
-        self.term_dimensions.width - self.right_edge_offset
-    }
+        let document = self.current_buffer();
 
-    pub fn doc_disp_left_edge(&self) -> usize {
-        //! The offset from the left side of the terminal, first column
-        //! the document will be displayed
-        self.left_edge_offset
-    }
+        let (first_row, row, num_rows) = {
+            let document = document.borrow();
+            let first_row = document.first_row_of_line(line_ind);
 
-    pub fn doc_disp_width(&self) -> usize {
-        //! The width spanned from the first possible column to the last
-        //! where the document is displayed
-        (self.term_dimensions.width - self.right_edge_offset) - self.left_edge_offset
-    }
+            let row_in_line = if document.wraps_plainly() {
+                // The same row move_to_pos puts the cursor on, a position at the very end of a row
+                // is shown hanging off the end of it rather than at the start of the next one
+                plain_row_of(pos, self.wrap_width())
+            } else {
+                document.row_layout(line_ind).row_of(pos)
+            };
 
-    pub fn mode_row(&self) -> usize {
-        //! The row on which the mode will be displayed
-        self.term_dimensions.height - 1
+            (first_row, first_row + row_in_line, document.num_rows())
+        };
+
+        let scrolled =
+            document
+                .borrow_mut()
+                .viewport
+                .follow(row, 0, self.doc_disp_height(), num_rows);
+
+        self.move_cursor_doc_to(first_row, 0);
+        self.move_cursor_to_pos(pos);
+
+        if scrolled {
+            self.reset_editor_view();
+        }
+    }
+
+    pub fn move_cursor_to_row(&self, row: usize) {
+        //! Moves the cursor onto row of the document, keeping it in the same column of the
+        //! document display window as far as the text on that row allows
+
+        let column = self.get_cursor_column_in_doc_disp();
+
+        let (line_ind, pos) = {
+            let document = self.current_buffer();
+
+            let line_ind = document.borrow().get_index_at_cursor(row);
+            let row_in_line = row - document.borrow().first_row_of_line(line_ind);
+
+            // A line that has not been laid out yet may span fewer rows than it was estimated to
+            document.borrow_mut().settle_rows(line_ind, 1);
+
+            let document = document.borrow();
+
+            let layout = document.row_layout(line_ind);
+            let row_in_line = row_in_line.min(layout.num_rows() - 1);
+
+            let start = layout.starts[row_in_line];
+            let plain = document.wraps_plainly();
+
+            // The last row may hold the cursor just past the end of the line. Plainly wrapped rows
+            // hold it just past their last character and never on their first one, as plain_row_of
+            // has it, other rows end just before the first character of the next row
+            let first_pos = if plain && row_in_line > 0 {
+                start + 1
+            } else {
+                start
+            };
+
+            let line = document.get_line(line_ind).1;
+
+            let last_pos = match layout.starts.get(row_in_line + 1) {
+                Some(next_start) if plain => *next_start,
+                Some(next_start) => line[..*next_start]
+                    .chars()
+                    .next_back()
+                    .map_or(start, |c| next_start - c.len_utf8()),
+                None => line.len(),
+            };
+
+            let pos = layout.pos_at_column(&line, row_in_line, column);
+
+            // Coming onto the second column of a plainly wrapped line's first row, the column the
+            // rows below it start their cursor at, puts the cursor at the start of the line
+            let pos = if plain && row_in_line == 0 && pos == 1 {
+                0
+            } else {
+                pos
+            };
+
+            (line_ind, pos.clamp(first_pos, last_pos))
+        };
+
+        self.move_cursor_into_view(line_ind, pos);
+    }
+
+    // ==================== DIMENSIONS ====================================
+
+    // -------------------- DIMENSION INFORMATION -------------------------
+
+    fn active_rect(&self) -> Rect {
+        //! The part of the screen the document display window takes up, the active window's
+        self.windows().active().rect
+    }
+
+    fn window_area(&self) -> Rect {
+        //! The rows shared by the windows, between the title and the status line
+        Rect {
+            top: 2,
+            left: 1,
+            height: self.term_dimensions.height - 3,
+            width: self.term_dimensions.width,
+        }
+    }
+
+    pub fn doc_disp_home_row(&self) -> usize {
+        //! The first row on which the document will be displayed
+        self.active_rect().top
+    }
+
+    pub fn doc_disp_height(&self) -> usize {
+        //! The height spanned from the first possible row to the last where
+        //! the document is displayed
+        self.active_rect().height
+    }
+
+    pub fn doc_disp_bottom(&self) -> usize {
+        //! The last row on which the document will be displayed
+        self.active_rect().bottom()
+    }
+
+    pub fn doc_disp_right_edge(&self) -> usize {
+        //! The offset from the right side of the terminal, last column
+        //! the document will be displayed
+        self.active_rect().right() - self.right_edge_offset
+    }
+
+    pub fn doc_disp_left_edge(&self) -> usize {
+        //! The offset from the left side of the terminal, first column
+        //! the document will be displayed, pushed over by the gutter when it is shown
+        self.active_rect().left - 1
+            + self
+                .left_edge_offset
+                .max(self.sign_width.get() + self.gutter_width.get() + 1)
+    }
+
+    pub fn doc_disp_width(&self) -> usize {
+        //! The width spanned from the first possible column to the last
+        //! where the document is displayed
+        self.doc_disp_right_edge() - self.doc_disp_left_edge()
+    }
+
+    pub fn wraps_plainly(&self) -> bool {
+        //! Whether the current buffer's rows all hold wrap_width characters, the cursor can only be
+        //! moved by counting rows and columns while they do
+        self.current_buffer().borrow().wraps_plainly()
+    }
+
+    pub fn wrap_width(&self) -> usize {
+        //! The width the current buffer's lines are cut into rows at, the width of the document
+        //! display window unless wrapping is off
+        self.current_buffer()
+            .borrow()
+            .wrap_width(self.doc_disp_width())
+    }
+
+    pub fn mode_row(&self) -> usize {
+        //! The row on which the mode will be displayed
+        self.term_dimensions.height - 1
     }
 
     pub fn command_row(&self) -> usize {
@@ -569,173 +1247,2161 @@ impl Editor {
         self.term_dimensions.height
     }
 
-    // -------------------- DIMENSION MANIPULATION ------------------------
+    pub fn command_text_column(&self) -> usize {
+        //! The column of the first character typed on the command row, just after the : or /
+        2
+    }
+
+    // -------------------- DIMENSION MANIPULATION ------------------------
+
+    pub fn check_resize(&mut self) {
+        let checker = term_size();
+
+        if checker.width != self.term_dimensions.width
+            || checker.height != self.term_dimensions.height
+        {
+            self.draw_buffer
+                .borrow_mut()
+                .resize(checker.width, checker.height);
+
+            self.term_dimensions = checker;
+
+            self.arrange_windows();
+
+            self.redraw_screen();
+
+            self.update_horizontal_scroll();
+        }
+    }
+
+    // ============================== VIEWPORT ============================
+
+    pub fn keep_cursor_in_view(&self) {
+        //! Scrolls the current buffer so that there are scrolloff rows in view above and below the
+        //! cursor, as far as the document allows
+
+        let document = self.current_buffer();
+
+        let num_rows = document.borrow().num_rows();
+
+        let scrolled = document.borrow_mut().viewport.follow(
+            self.get_cursor_doc_row(),
+            self.scrolloff,
+            self.doc_disp_height(),
+            num_rows,
+        );
+
+        if scrolled {
+            self.place_cursor_in_viewport();
+
+            self.reset_editor_view();
+        }
+    }
+
+    pub fn align_view(&self, align: Align) {
+        //! Scrolls the current buffer to put the cursor's row at the top, middle or bottom of the
+        //! document display window, the cursor stays on the same character
+
+        self.current_buffer().borrow_mut().viewport.align(
+            self.get_cursor_doc_row(),
+            align,
+            self.doc_disp_height(),
+        );
+
+        self.place_cursor_in_viewport();
+
+        self.reset_editor_view();
+    }
+
+    pub fn scroll_half_page(&self, down: bool) {
+        //! Scrolls the current buffer by half the height of the document display window, taking
+        //! the cursor along by as many rows
+
+        self.scroll_with_cursor(down, self.doc_disp_height() / 2);
+    }
+
+    pub fn scroll_page(&self, down: bool) {
+        //! Scrolls the current buffer by the height of the document display window, the two rows
+        //! at the edge of the page scrolled away stay in view
+
+        self.scroll_with_cursor(down, self.doc_disp_height().saturating_sub(2).max(1));
+    }
+
+    fn scroll_with_cursor(&self, down: bool, rows: usize) {
+        let document = self.current_buffer();
+
+        let height = self.doc_disp_height();
+        let num_rows = document.borrow().num_rows();
+        let row = self.get_cursor_doc_row();
+
+        let target = document
+            .borrow_mut()
+            .viewport
+            .scroll_carrying(row, down, rows, height, num_rows);
+
+        self.move_cursor_to_row(target);
+
+        self.reset_editor_view();
+    }
+
+    fn place_cursor_in_viewport(&self) {
+        //! Moves the cursor visually to the row its row in the document is shown on, after the
+        //! viewport moved without it
+
+        let top = self.current_buffer().borrow().viewport.top();
+
+        let vis_row = self.doc_disp_home_row() + self.get_cursor_doc_row().saturating_sub(top);
+
+        self.move_cursor_vis_to(vis_row, self.get_cursor_vis_col());
+    }
+
+    // ============================== WINDOWS =============================
+
+    fn windows(&self) -> &Windows {
+        //! The windows of the current tab page
+        self.tabs.current()
+    }
+
+    fn windows_mut(&mut self) -> &mut Windows {
+        self.tabs.current_mut()
+    }
+
+    fn arrange_windows(&mut self) {
+        //! Lays the windows out again, the document display window is the active one afterwards
+
+        let area = self.window_area();
+
+        self.windows_mut().arrange(area);
+
+        self.clip_to(Some(self.active_rect()));
+    }
+
+    fn clip_to(&self, rect: Option<Rect>) {
+        //! Keeps whatever is drawn on the rows shared by the windows inside rect, or lets it go
+        //! anywhere on them
+
+        let area = self.window_area();
+
+        let clip = rect.map(|rect| Clip {
+            area_top: area.top - 1,
+            area_bottom: area.bottom() - 1,
+            top: rect.top - 1,
+            bottom: rect.bottom().saturating_sub(1),
+            left: rect.left - 1,
+            right: rect.right().saturating_sub(1),
+        });
+
+        self.draw_buffer.borrow_mut().set_clip(clip);
+    }
+
+    pub fn window_count(&self) -> usize {
+        //! How many windows there are in every tab page together
+        self.tabs.iter().map(Windows::len).sum()
+    }
+
+    pub fn split_window(
+        &mut self,
+        direction: Direction,
+        file_name: Option<&str>,
+    ) -> Result<(), String> {
+        //! Splits the active window in two, the new window becomes active and shows the file, or
+        //! the current buffer when no file is given
+
+        self.store_window_state();
+
+        self.windows_mut().split(direction)?;
+
+        if let Some(file_name) = file_name {
+            self.add_file_buffer(file_name);
+
+            self.windows_mut().active_mut().view = BufferView::default();
+        }
+
+        self.switch_window();
+
+        Ok(())
+    }
+
+    pub fn close_window(&mut self) -> Result<(), String> {
+        //! Closes the active window, or its tab page when it is the only window there
+
+        if self.windows().len() == 1 && self.tabs.len() > 1 {
+            return self.close_tab();
+        }
+
+        let id = self.windows().active_id();
+
+        self.windows_mut().close(id)?;
+
+        self.switch_window();
+
+        Ok(())
+    }
+
+    pub fn only_window(&mut self) {
+        self.windows_mut().only();
+
+        self.store_window_state();
+        self.switch_window();
+    }
+
+    pub fn cycle_window(&mut self, forward: bool) {
+        //! Makes the next window active, or the previous one, going round at the last one
+
+        let id = self.windows().cycle(forward);
+
+        self.store_window_state();
+        self.windows_mut().set_active(id);
+        self.switch_window();
+    }
+
+    pub fn move_to_window(&mut self, heading: Heading) {
+        //! Makes the window next to the active one in the direction of heading active
+
+        let Some(id) = self.windows().neighbour(heading) else {
+            return;
+        };
+
+        self.store_window_state();
+        self.windows_mut().set_active(id);
+        self.switch_window();
+    }
+
+    pub fn resize_window(&mut self, along: Direction, delta: isize) {
+        //! Makes the active window delta rows taller, or delta columns wider when along is
+        //! SideBySide
+
+        self.store_window_state();
+        self.windows_mut().resize(along, delta);
+        self.switch_window();
+    }
+
+    pub fn equalize_windows(&mut self) {
+        self.store_window_state();
+        self.windows_mut().equalize();
+        self.switch_window();
+    }
+
+    pub fn open_start_windows(&mut self, split: Option<Direction>) {
+        //! Shows the buffers opened from the command line each in a window of its own, laid out one
+        //! way, then puts the cursor where the command line asked in the first buffer
+        //!
+        //! Buffers that do not fit are left hidden
+
+        if let Some(direction) = split {
+            let buffers = self.file_buffers.clone();
+
+            for _ in 1..buffers.len() {
+                if self.windows_mut().split(direction).is_err() {
+                    break;
+                }
+            }
+
+            self.windows_mut().equalize();
+
+            let ids = self.windows().ids();
+
+            for (id, buffer) in ids.iter().zip(buffers) {
+                if let Some(window) = self.windows_mut().get_mut(*id) {
+                    window.view = buffer.borrow().last_view;
+                    window.buffer = buffer;
+                }
+            }
+
+            self.windows_mut().set_active(ids[0]);
+        }
+
+        let view = self.windows().active().view;
+
+        if split.is_none() && view == BufferView::default() {
+            return;
+        }
+
+        self.switch_window();
+
+        if view.cursor.0 > 0 {
+            self.align_view(Align::Center);
+        }
+    }
+
+    fn store_window_state(&mut self) {
+        //! Copies the live cursor and viewport back into the active window
+
+        let document = self.current_buffer();
+
+        let cursor = (self.get_cursor_line_ind(), self.get_cursor_pos_in_line());
+
+        let (top, left_col) = {
+            let document = document.borrow();
+
+            let top_row = document.viewport.top();
+            let line_ind = document.get_index_at_cursor(top_row);
+
+            (
+                (line_ind, top_row - document.first_row_of_line(line_ind)),
+                document.left_col,
+            )
+        };
+
+        let window = self.windows_mut().active_mut();
+
+        window.view = BufferView {
+            cursor,
+            top,
+            left_col,
+        };
+    }
+
+    fn switch_window(&mut self) {
+        //! Lays the windows out and makes the cursor and viewport of the active window live, then
+        //! draws every window again
+        //!
+        //! The buffer may have been edited in another window since the window was last active, so
+        //! the line it was scrolled to and the cursor's place are kept within the buffer
+
+        self.arrange_windows();
+
+        // The windows may be those of another tab page, whose windows go by the same ids
+        self.drawn_windows.borrow_mut().clear();
+
+        let view = self.windows().active().view;
+
+        let document = self.current_buffer();
+
+        let wrap_width = self.wrap_width();
+
+        document.borrow_mut().check_wrap_width(wrap_width);
+
+        let (
+            BufferView {
+                cursor: (line_ind, pos),
+                left_col,
+                ..
+            },
+            top,
+        ) = {
+            let document = document.borrow();
+
+            let view = document.clamp_view(view);
+
+            (view, document.first_row_of_line(view.top.0) + view.top.1)
+        };
+
+        {
+            let mut document = document.borrow_mut();
+
+            document.viewport.scroll_to(top, self.doc_disp_height());
+            document.left_col = left_col;
+        }
+
+        self.move_cursor_into_view(line_ind, pos);
+
+        self.print_title();
+
+        self.reset_editor_view();
+
+        self.update_windows();
+
+        self.print_status_line();
+
+        // The buffer may need a gutter of another width, which redraws everything once more
+        self.update_gutter();
+
+        self.update_horizontal_scroll();
+    }
+
+    // ============================== TABS ================================
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn new_tab(&mut self, file_name: Option<&str>) {
+        //! Opens a tab page after the current one with a single window showing the file, or a new
+        //! scratch buffer when no file is given
+
+        self.store_window_state();
+
+        self.tabs.open(Windows::new(self.current_buffer()));
+
+        // The buffer is opened to fit the window, so the window is laid out first
+        self.arrange_windows();
+
+        match file_name {
+            Some(file_name) => self.add_file_buffer(file_name),
+            None => {
+                let buffer = Rc::new(RefCell::new(Document::new_unnamed(self.doc_disp_height())));
+
+                self.file_buffers.push(Rc::clone(&buffer));
+
+                self.windows_mut().active_mut().buffer = buffer;
+            }
+        }
+
+        self.switch_window();
+    }
+
+    pub fn close_tab(&mut self) -> Result<(), String> {
+        self.tabs.close()?;
+
+        self.switch_window();
+
+        Ok(())
+    }
+
+    pub fn only_tab(&mut self) {
+        self.tabs.only();
+
+        self.print_title();
+    }
+
+    pub fn cycle_tab(&mut self, forward: bool) {
+        //! Makes the next tab page current, or the previous one, going round at the last one
+
+        self.store_window_state();
+        self.tabs.cycle(forward);
+        self.switch_window();
+    }
+
+    pub fn go_to_tab(&mut self, number: usize) -> Result<(), String> {
+        //! Makes the tab page with the number current, tab pages are counted from 1
+
+        self.store_window_state();
+        self.tabs.go_to(number.saturating_sub(1))?;
+        self.switch_window();
+
+        Ok(())
+    }
+
+    pub fn move_tab(&mut self, number: Option<usize>) {
+        //! Moves the current tab page after the number of other tab pages, 0 puts it first and
+        //! without a number it goes last
+
+        self.tabs.move_current(number.unwrap_or(usize::MAX));
+
+        self.print_title();
+    }
+
+    pub fn move_window_to_new_tab(&mut self) -> Result<(), String> {
+        //! Takes the active window out of its tab page and opens it in a tab page of its own
+
+        if self.windows().len() == 1 {
+            return Err("Only one window".to_string());
+        }
+
+        self.store_window_state();
+
+        let window = self.windows().active().clone();
+        let id = self.windows().active_id();
+
+        self.windows_mut().close(id)?;
+
+        let mut windows = Windows::new(Rc::clone(&window.buffer));
+
+        *windows.active_mut() = Window {
+            rect: windows.active().rect,
+            ..window
+        };
+
+        self.tabs.open(windows);
+
+        self.switch_window();
+
+        Ok(())
+    }
+
+    fn tab_label(&self, windows: &Windows) -> String {
+        //! What the tab bar shows for a tab page, the buffer of its active window along with how
+        //! many windows it has when there is more than one
+
+        let document = windows.active().buffer.borrow();
+
+        format!(
+            "{}{}{}",
+            if windows.len() > 1 {
+                format!("{} ", windows.len())
+            } else {
+                String::new()
+            },
+            document.display_name(),
+            document.flags(),
+        )
+    }
+
+    fn print_tab_bar(&self) {
+        //! Draws a label for every tab page on the title line, the current one stands out
+
+        let width = self.term_dimensions.width;
+
+        let mut used = 0;
+
+        for (ind, windows) in self.tabs.iter().enumerate() {
+            let label = format!(" {} ", self.tab_label(windows));
+            let label = label
+                .chars()
+                .take(width.saturating_sub(used))
+                .collect::<String>();
+
+            used += label.chars().count();
+
+            let color = if ind == self.tabs.current_index() {
+                self.theme.title_text_color()
+            } else {
+                self.theme.gutter_text_color()
+            };
+
+            self.print_text_w_color(color, label);
+
+            if used < width && ind + 1 < self.tabs.len() {
+                self.print_text_w_color(self.theme.gutter_text_color(), "│");
+
+                used += 1;
+            }
+        }
+    }
+
+    pub fn update_windows(&self) {
+        //! Draws the windows other than the active one and what separates the windows, edits made
+        //! in the active window show up straight away in the others showing the same buffer
+        //!
+        //! A window is only drawn again when what it shows changed since it was last drawn, e.g.
+        //! its buffer was edited or the layout moved it, as the screen keeps what was drawn before
+
+        if self.windows().len() == 1 {
+            return;
+        }
+
+        // The active window's sideways scroll would be applied to whatever is drawn on its rows
+        let scroll_window = self.scroll_window.get();
+        self.draw_buffer.borrow_mut().set_scroll_window(None);
+
+        self.save_cursor_vis_pos();
+
+        let mut drawn = self.drawn_windows.borrow_mut();
+
+        // The active window draws over its own rows, and closed windows have none
+        drawn
+            .retain(|id, _| *id != self.windows().active_id() && self.windows().get(*id).is_some());
+
+        for (id, window) in self.windows().iter() {
+            if id == self.windows().active_id() {
+                continue;
+            }
+
+            let look = self.window_look(window);
+
+            if drawn.get(&id) != Some(&look) {
+                self.clip_to(Some(window.rect));
+
+                self.print_window(window, &look.signs);
+
+                drawn.insert(id, look);
+            }
+        }
+
+        self.clip_to(None);
+
+        for separator in self.windows().separators() {
+            self.print_separator(*separator);
+        }
+
+        self.clip_to(Some(self.active_rect()));
+
+        self.draw_buffer
+            .borrow_mut()
+            .set_scroll_window(scroll_window);
+
+        self.revert_cursor_vis_pos();
+    }
+
+    fn window_look(&self, window: &Window) -> WindowLook {
+        let document = window.buffer.borrow();
+
+        let num_lines = document.get_number_lines();
+        let top_line = document.clamp_view(window.view).top.0;
+
+        WindowLook {
+            rect: window.rect,
+            view: window.view,
+            buffer: Rc::as_ptr(&window.buffer),
+            changes: document.changes(),
+            num_lines,
+            wrap: document.wrap,
+            wrap_style: document.wrap_style.clone(),
+            line_numbers: self.gutter.line_numbers(),
+            has_placed_signs: !document.signs().is_empty(),
+            signs: self.signs_on_lines(
+                &document,
+                top_line..(top_line + window.rect.height).min(num_lines),
+            ),
+        }
+    }
+
+    fn print_window(&self, window: &Window, signs: &BTreeMap<usize, (u32, char)>) {
+        //! Draws a window that is not active the way it was left, without the cursor, signs are
+        //! those of the lines it shows
+
+        let rect = window.rect;
+        let document = window.buffer.borrow();
+
+        let num_lines = document.get_number_lines();
+        let view = document.clamp_view(window.view);
+        let top_line = view.top.0;
+
+        let sign_width = if document.signs().is_empty() && signs.is_empty() {
+            0
+        } else {
+            SIGN_COLUMN_WIDTH
+        };
+        let gutter_width = self.gutter.width(num_lines);
+
+        let left_edge = rect.left - 1 + self.left_edge_offset.max(sign_width + gutter_width + 1);
+        let text_width = rect
+            .right()
+            .saturating_sub(self.right_edge_offset)
+            .saturating_sub(left_edge);
+        let wrap_width = document.wrap_width(text_width);
+
+        // Each row along with the index of the line it begins, if it is the first row of a line
+        let mut rows: Vec<(Option<usize>, String)> = Vec::new();
+
+        'lines: for (line_ind, line) in (top_line..).zip(document.lines_from(top_line)) {
+            let layout = document.wrap_style.layout(&line, wrap_width);
+
+            let skipped = if line_ind == top_line {
+                view.top.1.min(layout.num_rows() - 1)
+            } else {
+                0
+            };
+
+            for row in skipped..layout.num_rows() {
+                if rows.len() == rect.height {
+                    break 'lines;
+                }
+
+                let text = layout.row_display(&line, row);
+
+                rows.push((Some(line_ind).filter(|_| row == 0), text));
+            }
+        }
+
+        for offset in 0..rect.height {
+            let row = rect.top + offset;
+
+            self.move_cursor_vis_to(row, rect.left);
+
+            self.apply_line_color(self.theme.background_color());
+
+            let Some((line_ind, text)) = rows.get(offset) else {
+                continue;
+            };
+
+            if sign_width > 0 {
+                let glyph = line_ind
+                    .and_then(|ind| signs.get(&ind))
+                    .map_or(' ', |(_, glyph)| *glyph);
+
+                self.print_text_w_color(
+                    self.theme.sign_text_color(),
+                    format!("{glyph:<sign_width$}"),
+                );
+            }
+
+            if gutter_width > 0 {
+                self.print_text_w_color(
+                    self.theme.gutter_text_color(),
+                    self.gutter.label(*line_ind, view.cursor.0, gutter_width),
+                );
+            }
+
+            let text = if document.wrap {
+                text.as_str()
+            } else {
+                wrap::columns(text, view.left_col..view.left_col + text_width)
+            };
+
+            self.move_cursor_vis_to(row, left_edge);
+
+            self.print_text_w_color(self.theme.body_text_color(), text);
+        }
+
+        self.apply_reset_color();
+    }
+
+    fn print_separator(&self, separator: Separator) {
+        match separator {
+            Separator::Bar {
+                window,
+                row,
+                left,
+                width,
+            } => {
+                let Some(shown) = self.windows().get(window) else {
+                    return;
+                };
+
+                let document = shown.buffer.borrow();
+
+                let color = if window == self.windows().active_id() {
+                    self.theme.mode_text_color()
+                } else {
+                    self.theme.gutter_text_color()
+                };
+
+                let label = format!(" {}{}", document.display_name(), document.flags());
+
+                self.move_cursor_vis_to(row, left);
+
+                self.print_text_w_color(
+                    color,
+                    format!("{label:<width$}")
+                        .chars()
+                        .take(width)
+                        .collect::<String>(),
+                );
+            }
+            Separator::Column {
+                column,
+                top,
+                height,
+            } => {
+                for row in top..top + height {
+                    self.move_cursor_vis_to(row, column);
+
+                    self.print_text_w_color(self.theme.gutter_text_color(), "│");
+                }
+            }
+        }
+
+        self.apply_reset_color();
+    }
+
+    // ============================== INDENTATION =========================
+
+    pub fn new_line_indentation(&self) -> String {
+        //! The indentation of the line made by breaking the cursor's line at the cursor
+
+        let line = self
+            .current_buffer()
+            .borrow()
+            .get_line(self.get_cursor_line_ind())
+            .1;
+
+        let (before, after) = line.split_at(self.get_cursor_pos_in_line().min(line.len()));
+
+        self.current_buffer()
+            .borrow()
+            .indent
+            .new_line(before, after)
+    }
+
+    pub fn line_below_indentation(&self) -> String {
+        //! The indentation of a line opened below the cursor's line with o
+
+        let line = self
+            .current_buffer()
+            .borrow()
+            .get_line(self.get_cursor_line_ind())
+            .1;
+
+        self.current_buffer().borrow().indent.new_line(&line, "")
+    }
+
+    pub fn line_above_indentation(&self) -> String {
+        //! The indentation of a line opened above the cursor's line with O
+
+        let line = self
+            .current_buffer()
+            .borrow()
+            .get_line(self.get_cursor_line_ind())
+            .1;
+
+        self.current_buffer().borrow().indent.line_above(&line)
+    }
+
+    pub fn indent_closing_bracket(&self, c: char) {
+        //! Takes a level of indentation off the cursor's line when c, about to be typed, closes a
+        //! block at the start of the line
+
+        if !matches!(c, '}' | ')' | ']') {
+            return;
+        }
+
+        let document = self.current_buffer();
+
+        let line_ind = self.get_cursor_line_ind();
+        let pos = self.get_cursor_pos_in_line();
+
+        let line = document.borrow().get_line(line_ind).1;
+
+        let before = &line[..pos.min(line.len())];
+
+        let Some(indentation) = document.borrow().indent.closing(before) else {
+            return;
+        };
+
+        for _ in before.chars() {
+            document.borrow_mut().edit_pop();
+        }
+
+        document.borrow_mut().edit_insert_str(&indentation);
+
+        self.move_cursor_into_view(line_ind, indentation.len());
+
+        self.print_line();
+    }
+
+    pub fn insert_tab(&self) {
+        //! Puts in what the tab key does at the cursor, a tab character or whitespace up to the
+        //! next soft tab stop
+
+        let (line_ind, before) = self.text_before_cursor();
+
+        let change = self.current_buffer().borrow().indent.tab(&before);
+
+        match change {
+            Some((taken, whitespace)) => self.replace_before_cursor(&before, taken, &whitespace),
+            None => {
+                self.current_buffer().borrow_mut().edit_insert('\t');
+
+                self.move_cursor_into_view(line_ind, before.len() + 1);
+            }
+        }
+
+        self.reset_editor_view();
+    }
+
+    pub fn delete_soft_tab(&self) -> bool {
+        //! Takes the whitespace before the cursor back to the previous soft tab stop, false when
+        //! backspace should take out a single character instead
+
+        let (_, before) = self.text_before_cursor();
+
+        let change = self.current_buffer().borrow().indent.backspace(&before);
+
+        let Some((taken, whitespace)) = change else {
+            return false;
+        };
+
+        self.replace_before_cursor(&before, taken, &whitespace);
+
+        true
+    }
+
+    pub fn break_at_textwidth(&self, c: char) {
+        //! Carries the words at the end of the cursor's line onto a new line when c, about to be
+        //! typed, would go past textwidth, the line is broken at the last blank that leaves it short
+        //! enough
+
+        let document = self.current_buffer();
+
+        let textwidth = document.borrow().textwidth;
+
+        if textwidth == 0 || c.is_whitespace() {
+            return;
+        }
+
+        let (line_ind, before) = self.text_before_cursor();
+
+        let indent = document.borrow().indent.clone();
+
+        if indent.width(&before) < textwidth {
+            return;
+        }
+
+        // The line's own indentation is never broken at
+        let start = indent::leading_whitespace(&before).len();
+
+        let kept = before
+            .char_indices()
+            .rev()
+            .filter(|(ind, c)| *ind > start && (*c == ' ' || *c == '\t'))
+            .map(|(ind, _)| before[..ind].trim_end())
+            .find(|kept| kept.len() > start && indent.width(kept) <= textwidth);
+
+        let Some(kept) = kept else {
+            return;
+        };
+
+        let carried = before[kept.len()..].trim_start();
+        let indentation = indent.new_line(kept, carried);
+
+        for _ in before[kept.len()..].chars() {
+            document.borrow_mut().edit_pop();
+        }
+
+        document.borrow_mut().edit_insert('\n');
+        document
+            .borrow_mut()
+            .edit_insert_str(&(indentation.clone() + carried));
+
+        self.move_cursor_into_view(line_ind + 1, indentation.len() + carried.len());
+
+        self.reset_editor_view();
+    }
+
+    fn text_before_cursor(&self) -> (usize, String) {
+        let line_ind = self.get_cursor_line_ind();
+        let pos = self.get_cursor_pos_in_line();
+
+        let line = self.current_buffer().borrow().get_line(line_ind).1;
+
+        (line_ind, line[..pos.min(line.len())].to_string())
+    }
+
+    fn replace_before_cursor(&self, before: &str, taken: usize, text: &str) {
+        //! Takes the last taken characters of before, the text before the cursor, out of the line
+        //! and puts text in their place
+
+        let document = self.current_buffer();
+
+        for _ in 0..taken {
+            document.borrow_mut().edit_pop();
+        }
+
+        document.borrow_mut().edit_insert_str(text);
+
+        let removed: usize = before.chars().rev().take(taken).map(char::len_utf8).sum();
+
+        self.move_cursor_into_view(
+            self.get_cursor_line_ind(),
+            before.len() - removed + text.len(),
+        );
+    }
+
+    pub fn shift_lines(&self, lines: Range<usize>, levels: isize) {
+        //! Moves the lines levels of indentation in, or out when levels is negative
+
+        self.indent_lines(lines, |indent, _, line| indent.shifted(line, levels));
+    }
+
+    pub fn reindent_lines(&self, lines: Range<usize>) {
+        //! Works the indentation of the lines out again from the lines above them
+
+        self.indent_lines(lines, |indent, above, line| indent.reindented(above, line));
+    }
+
+    fn indent_lines(
+        &self,
+        lines: Range<usize>,
+        change: impl Fn(&IndentSettings, Option<&str>, &str) -> String,
+    ) {
+        //! Replaces each of the lines by what change makes of it, given the closest line above it
+        //! that is not blank, then puts the cursor on the first line's text
+
+        let document = self.current_buffer();
+
+        if document.borrow().is_read_only() {
+            self.notify(Level::Error, "Cannot make changes, buffer is read only");
+
+            return;
+        }
+
+        let first = lines.start;
+
+        let mut above = (0..first)
+            .rev()
+            .map(|line_ind| document.borrow().get_line(line_ind).1)
+            .find(|line| !line.trim().is_empty());
+
+        for line_ind in lines {
+            let line = document.borrow().get_line(line_ind).1;
+
+            let new_line = {
+                let document = document.borrow();
+
+                change(&document.indent, above.as_deref(), &line)
+            };
+
+            if !new_line.trim().is_empty() {
+                above = Some(new_line.clone());
+            }
+
+            document.borrow_mut().replace_line(line_ind, new_line);
+        }
+
+        let line = document.borrow().get_line(first).1;
+
+        self.move_cursor_into_view(first, indent::leading_whitespace(&line).len());
+
+        self.reset_editor_view();
+    }
+
+    // ============================== MODE ================================
+
+    pub fn change_mode(&mut self, new_mode: Modes) {
+        //! curr - Current mode stored in the state of the application
+        //! new_mode - The new mode which will be stored in the state of the application
+        //! mode_row - The row at which the mode will be printed
+        //! cursor - Get control of cursor
+        //!
+        //! Changes the current mode of the editor to a new target mode, handles changing state and drawing to screen
+
+        if new_mode.cursor_shape() != self.curr_mode.cursor_shape() {
+            self.add_to_draw_buf(new_mode.cursor_shape());
+        }
+
+        let left_insert = self.curr_mode == Modes::Insert && new_mode != Modes::Insert;
+
+        self.curr_mode = new_mode;
+        self.inserting.set(new_mode == Modes::Insert);
+
+        // Signs worked out from the text are held while typing, see DiffChanges
+        if left_insert {
+            self.print_gutter();
+        }
+
+        self.print_status_line();
+    }
+
+    pub fn await_key(&self, pending: impl AsRef<str>) -> char {
+        //! Waits for the next key of a command that takes more than one, the keys typed so far are
+        //! shown in the status line in the meantime
+
+        self.pending_keys.replace(pending.as_ref().to_string());
+
+        self.print_status_line();
+
+        self.flush_pen();
+
+        let c = get_char();
+
+        self.pending_keys.borrow_mut().clear();
+
+        c
+    }
+
+    // ============================== OPTIONS =============================
+
+    pub fn set_option(&mut self, option: &str) -> Result<(), String> {
+        //! Changes an option the way :set does, "name" turns it on, "noname" turns it off and
+        //! "name!" or "invname" toggles it, "name=value" sets an option that takes text or a number
+
+        if let Some((name, value)) = option.split_once('=') {
+            if matches!(name, "scrolloff" | "so") {
+                self.scrolloff = value
+                    .parse()
+                    .map_err(|_| format!("Number required after =: {option}"))?;
+
+                return Ok(());
+            }
+
+            if matches!(name, "shiftwidth" | "sw" | "tabstop" | "ts") {
+                let width = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|width| *width > 0)
+                    .ok_or_else(|| format!("Number above 0 required after =: {option}"))?;
+
+                if matches!(name, "shiftwidth" | "sw") {
+                    self.current_buffer().borrow_mut().indent.shiftwidth = width;
+                } else {
+                    // Tabs are drawn wider or narrower, so the rows are worked out again
+                    self.rewrap(|document| {
+                        document.wrap_style.tabstop = width;
+                        document.indent.tabstop = width;
+                    });
+                }
+
+                return Ok(());
+            }
+
+            if matches!(name, "softtabstop" | "sts" | "textwidth" | "tw") {
+                let number = value
+                    .parse()
+                    .map_err(|_| format!("Number required after =: {option}"))?;
+
+                let document = self.current_buffer();
+                let mut document = document.borrow_mut();
+
+                match name {
+                    "softtabstop" | "sts" => document.indent.softtabstop = number,
+                    _ => document.textwidth = number,
+                }
+
+                return Ok(());
+            }
+
+            if matches!(name, "statusline" | "stl") {
+                self.status_layout = value.parse()?;
+
+                return Ok(());
+            }
+
+            let value = value.to_string();
+
+            match name {
+                "breakat" | "brk" => self.rewrap(|document| document.wrap_style.breakat = value),
+                "showbreak" | "sbr" => {
+                    self.rewrap(|document| document.wrap_style.showbreak = value)
+                }
+                _ => return Err(format!("Unknown option: {option}")),
+            }
+
+            return Ok(());
+        }
+
+        let (name, value): (&str, fn(bool) -> bool) = if let Some(name) = option.strip_suffix('!') {
+            (name, |old| !old)
+        } else if let Some(name) = option.strip_prefix("inv") {
+            (name, |old| !old)
+        } else if let Some(name) = option.strip_prefix("no") {
+            (name, |_| false)
+        } else {
+            (option, |_| true)
+        };
+
+        if name == "wrap" {
+            let wrap = value(self.current_buffer().borrow().wrap);
+
+            self.set_wrap(wrap);
+
+            return Ok(());
+        }
+
+        if matches!(name, "linebreak" | "lbr" | "breakindent" | "bri") {
+            let document = self.current_buffer();
+            let mut style = document.borrow().wrap_style.clone();
+
+            let flag = match name {
+                "linebreak" | "lbr" => &mut style.linebreak,
+                _ => &mut style.breakindent,
+            };
+
+            *flag = value(*flag);
+
+            self.rewrap(|document| document.wrap_style = style);
+
+            return Ok(());
+        }
+
+        if matches!(
+            name,
+            "autoindent" | "ai" | "smartindent" | "si" | "expandtab" | "et"
+        ) {
+            let document = self.current_buffer();
+            let mut document = document.borrow_mut();
+
+            let flag = match name {
+                "autoindent" | "ai" => &mut document.indent.autoindent,
+                "smartindent" | "si" => &mut document.indent.smartindent,
+                _ => &mut document.indent.expandtab,
+            };
+
+            *flag = value(*flag);
+
+            return Ok(());
+        }
+
+        if matches!(name, "readonly" | "ro") {
+            let document = self.current_buffer();
+
+            let read_only = value(document.borrow().read_only);
+
+            document.borrow_mut().read_only = read_only;
+
+            self.print_title();
+
+            return Ok(());
+        }
+
+        let flag = match name {
+            "number" | "nu" => &mut self.gutter.number,
+            "relativenumber" | "rnu" => &mut self.gutter.relative_number,
+            _ => return Err(format!("Unknown option: {option}")),
+        };
+
+        *flag = value(*flag);
+
+        Ok(())
+    }
+
+    fn set_wrap(&self, wrap: bool) {
+        //! Turns wrapping of the current buffer on or off
+
+        if self.current_buffer().borrow().wrap == wrap {
+            return;
+        }
+
+        self.rewrap(|document| {
+            document.wrap = wrap;
+            document.left_col = 0;
+        });
+    }
+
+    fn rewrap(&self, change: impl FnOnce(&mut Document)) {
+        //! Makes a change to how the current buffer is wrapped, the rows are worked out again and
+        //! the cursor is kept on the same character
+
+        let document = self.current_buffer();
+
+        let line_ind = document
+            .borrow()
+            .get_index_at_cursor(self.get_cursor_doc_row());
+        let pos = self.get_cursor_pos_in_line();
+        let top_line = {
+            let document = document.borrow();
+            document.get_index_at_cursor(document.viewport.top())
+        };
+
+        change(&mut document.borrow_mut());
+
+        let wrap_width = self.wrap_width();
+
+        document.borrow_mut().recalculate_indices(wrap_width);
+
+        let top = document.borrow().first_row_of_line(top_line);
+
+        document
+            .borrow_mut()
+            .viewport
+            .scroll_to(top, self.doc_disp_height());
+
+        self.move_cursor_to_line(line_ind, pos);
+    }
+
+    // ============================== CONFIG ==============================
+
+    pub fn config_path() -> Option<PathBuf> {
+        //! $XDG_CONFIG_HOME/textchen/config, or ~/.config/textchen/config when that is not set
+
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("HOME")
+                    .or_else(|| env::var_os("USERPROFILE"))
+                    .map(|home| PathBuf::from(home).join(".config"))
+            })
+            .map(|dir| dir.join("textchen").join("config"))
+    }
+
+    pub fn load_config(&mut self, file: &ConfigFile) {
+        //! Runs the commands in the config file, a missing config file is only an error when it was
+        //! given with -u
+        //!
+        //! Has to be called once the screen is up, some options move the cursor
+
+        let mut errors = Vec::new();
+
+        let (path, config) = match file {
+            ConfigFile::Skip => return,
+            ConfigFile::Default => {
+                let Some(path) = Self::config_path() else {
+                    return;
+                };
+
+                let Ok(config) = fs::read_to_string(&path) else {
+                    return;
+                };
+
+                (path, config)
+            }
+            ConfigFile::Path(path) => match fs::read_to_string(path) {
+                Ok(config) => (path.clone(), config),
+                Err(e) => {
+                    errors.push(format!("Cannot read {}: {e}", path.display()));
+
+                    (path.clone(), String::new())
+                }
+            },
+        };
+
+        for (ind, line) in config.lines().enumerate() {
+            if let Err(e) = self.run_config_line(line) {
+                errors.push(format!("{}:{}: {e}", path.display(), ind + 1));
+            }
+        }
+
+        // Options like number change the width of the gutter, which redraws the whole screen, so
+        // that has to happen before anything is put on the command row
+        self.update_gutter();
+
+        self.print_status_line();
+
+        if !errors.is_empty() {
+            self.notify(Level::Error, errors.join("\n"));
+        }
+    }
+
+    fn run_config_line(&mut self, line: &str) -> Result<(), String> {
+        //! Lines are the same commands typed after :, blank lines and lines starting with # or "
+        //! are skipped
+
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with('"') {
+            return Ok(());
+        }
+
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            Some("set" | "se") => words.try_for_each(|option| self.set_option(option)),
+            Some(command) => Err(format!("Not a config command: {command}")),
+            None => Ok(()),
+        }
+    }
+
+    // ============================== MESSAGES ============================
+
+    fn message_color(&self, level: Level) -> String {
+        match level {
+            Level::Info => self.theme.info_text_color(),
+            Level::Warning => self.theme.warning_text_color(),
+            Level::Error => self.theme.error_text_color(),
+        }
+    }
+
+    pub fn notify(&self, level: Level, text: impl AsRef<str>) {
+        //! Tells the user something and remembers it for :messages, a message that fits on the
+        //! command row is drawn there and a longer one rises over the document until a key is
+        //! pressed
+
+        let text = text.as_ref();
+
+        self.messages.borrow_mut().push(Message {
+            level,
+            text: text.to_string(),
+        });
+
+        let lines = text
+            .lines()
+            .map(|line| (level, line.to_string()))
+            .collect::<Vec<_>>();
+
+        self.show_message_lines(&lines);
+    }
+
+    pub fn show_message_history(&self) {
+        //! Lists every message kept so far, the way :messages does
+
+        let lines = self.messages.borrow().lines();
+
+        self.show_message_lines(&lines);
+    }
+
+    pub fn clear_message_history(&self) {
+        self.messages.borrow_mut().clear();
+    }
+
+    fn show_message_lines(&self, lines: &[(Level, String)]) {
+        match lines {
+            [] => self.print_command_message(self.theme.command_text_color(), ""),
+            [(level, line)] if line.chars().count() < self.term_dimensions.width => {
+                self.print_command_message(self.message_color(*level), line)
+            }
+            _ => self.show_message_area(lines),
+        }
+    }
+
+    fn show_message_area(&self, lines: &[(Level, String)]) {
+        //! Draws the lines over the bottom of the screen with a prompt on the command row, then
+        //! waits for a key before drawing the screen again
+        //!
+        //! Lines wider than the terminal carry on onto the row below, and when there are more rows
+        //! than fit under the title only the newest are shown
+
+        const PROMPT: &str = "Press ENTER to continue";
+
+        let width = self.term_dimensions.width.max(1);
+
+        let rows = lines
+            .iter()
+            .flat_map(|(level, line)| {
+                let chars = line.chars().collect::<Vec<_>>();
+
+                if chars.is_empty() {
+                    vec![(*level, String::new())]
+                } else {
+                    chars
+                        .chunks(width)
+                        .map(|chunk| (*level, chunk.iter().collect()))
+                        .collect()
+                }
+            })
+            .collect::<Vec<(Level, String)>>();
+
+        let room = self.command_row().saturating_sub(self.window_area().top);
+        let shown = &rows[rows.len().saturating_sub(room)..];
+
+        self.save_cursor_vis_pos();
+
+        self.clip_to(None);
+
+        for (offset, (level, row)) in shown.iter().enumerate() {
+            self.move_cursor_vis_to(self.command_row() - shown.len() + offset, 0);
+
+            self.apply_line_color(self.theme.background_color());
+
+            self.print_text_w_color(self.message_color(*level), row);
+        }
+
+        self.move_cursor_vis_to(self.command_row(), 0);
+
+        self.apply_line_color(self.theme.background_color());
+
+        self.print_text_w_color(self.theme.info_text_color(), PROMPT);
+
+        self.apply_reset_color();
+
+        self.move_cursor_vis_to(self.command_row(), PROMPT.len() + 1);
+
+        self.flush_pen();
+
+        get_char();
+
+        // The lines were drawn over the windows
+        self.drawn_windows.borrow_mut().clear();
+
+        self.clip_to(Some(self.active_rect()));
+
+        self.reset_editor_view();
+
+        self.update_windows();
+
+        self.print_status_line();
+
+        self.print_command_row();
+
+        self.revert_cursor_vis_pos();
+    }
+
+    // ============================== COMMAND =============================
+
+    pub fn initialize_command_row(&self) {
+        self.save_cursor_vis_pos();
+
+        self.move_cursor_vis_to(self.command_row(), 1);
+
+        self.apply_line_color(self.theme.background_color());
+
+        self.print_char(':');
+
+        self.move_cursor_vis_right();
+    }
+
+    pub fn initialize_search_row(&self) {
+        //! Like initialize_command_row but for entering a search pattern, the pattern is typed into
+        //! the command buffer
+
+        self.save_cursor_vis_pos();
+
+        self.move_cursor_vis_to(self.command_row(), 1);
+
+        self.apply_line_color(self.theme.background_color());
+
+        self.print_text_w_color(self.theme.command_text_color(), "/");
+
+        self.move_cursor_vis_right();
+    }
+
+    fn print_command_message(&self, color: impl AsRef<str>, message: impl AsRef<str>) {
+        self.save_cursor_vis_pos();
+
+        self.print_command_row();
+
+        self.move_cursor_vis_to(self.command_row(), 1);
+        self.print_text_w_color(color, message);
+
+        self.revert_cursor_vis_pos();
+    }
+
+    pub fn exit_command_mode<S: AsRef<str>>(&self, error: Option<S>) {
+        //! Leaves the command row and puts the cursor back in the document, error is shown once
+        //! the cursor is back
+
+        self.print_command_message(self.theme.command_text_color(), "");
+
+        self.command_buf.borrow_mut().clear();
+
+        self.revert_cursor_vis_pos();
+
+        if let Some(error) = error {
+            self.notify(Level::Error, error);
+        }
+    }
+
+    pub fn pop_command_buf(&self) {
+        self.command_buf.borrow_mut().pop();
+        self.print_text_w_color(self.theme.command_text_color(), " ");
+    }
+
+    pub fn add_file_buffer(&mut self, file_name: &str) {
+        //! Opens the file in a new buffer and shows it in the active window
+
+        let document = Document::new(file_name, (self.doc_disp_height(), self.doc_disp_width()));
+
+        self.add_buffer(document);
+    }
+
+    fn add_buffer(&mut self, document: Document) {
+        let buffer = Rc::new(RefCell::new(document));
+
+        self.file_buffers.push(Rc::clone(&buffer));
+
+        self.windows_mut().active_mut().buffer = buffer;
+    }
+
+    pub fn remove_file_buffer(&mut self) {
+        //! Closes the current buffer, see remove_buffer_at
+
+        self.remove_buffer_at(self.current_buffer_index());
+    }
+
+    fn remove_buffer_at(&mut self, ind: usize) {
+        //! Closes the buffer at ind in the buffer list, the windows showing it are given the buffer
+        //! before it, or the first one, with the cursor where it was last left in that buffer
+
+        self.store_window_state();
+
+        let removed = self.file_buffers.remove(ind);
+
+        if self.file_buffers.is_empty() {
+            self.file_buffers
+                .push(Rc::new(RefCell::new(Document::new_unnamed(
+                    self.doc_disp_height(),
+                ))));
+        }
+
+        let heir = Rc::clone(&self.file_buffers[ind.saturating_sub(1)]);
+
+        let view = heir.borrow().last_view;
+
+        for window in self.tabs.iter_mut().flat_map(Windows::iter_mut) {
+            if Rc::ptr_eq(&window.buffer, &removed) {
+                window.buffer = Rc::clone(&heir);
+                window.view = view;
+            }
+        }
+
+        self.switch_window();
+    }
+
+    pub fn current_buffer(&self) -> Rc<RefCell<Document>> {
+        Rc::clone(&self.windows().active().buffer)
+    }
 
-    pub fn check_resize(&mut self) {
-        let checker = term_size();
+    fn current_buffer_index(&self) -> usize {
+        //! Where the current buffer is in the buffer list
+        let current = self.current_buffer();
 
-        if checker.width != self.term_dimensions.width
-            || checker.height != self.term_dimensions.height
-        {
-            self.term_dimensions = checker;
+        self.file_buffers
+            .iter()
+            .position(|buffer| Rc::ptr_eq(buffer, &current))
+            .unwrap_or(0)
+    }
 
-            self.redraw_screen();
+    pub fn set_active_buffer_start(&mut self) {
+        if self.file_buffers.is_empty() {
+            self.file_buffers
+                .push(Rc::new(RefCell::new(Document::new_unnamed(
+                    self.doc_disp_height(),
+                ))));
         }
+
+        self.windows_mut().active_mut().buffer = Rc::clone(&self.file_buffers[0]);
     }
 
-    // ============================== MODE ================================
+    pub fn next_buffer(&mut self) {
+        if self.file_buffers.is_empty() {
+            return;
+        }
 
-    pub fn change_mode(&mut self, new_mode: Modes) {
-        //! curr - Current mode stored in the state of the application
-        //! new_mode - The new mode which will be stored in the state of the application
-        //! mode_row - The row at which the mode will be printed
-        //! cursor - Get control of cursor
-        //!
-        //! Changes the current mode of the editor to a new target mode, handles changing state and drawing to screen
+        let ind = (self.current_buffer_index() + 1) % self.file_buffers.len();
 
-        self.curr_mode = new_mode;
+        self.show_buffer(Rc::clone(&self.file_buffers[ind]));
+    }
 
-        self.save_cursor_vis_pos();
+    pub fn prev_buffer(&mut self) {
+        if self.file_buffers.is_empty() {
+            return;
+        }
 
-        self.move_cursor_vis_to(self.mode_row(), 0);
+        let len = self.file_buffers.len();
+        let ind = (self.current_buffer_index() + len - 1) % len;
+
+        self.show_buffer(Rc::clone(&self.file_buffers[ind]));
+    }
 
-        self.print_mode_row();
+    fn show_buffer(&mut self, buffer: Rc<RefCell<Document>>) {
+        //! Shows the buffer in the active window with the cursor where it was when the buffer was
+        //! last switched away from, the buffer shown until now becomes the alternate buffer
 
-        self.revert_cursor_vis_pos();
+        let current = self.current_buffer();
+
+        if Rc::ptr_eq(&current, &buffer) {
+            return;
+        }
+
+        self.store_window_state();
+
+        current.borrow_mut().last_view = self.windows().active().view;
+
+        self.alternate_buffer = Rc::downgrade(&current);
+
+        let view = buffer.borrow().last_view;
+
+        let window = self.windows_mut().active_mut();
+
+        window.buffer = buffer;
+        window.view = view;
+
+        self.switch_window();
     }
 
-    // ============================== COMMAND =============================
+    fn buffer_index(&self, spec: &str) -> Result<usize, String> {
+        //! Where the buffer picked out by spec is in the buffer list, see buffer_index_by_name
 
-    pub fn initialize_command_row(&self) {
-        self.save_cursor_vis_pos();
+        let names = self
+            .file_buffers
+            .iter()
+            .map(|buffer| buffer.borrow().display_name().to_string())
+            .collect::<Vec<_>>();
 
-        self.move_cursor_vis_to(self.command_row(), 1);
+        buffer_index_by_name(&names, spec)
+    }
 
-        self.apply_line_color(self.theme.background_color());
+    pub fn switch_to_buffer(&mut self, spec: &str) -> Result<(), String> {
+        //! Shows the buffer picked out by spec in the active window, see buffer_index
 
-        self.print_char(':');
+        let ind = self.buffer_index(spec)?;
 
-        self.move_cursor_vis_right();
+        self.show_buffer(Rc::clone(&self.file_buffers[ind]));
+
+        Ok(())
     }
 
-    pub fn print_command_message(&self, message: impl AsRef<str>) {
-        self.save_cursor_vis_pos();
+    pub fn new_buffer(&mut self, kind: BufferKind) {
+        //! Opens an empty buffer without a file, unnamed or scratch, in the active window
 
-        self.print_command_row();
+        let buffer = Rc::new(RefCell::new(match kind {
+            BufferKind::Scratch => Document::new_scratch(self.doc_disp_height()),
+            _ => Document::new_unnamed(self.doc_disp_height()),
+        }));
 
-        self.move_cursor_vis_to(self.command_row(), 1);
-        self.print_text_w_color(self.theme.command_text_color(), message);
+        self.file_buffers.push(Rc::clone(&buffer));
 
-        self.revert_cursor_vis_pos();
+        self.show_buffer(buffer);
     }
 
-    pub fn exit_command_mode<S: AsRef<str>>(&self, message: Option<S>) {
-        match message.as_ref() {
-            Some(m) => self.print_command_message(m),
-            None => self.print_command_message(""),
-        };
+    pub fn switch_to_alternate_buffer(&mut self) -> Result<(), String> {
+        let buffer = self
+            .alternate_buffer
+            .upgrade()
+            .ok_or_else(|| "No alternate file".to_string())?;
 
-        self.command_buf.borrow_mut().clear();
+        self.show_buffer(buffer);
 
-        self.revert_cursor_vis_pos();
+        Ok(())
     }
 
-    pub fn pop_command_buf(&self) {
-        self.command_buf.borrow_mut().pop();
-        self.print_text_w_color(self.theme.command_text_color(), " ");
+    pub fn delete_buffer(&mut self, spec: Option<&str>, force: bool) -> Result<(), String> {
+        //! Closes the buffer picked out by spec, or the current buffer without one, a buffer with
+        //! changes that have not been written is only closed when forced
+
+        let ind = match spec {
+            Some(spec) => self.buffer_index(spec)?,
+            None => self.current_buffer_index(),
+        };
+
+        if !force && self.file_buffers[ind].borrow().is_modified() {
+            return Err(format!(
+                "No write since last change for buffer {} (add ! to override)",
+                ind + 1
+            ));
+        }
+
+        self.remove_buffer_at(ind);
+
+        Ok(())
     }
 
-    pub fn add_file_buffer(&mut self, file_name: &str) {
-        if self.file_buffers.len() == 0 {
-            self.file_buffers.push(Rc::new(RefCell::new(Document::new(
-                &file_name,
+    pub fn list_buffers(&mut self) {
+        //! Shows every buffer the way :ls does, with its number, flags, name and the line the
+        //! cursor is on in it
+        //!
+        //! The flags are % for the current buffer, # for the alternate buffer, a for a buffer shown
+        //! in a window and h for one that is not, and + for a buffer with unwritten changes
+
+        self.store_window_state();
+
+        let current = self.current_buffer();
+        let alternate = self.alternate_buffer.upgrade();
+
+        let lines = self
+            .file_buffers
+            .iter()
+            .enumerate()
+            .map(|(ind, buffer)| {
+                let shown_in = self
+                    .tabs
+                    .iter()
+                    .flat_map(|windows| windows.iter())
+                    .find(|(_, window)| Rc::ptr_eq(&window.buffer, buffer))
+                    .map(|(_, window)| window.view.cursor);
+
+                let line_ind = if Rc::ptr_eq(buffer, &current) {
+                    self.windows().active().view.cursor.0
+                } else {
+                    shown_in.unwrap_or(buffer.borrow().last_view.cursor).0
+                };
+
+                let document = buffer.borrow();
+
+                let flags = format!(
+                    "{}{}{}{}",
+                    if Rc::ptr_eq(buffer, &current) {
+                        '%'
+                    } else if alternate
+                        .as_ref()
+                        .is_some_and(|alt| Rc::ptr_eq(buffer, alt))
+                    {
+                        '#'
+                    } else {
+                        ' '
+                    },
+                    if shown_in.is_some() { 'a' } else { 'h' },
+                    if document.is_read_only() { '=' } else { ' ' },
+                    if document.is_modified() { '+' } else { ' ' },
+                );
+
+                let name = format!("\"{}\"", document.display_name());
+
                 (
-                    self.term_dimensions.height - 3,
-                    (self.term_dimensions.width - self.right_edge_offset) - self.left_edge_offset,
-                ),
-            ))));
+                    Level::Info,
+                    format!("{:>3} {flags} {name:<30} line {}", ind + 1, line_ind + 1),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        self.show_message_lines(&lines);
+    }
+
+    pub fn write_current_buffer_to_file(
+        &self,
+        new_name: Option<&str>,
+        force: bool,
+    ) -> io::Result<()> {
+        //! Writes the current buffer to its file, or to new_name, force writes a read only buffer
+        //! to its own file anyway
+        //!
+        //! A read only buffer can always be written to another file
+
+        let doc_bind = Rc::clone(&self.current_buffer());
+
+        if let Some(file_name) = new_name.filter(|name| *name != doc_bind.borrow().file_name) {
+            let mut out_file = File::create(file_name)?;
+
+            out_file.write_all(&doc_bind.borrow().file_contents())?;
+
+            let kind = doc_bind.borrow().kind;
+
+            // Writing to another file leaves the buffer's own file untouched, so it stays dirty,
+            // but a buffer without a file takes the name it was first written under
+            if kind == BufferKind::Unnamed {
+                doc_bind.borrow_mut().set_file(file_name);
+
+                self.print_title();
+            }
         } else {
-            self.file_buffers.push(Rc::new(RefCell::new(Document::new(
-                &file_name,
-                (
-                    self.term_dimensions.height - 3,
-                    (self.term_dimensions.width - self.right_edge_offset) - self.left_edge_offset,
+            Self::write_buffer_to_file(&doc_bind, force)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_current_buffer_as(&self, file_name: &str) -> io::Result<()> {
+        //! Writes the current buffer to the file and makes it the buffer's file from then on
+
+        let document = self.current_buffer();
+
+        if file_name == document.borrow().file_name {
+            return self.write_current_buffer_to_file(None, false);
+        }
+
+        let mut out_file = File::create(file_name)?;
+
+        out_file.write_all(&document.borrow().file_contents())?;
+
+        document.borrow_mut().set_file(file_name);
+
+        self.print_title();
+
+        Ok(())
+    }
+
+    pub fn write_all_buffers_to_file(&self) -> io::Result<()> {
+        for document in self.file_buffers.iter() {
+            if document.borrow().is_modified() {
+                Self::write_buffer_to_file(document, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_buffer_to_file(document: &Rc<RefCell<Document>>, force: bool) -> io::Result<()> {
+        //! Writes the buffer to its own file, which a buffer that is a view of the file never is
+
+        if document.borrow().is_view_of_file() {
+            return Err(read_only_error());
+        }
+
+        if document.borrow().read_only && !force {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "\"{}\" is read only (add ! to override)",
+                    document.borrow().display_name()
                 ),
-            ))));
+            ));
+        }
+
+        if document.borrow().kind != BufferKind::File {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No file name"));
+        }
 
-            self.active_buffer = self.file_buffers.len() - 1;
+        if document.borrow().lossy && !force {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "\"{}\" had bytes that are not {}, writing replaces them (add ! to override)",
+                    document.borrow().display_name(),
+                    document.borrow().charset.name()
+                ),
+            ));
         }
+
+        let mut out_file = File::create(&document.borrow().file_name)?;
+
+        out_file.write_all(&document.borrow().file_contents())?;
+
+        let mut document = document.borrow_mut();
+
+        // The file now holds what the buffer does, so there is nothing left to lose
+        document.dirty = false;
+        document.lossy = false;
+
+        Ok(())
     }
 
-    pub fn remove_file_buffer(&mut self) {
-        self.file_buffers.remove(self.active_buffer);
+    pub fn poll_indexing(&self) {
+        //! Takes in the lines of every buffer that were indexed in the background, redrawing what
+        //! changed because of them
 
-        if self.active_buffer != 0 {
-            self.active_buffer -= 1;
-        } else {
-            self.file_buffers
-                .push(Rc::new(RefCell::new(Document::new_scratch(
-                    self.doc_disp_height(),
-                ))));
+        let document = self.current_buffer();
+
+        let mut shown_elsewhere = false;
+
+        for buffer in &self.file_buffers {
+            if Rc::ptr_eq(buffer, &document) || buffer.borrow().index_progress().is_none() {
+                continue;
+            }
+
+            let added = buffer.borrow_mut().poll_indexing();
+
+            shown_elsewhere |= added > 0
+                && self
+                    .windows()
+                    .iter()
+                    .any(|(_, window)| Rc::ptr_eq(&window.buffer, buffer));
+        }
+
+        if shown_elsewhere {
+            self.update_windows();
+        }
+
+        let old_progress = document.borrow().index_progress();
+        let old_num_rows = document.borrow().num_rows();
+
+        let added = document.borrow_mut().poll_indexing();
+
+        if added > 0 && old_num_rows < document.borrow().viewport.bottom() {
+            // The new lines are on screen
+            self.reset_editor_view();
+        } else if old_progress != document.borrow().index_progress() {
+            self.print_title();
+        }
+
+        if added > 0 {
+            // More lines may need a wider gutter
+            self.update_gutter();
         }
     }
 
-    pub fn current_buffer(&self) -> Rc<RefCell<Document>> {
-        Rc::clone(&self.file_buffers[self.active_buffer])
+    pub fn search_forward(&self, pattern: &str) -> bool {
+        //! Moves the cursor to the next match of pattern after the cursor, wrapping around to the
+        //! start of the document, returns false if there is no match anywhere
+        //!
+        //! The pattern is remembered for search_next
+
+        *self.last_search.borrow_mut() = pattern.to_string();
+
+        let line_ind = self.get_cursor_line_ind();
+        let pos = self.get_cursor_pos_in_line();
+
+        let found = self
+            .current_buffer()
+            .borrow()
+            .find(pattern, line_ind, pos + 1);
+
+        match found {
+            Some(hit) => {
+                if hit <= (line_ind, pos) {
+                    self.notify(Level::Warning, "Search hit BOTTOM, continuing at TOP");
+                }
+
+                self.move_cursor_to_line(hit.0, hit.1);
+                true
+            }
+            None => false,
+        }
     }
 
-    pub fn set_active_buffer_start(&mut self) {
-        if self.file_buffers.len() == 0 {
-            self.file_buffers
-                .push(Rc::new(RefCell::new(Document::new_scratch(
-                    self.doc_disp_height(),
-                ))));
-        } else {
-            self.active_buffer = 0;
+    pub fn search_next(&self) {
+        //! Searches for the last searched pattern again, does nothing if nothing was searched for yet
+
+        let pattern = self.last_search();
+
+        if !pattern.is_empty() && !self.search_forward(&pattern) {
+            self.notify(Level::Error, format!("Pattern not found: {pattern}"));
         }
     }
 
-    pub fn next_buffer(&mut self) {
-        if self.active_buffer + 1 == self.file_buffers.len() {
-            self.active_buffer = 0;
-        } else {
-            self.active_buffer += 1;
+    pub fn last_search(&self) -> String {
+        self.last_search.borrow().clone()
+    }
+
+    // ============================== SIGNS ===============================
+
+    pub fn register_sign_provider(&mut self, provider: Box<dyn SignProvider>) -> SignProviderId {
+        //! Adds a source of signs, the id returned is used to place and remove the provider's signs
+
+        self.sign_providers.push(provider);
+
+        SignProviderId(self.sign_providers.len() - 1)
+    }
+
+    pub fn place_sign(&self, provider: SignProviderId, line_ind: usize, glyph: char) {
+        //! Puts a sign on a line of the current buffer, replacing the sign the provider already had
+        //! there, the sign moves along with the line as the buffer is edited
+
+        self.current_buffer()
+            .borrow_mut()
+            .signs_mut()
+            .place(line_ind, PlacedSign { provider, glyph });
+
+        self.print_gutter();
+    }
+
+    pub fn unplace_sign(&self, provider: SignProviderId, line_ind: usize) {
+        self.current_buffer()
+            .borrow_mut()
+            .signs_mut()
+            .unplace(line_ind, provider);
+
+        self.print_gutter();
+    }
+
+    pub fn clear_signs(&self, provider: SignProviderId) {
+        //! Removes every sign the provider placed in the current buffer
+
+        self.current_buffer()
+            .borrow_mut()
+            .signs_mut()
+            .unplace_where(|s| s.provider == provider);
+
+        self.print_gutter();
+    }
+
+    pub fn set_bookmark(&self, letter: char) {
+        //! Puts the letter in the sign column next to the cursor's line, taking it off the line it
+        //! was on before
+
+        let document = self.current_buffer();
+
+        let line_ind = document
+            .borrow()
+            .get_index_at_cursor(self.get_cursor_doc_row());
+
+        document
+            .borrow_mut()
+            .signs_mut()
+            .unplace_where(|s| s.provider == self.bookmarks && s.glyph == letter);
+
+        self.place_sign(self.bookmarks, line_ind, letter);
+    }
+
+    pub fn delete_bookmarks(&self, letters: Option<&str>) {
+        //! Takes the given letters off their lines, or every letter if None
+
+        self.current_buffer()
+            .borrow_mut()
+            .signs_mut()
+            .unplace_where(|s| {
+                s.provider == self.bookmarks && letters.is_none_or(|l| l.contains(s.glyph))
+            });
+
+        self.print_gutter();
+    }
+
+    fn signs_in_view(&self, document: &Document) -> BTreeMap<usize, (u32, char)> {
+        //! The sign to draw for each line in view that has one, as (priority, glyph), the sign of
+        //! the provider with the highest priority wins
+
+        let (top, bottom) = (document.viewport.top(), document.viewport.bottom());
+
+        let last_row = bottom.min(document.num_rows());
+
+        if top >= last_row {
+            return BTreeMap::new();
         }
 
-        self.initialize_display();
+        let lines =
+            document.get_index_at_cursor(top)..document.get_index_at_cursor(last_row - 1) + 1;
+
+        self.signs_on_lines(document, lines)
     }
 
-    pub fn prev_buffer(&mut self) {
-        if self.active_buffer == 0 {
-            self.active_buffer = self.file_buffers.len() - 1;
-        } else {
-            self.active_buffer -= 1;
+    fn signs_on_lines(
+        &self,
+        document: &Document,
+        lines: Range<usize>,
+    ) -> BTreeMap<usize, (u32, char)> {
+        //! Like signs_in_view for the lines in the range
+
+        let mut shown: BTreeMap<usize, (u32, char)> = BTreeMap::new();
+
+        for line_ind in lines.clone() {
+            for sign in document.signs().on_line(line_ind) {
+                signs::offer(
+                    &mut shown,
+                    line_ind,
+                    self.sign_providers[sign.provider.0].priority(),
+                    sign.glyph,
+                );
+            }
         }
 
-        self.initialize_display();
+        for provider in &self.sign_providers {
+            for (line_ind, glyph) in provider.signs(document, lines.clone()) {
+                signs::offer(&mut shown, line_ind, provider.priority(), glyph);
+            }
+        }
+
+        shown
     }
 
-    pub fn write_current_buffer_to_file(&self, new_name: Option<&str>) {
-        if let Some(file_name) = new_name {
-            let mut out_file = File::create(file_name).unwrap();
+    fn sign_column_width(&self, document: &Document) -> usize {
+        //! The sign column is only shown while the document has signs placed in it or a provider
+        //! has signs for the lines in view
 
-            out_file
-                .write(self.current_buffer().borrow().to_string().as_bytes())
-                .unwrap();
+        if document.signs().is_empty() && self.signs_in_view(document).is_empty() {
+            0
         } else {
-            let doc_bind = Rc::clone(&self.current_buffer());
+            SIGN_COLUMN_WIDTH
+        }
+    }
+
+    pub fn current_buffer_read_only(&self) -> bool {
+        self.current_buffer().borrow().is_read_only()
+    }
+
+    pub fn view_file(&mut self, file_name: Option<&str>) {
+        //! Opens the file in a new buffer like :o but read only, or makes the current buffer read
+        //! only when no file is given
+
+        if let Some(file_name) = file_name {
+            let document =
+                Document::open_view(file_name, (self.doc_disp_height(), self.doc_disp_width()));
+
+            self.add_buffer(document);
+
+            self.switch_window();
+        }
+
+        self.current_buffer().borrow_mut().read_only = true;
+
+        self.print_title();
+    }
+
+    pub fn warn_if_lossy(&self) {
+        //! Tells the user when the current buffer's file had bytes that could not be read, as they
+        //! were replaced and writing the file back would lose them
+
+        let document = self.current_buffer();
+        let document = document.borrow();
+
+        if document.lossy {
+            self.notify(
+                Level::Warning,
+                format!(
+                    "\"{}\" has bytes that are not {}, they were replaced",
+                    document.display_name(),
+                    document.charset.name()
+                ),
+            );
+        }
+    }
+
+    pub fn is_view_mode(&self) -> bool {
+        self.view_mode
+    }
+
+    pub fn current_buffer_dirty(&self) -> bool {
+        self.current_buffer().borrow().is_modified()
+    }
 
-            let document = doc_bind.borrow();
+    pub fn any_buffer_dirty(&self) -> bool {
+        self.file_buffers.iter().any(|b| b.borrow().is_modified())
+    }
 
-            let mut out_file = File::create(&document.file_name).unwrap();
+    pub fn dirty_buffer_name(&self) -> Option<String> {
+        //! The name of a buffer with unsaved changes, the current buffer's before any other
 
-            out_file.write(document.to_string().as_bytes()).unwrap();
+        if self.current_buffer_dirty() {
+            return Some(self.current_buffer().borrow().display_name().to_string());
         }
+
+        self.file_buffers
+            .iter()
+            .find(|b| b.borrow().is_modified())
+            .map(|b| b.borrow().display_name().to_string())
+    }
+}
+
+fn read_only_error() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "buffer is read only")
+}
+
+fn buffer_index_by_name(names: &[String], spec: &str) -> Result<usize, String> {
+    //! Where the buffer with the number in spec is among the buffers named names, or the buffer
+    //! whose name is spec or the only one whose name contains it
+
+    if let Ok(number) = spec.parse::<usize>() {
+        return match number {
+            1.. if number <= names.len() => Ok(number - 1),
+            _ => Err(format!("Buffer {number} does not exist")),
+        };
+    }
+
+    if let Some(ind) = names.iter().position(|name| name == spec) {
+        return Ok(ind);
+    }
+
+    let mut matches = (0..names.len()).filter(|ind| names[*ind].contains(spec));
+
+    match (matches.next(), matches.next()) {
+        (Some(ind), None) => Ok(ind),
+        (Some(_), Some(_)) => Err(format!("More than one match for {spec}")),
+        (None, _) => Err(format!("No matching buffer for {spec}")),
     }
 }
 
 impl Drop for Editor {
     fn drop(&mut self) {
+        self.add_to_draw_buf(cursor_shape_default());
         self.add_to_draw_buf(return_to_normal_buf());
 
         self.flush_pen();
@@ -766,3 +3432,48 @@ pub fn spawn_char_channel() -> Receiver<char> {
 
     to_use
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["src/main.rs", "src/editor.rs", "README.md", "[No Name]"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn buffers_are_picked_by_number() {
+        assert_eq!(buffer_index_by_name(&names(), "1"), Ok(0));
+        assert_eq!(buffer_index_by_name(&names(), "4"), Ok(3));
+        assert!(buffer_index_by_name(&names(), "0").is_err());
+        assert!(buffer_index_by_name(&names(), "5").is_err());
+    }
+
+    #[test]
+    fn buffers_are_picked_by_name() {
+        assert_eq!(buffer_index_by_name(&names(), "README.md"), Ok(2));
+        assert_eq!(buffer_index_by_name(&names(), "edit"), Ok(1));
+        assert_eq!(buffer_index_by_name(&names(), "[No Name]"), Ok(3));
+    }
+
+    #[test]
+    fn names_matching_several_buffers_are_refused() {
+        assert_eq!(
+            buffer_index_by_name(&names(), "src/"),
+            Err("More than one match for src/".to_string())
+        );
+        assert_eq!(
+            buffer_index_by_name(&names(), "lib.rs"),
+            Err("No matching buffer for lib.rs".to_string())
+        );
+    }
+
+    #[test]
+    fn a_whole_name_wins_over_names_containing_it() {
+        let names = ["a.rs", "ba.rs"].map(String::from).to_vec();
+
+        assert_eq!(buffer_index_by_name(&names, "a.rs"), Ok(0));
+    }
+}
@@ -0,0 +1,503 @@
+use crate::document::{Charset, LineEnding};
+use std::fs;
+use std::path::{self, Path};
+
+// Files are given their settings by the .editorconfig files in their directory and every directory
+// above it, up to one that says root = true. An .editorconfig is made of sections headed by a glob,
+// [*.rs] for example, and a file takes the properties of every section whose glob matches it. Later
+// sections win over earlier ones, and files closer to the file win over those further up.
+//
+// A glob without a / matches the file's name, one with a / matches its path from the directory the
+// .editorconfig is in. * matches anything but a /, ** matches anything, ? matches one character,
+// [abc] and [!abc] match one of or none of the characters, {a,b} matches either text and {1..10} a
+// number in the range. Properties and values that are not known are left out, and unset takes back
+// a property set further up.
+
+const FILE_NAME: &str = ".editorconfig";
+
+/// Whether a file is indented with spaces or with tabs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Space,
+    Tab,
+}
+
+/// The columns of one level of indentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentSize {
+    Columns(usize),
+    /// As wide as a tab
+    Tab,
+}
+
+/// The properties given to a file, None for those no section gives
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<IndentSize>,
+    pub tab_width: Option<usize>,
+    /// end_of_line = cr is left out, lines are only ever ended with \n or \r\n
+    pub end_of_line: Option<LineEnding>,
+    pub charset: Option<Charset>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    /// 0 for max_line_length = off
+    pub max_line_length: Option<usize>,
+}
+
+impl Properties {
+    pub fn for_file(file_name: &str) -> Self {
+        //! The properties the .editorconfig files above the file give it, the file does not have to
+        //! be there yet
+
+        let mut properties = Properties::default();
+
+        let Ok(path) = path::absolute(file_name) else {
+            return properties;
+        };
+
+        // Read from the closest directory up, then taken in from the furthest down
+        let mut configs = Vec::new();
+
+        for dir in path.ancestors().skip(1) {
+            let Ok(text) = fs::read_to_string(dir.join(FILE_NAME)) else {
+                continue;
+            };
+
+            let config = Config::parse(&text);
+            let root = config.root;
+
+            configs.push((dir, config));
+
+            if root {
+                break;
+            }
+        }
+
+        for (dir, config) in configs.iter().rev() {
+            config.apply_to(&mut properties, dir, &path);
+        }
+
+        properties
+    }
+
+    pub fn tab_width(&self) -> Option<usize> {
+        //! The columns between tab stops, the indent size when it is not given
+        match (self.tab_width, self.indent_size) {
+            (Some(width), _) | (None, Some(IndentSize::Columns(width))) => Some(width),
+            _ => None,
+        }
+    }
+
+    pub fn indent_width(&self) -> Option<usize> {
+        //! The columns of a level of indentation, the tab width for indent_size = tab
+        match self.indent_size {
+            Some(IndentSize::Columns(width)) => Some(width),
+            Some(IndentSize::Tab) => self.tab_width,
+            None => None,
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.to_lowercase();
+        let value = value.as_str();
+
+        match key {
+            "indent_style" => update(&mut self.indent_style, value, |value| match value {
+                "space" => Some(IndentStyle::Space),
+                "tab" => Some(IndentStyle::Tab),
+                _ => None,
+            }),
+            "indent_size" => update(&mut self.indent_size, value, |value| match value {
+                "tab" => Some(IndentSize::Tab),
+                _ => number(value).map(IndentSize::Columns),
+            }),
+            "tab_width" => update(&mut self.tab_width, value, number),
+            "end_of_line" => update(&mut self.end_of_line, value, |value| match value {
+                "lf" => Some(LineEnding::Lf),
+                "crlf" => Some(LineEnding::CrLf),
+                _ => None,
+            }),
+            "charset" => update(&mut self.charset, value, Charset::from_name),
+            "trim_trailing_whitespace" => update(&mut self.trim_trailing_whitespace, value, flag),
+            "insert_final_newline" => update(&mut self.insert_final_newline, value, flag),
+            "max_line_length" => update(&mut self.max_line_length, value, |value| match value {
+                "off" => Some(0),
+                _ => number(value),
+            }),
+            _ => {}
+        }
+    }
+}
+
+fn update<T>(property: &mut Option<T>, value: &str, parse: impl Fn(&str) -> Option<T>) {
+    //! Sets the property to the value, unless it is not one the property takes
+
+    if value == "unset" {
+        *property = None;
+    } else if let Some(value) = parse(value) {
+        *property = Some(value);
+    }
+}
+
+fn number(value: &str) -> Option<usize> {
+    value.parse().ok().filter(|number| *number > 0)
+}
+
+fn flag(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// What one .editorconfig says
+#[derive(Debug, Default)]
+struct Config {
+    /// Whether the .editorconfig files further up are left out
+    root: bool,
+    /// The glob of each section along with its properties, in the order they are written
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Config {
+    fn parse(text: &str) -> Self {
+        //! Reads the .editorconfig a line at a time, lines that are not a section header or a
+        //! property are left out
+
+        let mut config = Config::default();
+
+        for line in text.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+
+            if let Some(glob) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                config.sections.push((glob.to_string(), Vec::new()));
+
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_string();
+
+            match config.sections.last_mut() {
+                Some((_, properties)) => properties.push((key, value)),
+                // Before the first section is the only place root can be set
+                None if key == "root" => config.root = value.eq_ignore_ascii_case("true"),
+                None => {}
+            }
+        }
+
+        config
+    }
+
+    fn apply_to(&self, properties: &mut Properties, dir: &Path, path: &Path) {
+        //! Sets the properties of every section that matches the file at path, dir being where the
+        //! .editorconfig is
+
+        let Ok(relative) = path.strip_prefix(dir) else {
+            return;
+        };
+
+        let relative = relative.to_string_lossy();
+        let file_name = relative.rsplit('/').next().unwrap_or(&relative);
+
+        for (glob, section) in &self.sections {
+            let matched = match glob.strip_prefix('/') {
+                Some(glob) => glob_matches(glob, &relative),
+                None if glob.contains('/') => glob_matches(glob, &relative),
+                None => glob_matches(glob, file_name),
+            };
+
+            if matched {
+                for (key, value) in section {
+                    properties.set(key, value);
+                }
+            }
+        }
+    }
+}
+
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    matches_from(&glob, &text)
+}
+
+fn matches_from(glob: &[char], text: &[char]) -> bool {
+    //! Whether the whole of text is matched by the whole of glob, trying each way a * or a {a,b} can
+    //! be matched in turn
+
+    let Some((&first, rest)) = glob.split_first() else {
+        return text.is_empty();
+    };
+
+    // The first character of text, as long as it is not a /
+    let next = text.first().filter(|c| **c != '/');
+
+    let literal = |c: char| text.first() == Some(&c) && matches_from(rest, &text[1..]);
+
+    match first {
+        '*' if rest.first() == Some(&'*') => {
+            (0..=text.len()).any(|skip| matches_from(&rest[1..], &text[skip..]))
+        }
+        '*' => (0..=text.len())
+            .take_while(|skip| !text[..*skip].contains(&'/'))
+            .any(|skip| matches_from(rest, &text[skip..])),
+        '?' => next.is_some() && matches_from(rest, &text[1..]),
+        '[' => match class(rest) {
+            Some((class, negated, after)) => {
+                next.is_some_and(|c| in_class(class, *c) != negated)
+                    && matches_from(after, &text[1..])
+            }
+            None => literal('['),
+        },
+        '{' => match braces(rest) {
+            Some((alternatives, after)) if alternatives.len() > 1 => alternatives
+                .iter()
+                .any(|alternative| matches_from(&[alternative.as_slice(), after].concat(), text)),
+            Some((alternatives, after)) => match number_range(&alternatives[0]) {
+                Some(range) => {
+                    let len = text
+                        .iter()
+                        .enumerate()
+                        .take_while(|(ind, c)| c.is_ascii_digit() || (*ind == 0 && **c == '-'))
+                        .count();
+
+                    text[..len]
+                        .iter()
+                        .collect::<String>()
+                        .parse::<i64>()
+                        .is_ok_and(|number| range.contains(&number))
+                        && matches_from(after, &text[len..])
+                }
+                // A { that holds a single text is matched as it is, as is one that is never closed
+                None => literal('{'),
+            },
+            None => literal('{'),
+        },
+        '\\' if !rest.is_empty() => {
+            text.first() == Some(&rest[0]) && matches_from(&rest[1..], &text[1..])
+        }
+        c => literal(c),
+    }
+}
+
+fn class(glob: &[char]) -> Option<(&[char], bool, &[char])> {
+    //! The characters of a [...] that glob starts just after, whether it starts with ! and the rest
+    //! of glob after it, None when it is never closed
+
+    let (negated, glob) = match glob.strip_prefix(&['!']) {
+        Some(glob) => (true, glob),
+        None => (false, glob),
+    };
+
+    // A ] straight after the [ is one of the characters
+    let end = glob.iter().skip(1).position(|c| *c == ']')? + 1;
+
+    Some((&glob[..end], negated, &glob[end + 1..]))
+}
+
+fn in_class(class: &[char], c: char) -> bool {
+    //! Whether c is one of the characters of the class, a-z standing for every character between
+    //! them
+
+    let mut ind = 0;
+
+    while ind < class.len() {
+        if ind + 2 < class.len() && class[ind + 1] == '-' {
+            if (class[ind]..=class[ind + 2]).contains(&c) {
+                return true;
+            }
+
+            ind += 3;
+        } else {
+            if class[ind] == c {
+                return true;
+            }
+
+            ind += 1;
+        }
+    }
+
+    false
+}
+
+fn braces(glob: &[char]) -> Option<(Vec<Vec<char>>, &[char])> {
+    //! The texts separated by commas in a {...} that glob starts just after along with the rest of
+    //! glob after it, None when it is never closed
+
+    let mut alternatives = vec![Vec::new()];
+    let mut depth = 0;
+    let mut chars = glob.iter().enumerate();
+
+    while let Some((ind, c)) = chars.next() {
+        match c {
+            '}' if depth == 0 => return Some((alternatives, &glob[ind + 1..])),
+            ',' if depth == 0 => {
+                alternatives.push(Vec::new());
+
+                continue;
+            }
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '\\' => {
+                // An escaped character is kept escaped, to be matched as it is
+                alternatives.last_mut()?.push('\\');
+
+                let (_, escaped) = chars.next()?;
+
+                alternatives.last_mut()?.push(*escaped);
+
+                continue;
+            }
+            _ => {}
+        }
+
+        alternatives.last_mut()?.push(*c);
+    }
+
+    None
+}
+
+fn number_range(text: &[char]) -> Option<std::ops::RangeInclusive<i64>> {
+    //! The numbers from a to b of a {a..b}
+
+    let text = text.iter().collect::<String>();
+    let (from, to) = text.split_once("..")?;
+
+    Some(from.parse().ok()?..=to.parse().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_names() {
+        assert!(glob_matches("*.rs", "main.rs"));
+        assert!(!glob_matches("*.rs", "main.rsx"));
+        assert!(!glob_matches("*.rs", "src/main.rs"));
+        assert!(glob_matches("**.rs", "src/main.rs"));
+        assert!(glob_matches("src/**/*.rs", "src/a/b/main.rs"));
+        assert!(glob_matches("?.c", "a.c"));
+        assert!(!glob_matches("?.c", "/.c"));
+        assert!(!glob_matches("?.c", "ab.c"));
+    }
+
+    #[test]
+    fn globs_match_classes_and_braces() {
+        assert!(glob_matches("[ab].c", "b.c"));
+        assert!(!glob_matches("[ab].c", "c.c"));
+        assert!(glob_matches("[!ab].c", "c.c"));
+        assert!(!glob_matches("[!ab].c", "a.c"));
+        assert!(glob_matches("[a-c]x", "bx"));
+        assert!(glob_matches("*.{js,ts}", "main.ts"));
+        assert!(!glob_matches("*.{js,ts}", "main.rs"));
+        assert!(glob_matches("{a,{b,c}}.md", "c.md"));
+        assert!(glob_matches("file{1..10}.txt", "file10.txt"));
+        assert!(!glob_matches("file{1..10}.txt", "file11.txt"));
+        assert!(glob_matches("{-5..5}", "-3"));
+    }
+
+    #[test]
+    fn globs_match_escapes_and_unclosed_brackets_as_they_are() {
+        assert!(glob_matches("\\*.c", "*.c"));
+        assert!(!glob_matches("\\*.c", "a.c"));
+        assert!(glob_matches("{a\\,b,c}", "a,b"));
+        assert!(glob_matches("[ab", "[ab"));
+        assert!(glob_matches("{ab", "{ab"));
+        assert!(glob_matches("{ab}", "{ab}"));
+    }
+
+    const CONFIG: &str = "\
+root = true
+
+[*]
+indent_style = space
+indent_size = 4
+
+# Later sections win
+[src/*.rs]
+indent_size = 2
+
+[/Makefile]
+indent_style = tab
+
+[*.txt]
+indent_size = unset
+charset = klingon
+";
+
+    fn properties(path: &str) -> Properties {
+        let mut properties = Properties::default();
+
+        Config::parse(CONFIG).apply_to(&mut properties, Path::new("/project"), Path::new(path));
+
+        properties
+    }
+
+    #[test]
+    fn sections_are_read_in_order() {
+        let config = Config::parse(CONFIG);
+
+        assert!(config.root);
+        assert_eq!(
+            config
+                .sections
+                .iter()
+                .map(|(glob, _)| glob.as_str())
+                .collect::<Vec<_>>(),
+            vec!["*", "src/*.rs", "/Makefile", "*.txt"]
+        );
+        assert!(!Config::parse("[*]\nroot = true").root);
+    }
+
+    #[test]
+    fn later_sections_that_match_win() {
+        let rust = properties("/project/src/main.rs");
+
+        assert_eq!(rust.indent_style, Some(IndentStyle::Space));
+        assert_eq!(rust.indent_size, Some(IndentSize::Columns(2)));
+
+        // A glob with a / matches the path from the .editorconfig's directory
+        let deeper = properties("/project/lib/src/main.rs");
+
+        assert_eq!(deeper.indent_size, Some(IndentSize::Columns(4)));
+
+        assert_eq!(
+            properties("/project/Makefile").indent_style,
+            Some(IndentStyle::Tab)
+        );
+        assert_eq!(
+            properties("/project/sub/Makefile").indent_style,
+            Some(IndentStyle::Space)
+        );
+    }
+
+    #[test]
+    fn unset_takes_back_a_property_and_unknown_values_are_left_out() {
+        let text = properties("/project/notes.txt");
+
+        assert_eq!(text.indent_size, None);
+        assert_eq!(text.indent_style, Some(IndentStyle::Space));
+        assert_eq!(text.charset, None);
+    }
+
+    #[test]
+    fn files_outside_the_directory_are_not_matched() {
+        assert_eq!(properties("/elsewhere/main.rs"), Properties::default());
+    }
+}
@@ -1,73 +1,386 @@
-use crate::document::Line;
 use std::fmt;
 
+// The smallest gap left after the buffer grows, so that a run of insertions does not have to grow
+// it again straight away
+const MIN_GAP: usize = 64;
+
+/// A gap buffer holding text being edited, the text is split in two around a gap of unused space
+/// so that inserting and deleting at the gap only has to touch the edges of the gap
+///
+/// The gap can be moved anywhere in the text and the text may span several lines, the number of
+/// new line characters is tracked as the text changes so line counts are always at hand
 pub struct GapBuf {
-    pub lhs: Vec<char>,
-    pub rhs: String,
+    buf: Vec<char>,
+    gap_start: usize,
+    gap_end: usize,
+    /// Number of new line characters in the whole text
+    newlines: usize,
+    /// Number of new line characters before the gap, i.e. the line the gap is on
+    gap_line: usize,
 }
 
 impl GapBuf {
     pub fn new() -> Self {
         Self {
-            lhs: Vec::new(),
-            rhs: "".to_string(),
+            buf: vec!['\0'; MIN_GAP],
+            gap_start: 0,
+            gap_end: MIN_GAP,
+            newlines: 0,
+            gap_line: 0,
         }
     }
 
     pub fn from_str(src: String, ind: usize) -> Self {
-        //! ind refers to the index at which to split the source string
+        //! ind refers to the index at which to split the source string, this is where the gap is
+        //! placed
 
         let (lhs, rhs) = src.split_at(ind);
 
-        Self {
-            lhs: lhs.chars().collect(),
-            rhs: rhs.to_owned(),
-        }
+        let mut new = Self::new();
+
+        new.insert_str(lhs);
+        new.insert_str(rhs);
+        new.move_gap(lhs.chars().count());
+
+        new
     }
 
-    pub fn from_line(src: &Line, ind: usize) -> Self {
-        let (lhs, rhs) = src.1.split_at(ind);
+    // =================== Editing At The Gap ===================
 
-        Self {
-            lhs: lhs.chars().collect(),
-            rhs: rhs.to_owned(),
+    pub fn insert(&mut self, c: char) {
+        //! Inserts c just before the gap
+
+        if self.gap_start == self.gap_end {
+            self.grow();
+        }
+
+        self.buf[self.gap_start] = c;
+        self.gap_start += 1;
+
+        if c == '\n' {
+            self.newlines += 1;
+            self.gap_line += 1;
         }
     }
 
-    pub fn insert(&mut self, c: char) {
-        self.lhs.push(c);
+    pub fn insert_str(&mut self, s: &str) {
+        s.chars().for_each(|c| self.insert(c));
+    }
+
+    pub fn pop(&mut self) -> Option<char> {
+        //! Removes the character just before the gap
+
+        if self.gap_start == 0 {
+            return None;
+        }
+
+        self.gap_start -= 1;
+
+        let c = self.buf[self.gap_start];
+
+        if c == '\n' {
+            self.newlines -= 1;
+            self.gap_line -= 1;
+        }
+
+        Some(c)
     }
 
-    pub fn pop(&mut self) {
-        self.lhs.pop();
-        self.lhs.shrink_to_fit();
+    pub fn delete(&mut self) -> Option<char> {
+        //! Removes the character just after the gap
+
+        if self.gap_end == self.buf.len() {
+            return None;
+        }
+
+        let c = self.buf[self.gap_end];
+
+        self.gap_end += 1;
+
+        if c == '\n' {
+            self.newlines -= 1;
+        }
+
+        Some(c)
     }
 
-    pub fn pop_tab(&mut self) {
-        for _ in 0..4 {
-            self.lhs.pop();
+    // =================== Moving The Gap ===================
+
+    pub fn move_gap(&mut self, pos: usize) {
+        //! Moves the gap so that it sits just before the character at pos, only the characters
+        //! between the old and new position of the gap are moved
+
+        let pos = pos.min(self.len());
+
+        while self.gap_start > pos {
+            self.gap_start -= 1;
+            self.gap_end -= 1;
+            self.buf[self.gap_end] = self.buf[self.gap_start];
+
+            if self.buf[self.gap_end] == '\n' {
+                self.gap_line -= 1;
+            }
+        }
+
+        while self.gap_start < pos {
+            self.buf[self.gap_start] = self.buf[self.gap_end];
+
+            if self.buf[self.gap_start] == '\n' {
+                self.gap_line += 1;
+            }
+
+            self.gap_start += 1;
+            self.gap_end += 1;
         }
-        self.lhs.shrink_to_fit();
     }
 
+    fn grow(&mut self) {
+        //! Doubles the space of the buffer, moving the text after the gap to the new end
+
+        let old_len = self.buf.len();
+        let new_len = (old_len * 2).max(old_len + MIN_GAP);
+        let rhs_len = old_len - self.gap_end;
+
+        self.buf.resize(new_len, '\0');
+        self.buf
+            .copy_within(self.gap_end..old_len, new_len - rhs_len);
+
+        self.gap_end = new_len - rhs_len;
+    }
+
+    // =================== Information Retrieval ===================
+
     pub fn len(&self) -> usize {
-        self.lhs.iter().count() + self.rhs.len()
+        //! Number of characters held, not counting the gap
+        self.buf.len() - (self.gap_end - self.gap_start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn gap_pos(&self) -> usize {
+        //! Number of characters before the gap
+        self.gap_start
+    }
+
+    pub fn line_count(&self) -> usize {
+        //! Number of lines in the text, an empty buffer still holds one empty line
+        self.newlines + 1
+    }
+
+    pub fn gap_line(&self) -> usize {
+        //! The line the gap is on
+        self.gap_line
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = &char> {
+        self.buf[..self.gap_start]
+            .iter()
+            .chain(self.buf[self.gap_end..].iter())
+    }
+
+    pub fn line(&self, line_ind: usize) -> String {
+        //! The content of the line at line_ind, without its new line character
+
+        self.chars()
+            .collect::<String>()
+            .split('\n')
+            .nth(line_ind)
+            .unwrap_or("")
+            .to_string()
+    }
+
+    pub fn gap_line_pieces(&self) -> (String, String) {
+        //! The content of the line the gap is on split at the gap, only the characters around the
+        //! gap up to the closest new line characters are looked at
+
+        let lhs = self.buf[..self.gap_start]
+            .iter()
+            .rev()
+            .take_while(|c| **c != '\n')
+            .count();
+
+        let rhs = self.buf[self.gap_end..]
+            .iter()
+            .take_while(|c| **c != '\n')
+            .count();
+
+        (
+            self.buf[(self.gap_start - lhs)..self.gap_start]
+                .iter()
+                .collect(),
+            self.buf[self.gap_end..(self.gap_end + rhs)]
+                .iter()
+                .collect(),
+        )
+    }
+
+    pub fn gap_line_len(&self) -> usize {
+        //! Number of characters in the line the gap is on, found without collecting the line
+
+        let lhs = self.buf[..self.gap_start]
+            .iter()
+            .rev()
+            .take_while(|c| **c != '\n')
+            .count();
+
+        let rhs = self.buf[self.gap_end..]
+            .iter()
+            .take_while(|c| **c != '\n')
+            .count();
+
+        lhs + rhs
+    }
+
+    pub fn gap_line_contains(&self, pred: impl Fn(char) -> bool) -> bool {
+        //! Whether a character matching pred is in the line the gap is on, found without collecting
+        //! the line
+
+        self.buf[..self.gap_start]
+            .iter()
+            .rev()
+            .take_while(|x| **x != '\n')
+            .any(|x| pred(*x))
+            || self.buf[self.gap_end..]
+                .iter()
+                .take_while(|x| **x != '\n')
+                .any(|x| pred(*x))
+    }
+
+    pub fn gap_line_str(&self) -> String {
+        //! The content of the line the gap is on
+        let (lhs, rhs) = self.gap_line_pieces();
+        lhs + rhs.as_str()
+    }
+
+    pub fn line_lens(&self) -> Vec<usize> {
+        //! The length in bytes of every line, found without collecting the lines
+
+        let mut lens = vec![0];
+
+        for c in self.chars() {
+            if *c == '\n' {
+                lens.push(0);
+            } else {
+                *lens.last_mut().unwrap() += c.len_utf8();
+            }
+        }
+
+        lens
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.collect_to_string()
+            .split('\n')
+            .map(|l| l.to_string())
+            .collect()
     }
 
     pub fn collect_to_string(&self) -> String {
-        // self.lhs.iter().chain(self.rhs.iter()).collect()
-        self.lhs.iter().collect::<String>() + self.rhs.as_str()
+        self.chars().collect()
     }
 
     pub fn collect_to_pieces(&self) -> (String, String) {
-        let lhs = self.lhs.iter().collect::<String>();
-        let rhs = self.rhs.to_owned();
+        let lhs = self.buf[..self.gap_start].iter().collect::<String>();
+        let rhs = self.buf[self.gap_end..].iter().collect::<String>();
         (lhs, rhs)
     }
 }
 
+impl Default for GapBuf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl fmt::Display for GapBuf {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}{}", self.lhs.iter().collect::<String>(), self.rhs)
+        write!(f, "{}", self.collect_to_string())
+    }
+}
+
+impl fmt::Debug for GapBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (lhs, rhs) = self.collect_to_pieces();
+
+        f.debug_struct("GapBuf")
+            .field("lhs", &lhs)
+            .field("rhs", &rhs)
+            .field("gap", &(self.gap_end - self.gap_start))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_line_splits_the_line() {
+        let mut buf = GapBuf::from_str("hello world".to_string(), 5);
+
+        buf.insert('\n');
+
+        assert_eq!(buf.lines(), vec!["hello", " world"]);
+        assert_eq!(buf.line_count(), 2);
+        assert_eq!(buf.gap_line(), 1);
+        assert_eq!(buf.gap_line_pieces(), (String::new(), " world".to_string()));
+    }
+
+    #[test]
+    fn removing_a_new_line_joins_the_lines() {
+        let mut buf = GapBuf::from_str("hello\nworld".to_string(), 6);
+
+        assert_eq!(buf.pop(), Some('\n'));
+
+        assert_eq!(buf.lines(), vec!["helloworld"]);
+        assert_eq!(buf.line_count(), 1);
+        assert_eq!(buf.gap_line(), 0);
+        assert_eq!(buf.gap_line_str(), "helloworld");
+
+        let mut buf = GapBuf::from_str("hello\nworld".to_string(), 5);
+
+        assert_eq!(buf.delete(), Some('\n'));
+
+        assert_eq!(buf.lines(), vec!["helloworld"]);
+        assert_eq!(buf.line_count(), 1);
+        assert_eq!(buf.gap_line(), 0);
+    }
+
+    #[test]
+    fn moving_the_gap_follows_the_lines() {
+        let mut buf = GapBuf::from_str("one\ntwo\nthree".to_string(), 0);
+
+        buf.move_gap(9);
+
+        assert_eq!(buf.gap_line(), 2);
+        assert_eq!(buf.gap_line_pieces(), ("t".to_string(), "hree".to_string()));
+        assert_eq!(buf.gap_line_len(), 5);
+
+        buf.move_gap(2);
+
+        assert_eq!(buf.gap_line(), 0);
+        assert_eq!(buf.gap_line_str(), "one");
+        assert_eq!(buf.collect_to_string(), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn growing_keeps_the_text_after_the_gap() {
+        let mut buf = GapBuf::from_str("end".to_string(), 0);
+
+        let long = "x".repeat(MIN_GAP * 3);
+        buf.insert_str(&long);
+
+        assert_eq!(buf.collect_to_string(), long + "end");
+        assert_eq!(buf.gap_pos(), MIN_GAP * 3);
+    }
+
+    #[test]
+    fn line_lengths_are_counted_in_bytes() {
+        let buf = GapBuf::from_str("héllo\n\nwörld!".to_string(), 3);
+
+        assert_eq!(buf.line_lens(), vec![6, 0, 7]);
+        assert_eq!(GapBuf::new().line_lens(), vec![0]);
     }
 }
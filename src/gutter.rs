@@ -0,0 +1,76 @@
+// The gutter is the column to the left of the document display window where line numbers are drawn.
+// Only the first row of each line gets a number, the rows a line wraps onto are left blank.
+
+/// The way line numbers are drawn, worked out from the number and relativenumber options the same
+/// way vim does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineNumbers {
+    Off,
+    /// Every line shows its own number
+    Absolute,
+    /// Every line shows how far away it is from the cursor's line
+    Relative,
+    /// Like Relative, but the cursor's line shows its own number
+    Hybrid,
+}
+
+#[derive(Debug, Default)]
+pub struct Gutter {
+    pub number: bool,
+    pub relative_number: bool,
+}
+
+// The fewest columns used for the numbers themselves, so that the gutter does not change width
+// every time a small document grows past another power of ten
+const MIN_NUMBER_WIDTH: usize = 3;
+
+impl Gutter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line_numbers(&self) -> LineNumbers {
+        match (self.number, self.relative_number) {
+            (false, false) => LineNumbers::Off,
+            (true, false) => LineNumbers::Absolute,
+            (false, true) => LineNumbers::Relative,
+            (true, true) => LineNumbers::Hybrid,
+        }
+    }
+
+    pub fn width(&self, num_lines: usize) -> usize {
+        //! Number of columns the gutter takes up for a document with num_lines lines, this is wide
+        //! enough for the biggest line number plus a space separating it from the document
+
+        if self.line_numbers() == LineNumbers::Off {
+            return 0;
+        }
+
+        let digits = num_lines.max(1).ilog10() as usize + 1;
+
+        digits.max(MIN_NUMBER_WIDTH) + 1
+    }
+
+    pub fn label(&self, line_ind: Option<usize>, cursor_line: usize, width: usize) -> String {
+        //! The text drawn in the gutter for a row, line_ind is the index of the line the row
+        //! begins, or None for rows that a line wrapped onto
+        //!
+        //! The result is always width characters long
+
+        let number_width = width.saturating_sub(1);
+
+        let Some(line_ind) = line_ind else {
+            return " ".repeat(width);
+        };
+
+        let distance = line_ind.abs_diff(cursor_line);
+
+        match self.line_numbers() {
+            LineNumbers::Off => String::new(),
+            LineNumbers::Absolute => format!("{:>number_width$} ", line_ind + 1),
+            LineNumbers::Relative => format!("{:>number_width$} ", distance),
+            LineNumbers::Hybrid if distance == 0 => format!("{:<number_width$} ", line_ind + 1),
+            LineNumbers::Hybrid => format!("{:>number_width$} ", distance),
+        }
+    }
+}
@@ -0,0 +1,290 @@
+// With autoindent on a new line starts as far in as the line it was made from. Smartindent adds a
+// level after a line that opens a block, one ending in a bracket or a colon, and takes a level off a
+// line that starts by closing one. The same rules are used by = to indent lines again, while >> and
+// << shift lines by a level. Indentation is measured in columns and put back as spaces, or with
+// expandtab off as tabs followed by as few spaces as it takes.
+//
+// Tab and backspace in insert mode move between stops softtabstop columns apart while they are in
+// whitespace, putting in or taking out as much whitespace as that takes.
+
+use crate::wrap::DEFAULT_TABSTOP;
+
+/// Characters that open a block when they end a line
+const OPENERS: [char; 4] = ['{', '(', '[', ':'];
+/// Characters that close a block when they start a line
+const CLOSERS: [char; 3] = ['}', ')', ']'];
+
+/// How the lines of a buffer are indented, set per buffer with :set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndentSettings {
+    /// How many columns apart tab stops are, the same as the buffer's wrap_style.tabstop
+    pub tabstop: usize,
+    /// The columns one level of indentation takes
+    pub shiftwidth: usize,
+    /// Whether indentation and the tab key put in spaces rather than tab characters
+    pub expandtab: bool,
+    /// How many columns apart the stops tab and backspace move between are, 0 for a tab key that
+    /// puts in a single tab and a backspace that takes out a single character
+    pub softtabstop: usize,
+    /// Whether a new line starts with the indentation of the line it was made from
+    pub autoindent: bool,
+    /// Whether a level is added after a line that opens a block and taken off a line that closes
+    /// one
+    pub smartindent: bool,
+}
+
+impl Default for IndentSettings {
+    fn default() -> Self {
+        Self {
+            tabstop: DEFAULT_TABSTOP,
+            shiftwidth: 4,
+            expandtab: true,
+            softtabstop: 4,
+            autoindent: true,
+            smartindent: true,
+        }
+    }
+}
+
+impl IndentSettings {
+    pub fn columns(&self, line: &str) -> usize {
+        //! How far in the text of the line starts
+
+        self.width(leading_whitespace(line))
+    }
+
+    pub fn width(&self, text: &str) -> usize {
+        //! The columns text takes from the start of a line, tabs reaching to the next tab stop and
+        //! every other character taking one column as it does when the line is laid out
+
+        text.chars().fold(0, |col, c| match c {
+            '\t' => self.next_tab_stop(col),
+            _ => col + 1,
+        })
+    }
+
+    pub fn indentation(&self, columns: usize) -> String {
+        //! The whitespace that indents a line by columns
+
+        self.whitespace(0, columns)
+    }
+
+    pub fn whitespace(&self, from: usize, to: usize) -> String {
+        //! The whitespace that fills the columns from from up to to
+
+        let mut filled = String::new();
+        let mut col = from;
+
+        if !self.expandtab {
+            while self.next_tab_stop(col) <= to {
+                filled.push('\t');
+                col = self.next_tab_stop(col);
+            }
+        }
+
+        filled + &" ".repeat(to.saturating_sub(col))
+    }
+
+    pub fn tab(&self, before: &str) -> Option<(usize, String)> {
+        //! What the tab key does after the text before it on its line, how many characters at the
+        //! end of before it takes out along with the whitespace it puts in their place, None when
+        //! it puts in a tab character as it is
+
+        let stop = match self.softtabstop {
+            0 if !self.expandtab => return None,
+            0 => self.tabstop.max(1),
+            stop => stop,
+        };
+
+        let col = self.width(before);
+        let target = (col / stop + 1) * stop;
+
+        if self.expandtab {
+            return Some((0, " ".repeat(target - col)));
+        }
+
+        // Whitespace already before the cursor is filled again so that tabs take the place of spaces
+        let run = trailing_whitespace(before);
+        let from = self.width(&before[..before.len() - run.len()]);
+
+        Some((run.chars().count(), self.whitespace(from, target)))
+    }
+
+    pub fn backspace(&self, before: &str) -> Option<(usize, String)> {
+        //! What backspace does after the text before it on its line when it is whitespace, how many
+        //! characters at the end of before it takes out along with the whitespace it puts back,
+        //! None when it takes out a single character
+
+        let run = trailing_whitespace(before);
+
+        if self.softtabstop == 0 || run.is_empty() {
+            return None;
+        }
+
+        let col = self.width(before);
+        let from = self.width(&before[..before.len() - run.len()]);
+
+        let target = ((col - 1) / self.softtabstop * self.softtabstop).max(from);
+
+        Some((run.chars().count(), self.whitespace(from, target)))
+    }
+
+    fn next_tab_stop(&self, col: usize) -> usize {
+        let tabstop = self.tabstop.max(1);
+
+        (col / tabstop + 1) * tabstop
+    }
+
+    pub fn new_line(&self, before: &str, after: &str) -> String {
+        //! The indentation of a line made by breaking a line in two, before is the text left on the
+        //! line above and after the text carried onto the new line
+
+        if !self.autoindent && !self.smartindent {
+            return String::new();
+        }
+
+        let mut columns = self.columns(before);
+
+        if self.smartindent {
+            if opens_block(before) {
+                columns += self.shiftwidth;
+            }
+
+            if closes_block(after) {
+                columns = columns.saturating_sub(self.shiftwidth);
+            }
+        }
+
+        self.indentation(columns)
+    }
+
+    pub fn line_above(&self, below: &str) -> String {
+        //! The indentation of a line opened above the line below
+
+        if !self.autoindent && !self.smartindent {
+            return String::new();
+        }
+
+        let mut columns = self.columns(below);
+
+        if self.smartindent && closes_block(below) {
+            columns += self.shiftwidth;
+        }
+
+        self.indentation(columns)
+    }
+
+    pub fn closing(&self, before: &str) -> Option<String> {
+        //! The indentation a line gets when a closing bracket is typed after nothing but its
+        //! indentation, before being the text before the bracket, None when it stays as it is
+
+        let blank = before.chars().all(char::is_whitespace);
+
+        (self.smartindent && blank && !before.is_empty())
+            .then(|| self.indentation(self.columns(before).saturating_sub(self.shiftwidth)))
+    }
+
+    pub fn reindented(&self, above: Option<&str>, line: &str) -> String {
+        //! The line with its indentation worked out again from the closest line above it that is
+        //! not blank, blank lines are left empty
+
+        let text = line.trim_start();
+
+        if text.is_empty() {
+            return String::new();
+        }
+
+        let indentation = match above {
+            Some(above) => self.new_line(above, text),
+            None => String::new(),
+        };
+
+        indentation + text
+    }
+
+    pub fn shifted(&self, line: &str, levels: isize) -> String {
+        //! The line moved levels of indentation in, or out when levels is negative, blank lines are
+        //! left as they are
+
+        let text = line.trim_start();
+
+        if text.is_empty() {
+            return line.to_string();
+        }
+
+        let shift = self.shiftwidth * levels.unsigned_abs();
+
+        let columns = if levels < 0 {
+            self.columns(line).saturating_sub(shift)
+        } else {
+            self.columns(line) + shift
+        };
+
+        self.indentation(columns) + text
+    }
+}
+
+pub fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+fn trailing_whitespace(text: &str) -> &str {
+    &text[text.trim_end_matches([' ', '\t']).len()..]
+}
+
+fn opens_block(line: &str) -> bool {
+    line.trim_end().ends_with(OPENERS)
+}
+
+fn closes_block(line: &str) -> bool {
+    line.trim_start().starts_with(CLOSERS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(expandtab: bool) -> IndentSettings {
+        IndentSettings {
+            tabstop: 8,
+            shiftwidth: 4,
+            expandtab,
+            softtabstop: 4,
+            ..IndentSettings::default()
+        }
+    }
+
+    #[test]
+    fn tabs_reach_the_next_tab_stop() {
+        let indent = settings(true);
+
+        assert_eq!(indent.width("\t"), 8);
+        assert_eq!(indent.width("ab\t"), 8);
+        assert_eq!(indent.width("\t\tx"), 17);
+        assert_eq!(indent.width("abcdefgh\t"), 16);
+    }
+
+    #[test]
+    fn mixed_indentation_is_measured_in_columns() {
+        let indent = settings(false);
+
+        assert_eq!(indent.columns("  \t  x"), 10);
+        assert_eq!(indent.columns("\t    \tx"), 16);
+        assert_eq!(indent.indentation(10), "\t  ");
+        assert_eq!(indent.shifted("  \t  x", 1), "\t      x");
+        assert_eq!(indent.shifted("\t  x", -1), "      x");
+    }
+
+    #[test]
+    fn characters_that_are_not_ascii_take_one_column() {
+        let indent = settings(true);
+
+        assert_eq!(indent.width("héllo"), 5);
+        assert_eq!(indent.width("日本\t"), 8);
+        assert_eq!(indent.width("ü\tx"), 9);
+
+        // Two characters that take four bytes are two columns in, two short of the next stop
+        assert_eq!(indent.tab("ßß"), Some((0, "  ".to_string())));
+        assert_eq!(indent.backspace("ßß    "), Some((4, "  ".to_string())));
+    }
+}
@@ -1,7 +1,25 @@
+pub mod args;
 pub mod cursor;
 pub mod debug;
+pub mod diff;
 pub mod document;
 pub mod editor;
+pub mod editorconfig;
 pub mod gapbuf;
+pub mod gutter;
+pub mod indent;
+pub mod mapped;
+pub mod message;
+pub mod rope;
+pub mod row_index;
+pub mod screen;
+pub mod signs;
+pub mod status;
+pub mod storage;
+pub mod tab;
 pub mod term;
 pub mod term_color;
+pub mod treap;
+pub mod viewport;
+pub mod window;
+pub mod wrap;
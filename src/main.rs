@@ -1,4 +1,12 @@
-use textchen::{document::*, editor::*, gapbuf::*, term::*};
+use std::{collections::VecDeque, env, process};
+use textchen::{
+    args::{Args, Invocation, USAGE},
+    document::BufferKind,
+    editor::*,
+    message::Level,
+    viewport::{Align, Viewport},
+    window::{Direction, Heading},
+};
 
 // ==== ASCII KEY CODE VALUES ====
 // Note: I use the ascii values as the keys so that it is more simple
@@ -8,23 +16,68 @@ const K_LOWER: u8 = 107;
 const L_LOWER: u8 = 108;
 const X_LOWER: u8 = 120;
 const O_LOWER: u8 = 111;
-const O_UPPER: u8 = 'O' as u8;
+const O_UPPER: u8 = b'O';
 const H_LOWER: u8 = 104;
 const G_LOWER: u8 = 103;
 const I_LOWER: u8 = 105;
+const N_LOWER: u8 = b'n';
+const Q_LOWER: u8 = b'q';
+const M_LOWER: u8 = b'm';
+const Z_LOWER: u8 = b'z';
+const SLASH: u8 = b'/';
+const GREATER: u8 = b'>';
+const LESS: u8 = b'<';
+const EQUALS: u8 = b'=';
 const COLON: u8 = 58;
+const CTRL_B: u8 = 2;
+const CTRL_D: u8 = 4;
+const CTRL_F: u8 = 6;
+const CTRL_U: u8 = 21;
+const CTRL_W: u8 = 23;
+const CTRL_CARET: u8 = 30;
 const ESC: u8 = 27;
 const BCKSP: u8 = if cfg!(target_os = "linux") { 127 } else { 8 };
 const RETURN: u8 = if cfg!(target_os = "linux") { 10 } else { 13 };
 
 fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(Invocation::Edit(args)) => args,
+        Ok(Invocation::Help) => {
+            print!("{USAGE}");
+            return;
+        }
+        Ok(Invocation::Version) => {
+            println!("textchen {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(e) => {
+            eprintln!("textchen: {e}\nTry 'textchen --help' for more information");
+            process::exit(2);
+        }
+    };
+
     // Editor is the primary instance to control the editor and all its data
-    let mut editor = Editor::new(2, 2);
+    let mut editor = Editor::new(2, 2, &args);
 
     editor.initialize();
 
-    // Initialize the gap buffer, it will be replaced later when editing actual text
-    let mut gap_buf = GapBuf::new();
+    // Options from the config file are set once the screen is up, some of them move the cursor
+    editor.load_config(&args.config);
+
+    editor.open_start_windows(args.split);
+
+    editor.warn_if_lossy();
+
+    // Commands given with -c are typed in for the user before anything they type
+    let mut startup_keys = args
+        .commands
+        .iter()
+        .flat_map(|command| {
+            format!(":{command}{}", RETURN as char)
+                .chars()
+                .collect::<Vec<_>>()
+        })
+        .collect::<VecDeque<_>>();
 
     // This will be the channel to receive the characters entered by the user
     let char_channel = spawn_char_channel();
@@ -33,945 +86,917 @@ fn main() {
     loop {
         editor.check_resize();
 
-        match char_channel.try_recv() {
-            Ok(c) => {
-                // Get a character and match it aginst some cases as a u8
-                match c as u8 {
-                    // Move down
-                    J_LOWER if editor.curr_mode == Modes::Normal => {
-                        // Store the position of the cursor in the original line, save on method calls
-
-                        let cursor_pos = editor.get_cursor_pos_in_line();
-
-                        if editor.get_cursor_vis_row() < editor.doc_disp_height()
-                            && editor.get_cursor_doc_row()
-                                != *editor
-                                    .current_buffer()
-                                    .borrow()
-                                    .lines
-                                    .last()
-                                    .unwrap()
-                                    .0
-                                    .last()
-                                    .unwrap()
-                        {
-                            // If the cursor's visual row is less than the height of the editor (the editor's height refers to the number of rows *downward* that the
-                            // editor's screen spans) and the cursor's row in relation to the document is not equal to the last row
+        // Take in any lines of the current buffer that were indexed in the background
+        editor.poll_indexing();
+
+        if let Some(c) = startup_keys
+            .pop_front()
+            .or_else(|| char_channel.try_recv().ok())
+        {
+            // Remember whether the buffer was modified so the title can be updated when that changes
+            let was_dirty = editor.current_buffer_dirty();
+
+            // Get a character and match it aginst some cases as a u8
+            match c as u8 {
+                // The rows of a line need not hold the same number of characters, so the cursor is
+                // moved by its position in the line, a character at a time, rather than by counting
+                // rows and columns
+                // Move left
+                H_LOWER if editor.curr_mode == Modes::Normal => {
+                    if let Some(pos) = editor.pos_before_cursor() {
+                        editor.move_cursor_into_view(editor.get_cursor_line_ind(), pos);
+                    }
+                }
+                // Move right
+                L_LOWER if editor.curr_mode == Modes::Normal => {
+                    if let Some(pos) = editor.pos_after_cursor() {
+                        editor.move_cursor_into_view(editor.get_cursor_line_ind(), pos);
+                    }
+                }
+                // Move down
+                J_LOWER if editor.curr_mode == Modes::Normal => {
+                    let last_row = editor.current_buffer().borrow().last_row();
 
-                            editor.move_cursor_down();
+                    editor.move_cursor_to_row((editor.get_cursor_doc_row() + 1).min(last_row));
+                }
+                // Move up
+                K_LOWER if editor.curr_mode == Modes::Normal => {
+                    editor.move_cursor_to_row(editor.get_cursor_doc_row().saturating_sub(1));
+                }
+                G_LOWER if editor.curr_mode == Modes::Normal => {
+                    editor.change_mode(Modes::MoveTo);
 
-                            let binding = editor.current_buffer();
+                    // Shows the new mode along with the pending key while waiting for the next one
+                    let new_c = editor.await_key("g");
 
-                            let document = binding.borrow();
-                            let curr_line =
-                                document.get_line_at_cursor(editor.get_cursor_doc_row());
+                    if new_c == 'l' {
+                        editor.move_cursor_to_end_line();
 
-                            if editor.get_cursor_doc_col()
-                                > curr_line.1.len() % editor.doc_disp_width()
-                                && editor.get_cursor_doc_row() == *curr_line.0.last().unwrap()
-                            {
-                                editor.move_cursor_to_end_line();
-                            }
-                        } else if editor.get_cursor_doc_row()
-                            != *editor
-                                .current_buffer()
-                                .borrow()
-                                .lines
-                                .last()
-                                .unwrap()
-                                .0
-                                .last()
-                                .unwrap()
-                        {
-                            editor.current_buffer().borrow_mut().push_vis_down();
-                            editor.move_cursor_doc_down();
+                        editor.change_mode(Modes::Normal);
+                    } else if new_c == 'h' {
+                        editor.move_cursor_to_start_line();
 
-                            editor.reset_editor_view();
-                        }
+                        editor.change_mode(Modes::Normal);
+                    } else if new_c == 'g' {
+                        editor.move_cursor_vis_to(
+                            editor.doc_disp_home_row(),
+                            editor.doc_disp_left_edge(),
+                        );
+                        editor.move_cursor_doc_to(0, 0);
 
-                        if cursor_pos % editor.doc_disp_width()
-                            > editor
-                                .current_buffer()
-                                .borrow()
-                                .get_line_at_cursor(editor.get_cursor_doc_row())
-                                .1
-                                .len()
-                                + 1
-                        {
-                            // If simply moving the cursor down to the next row will be outside of the bounds of that row's content and the line is one row long
+                        editor
+                            .current_buffer()
+                            .borrow_mut()
+                            .viewport
+                            .scroll_to(0, editor.doc_disp_height());
 
-                            // Move to the end of that new row
-                            editor.move_cursor_to_end_line();
-                        }
+                        editor.reset_editor_view();
 
-                        editor.multi_row_bump();
-                    }
-                    // Move right
-                    L_LOWER if editor.curr_mode == Modes::Normal => {
-                        // Get the current line where the cursor is at
+                        editor.change_mode(Modes::Normal);
+                    } else if new_c == 'e' {
+                        let num_rows = editor.current_buffer().borrow().num_rows();
 
-                        let binding = editor.current_buffer();
+                        let top = (num_rows + 1).saturating_sub(editor.doc_disp_height());
 
-                        let document = binding.borrow();
-                        let curr_line = document.get_line_at_cursor(editor.get_cursor_doc_row());
-                        let cursor_pos = editor.get_cursor_pos_in_line();
+                        editor.current_buffer().borrow_mut().viewport =
+                            Viewport::new(top, num_rows);
 
-                        if cursor_pos < curr_line.1.len()
-                            && editor.get_cursor_doc_col() < editor.doc_disp_width()
-                        {
-                            // If the cursor's position in the current line is less than the length of the total line and the cursor's column in relation to the document
-                            // is less than or equal to the editor's width
+                        editor.move_cursor_vis_to(
+                            editor.doc_disp_home_row() + (num_rows - 1 - top),
+                            editor.doc_disp_left_edge(),
+                        );
 
-                            editor.move_cursor_right();
-                        } else if cursor_pos < curr_line.1.len()
-                            && curr_line.0.contains(&(editor.get_cursor_doc_row() + 1))
-                        {
-                            // If the cursor's position in the current line is less than the length of the total line and the current line's row indices contains the next
-                            // cursor's row in relation to the document
+                        editor.move_cursor_doc_to(num_rows - 1, 0);
 
-                            if editor.get_cursor_vis_row() < editor.doc_disp_height() {
-                                // If the cursor's row is less than the editor's height
+                        editor.reset_editor_view();
 
-                                // Move down to the next row
-                                editor.move_cursor_vis_down();
-                            } else {
-                                // If the cursor's row is at the editor's height
+                        editor.change_mode(Modes::Normal);
+                    } else if new_c == 't' || new_c == 'T' {
+                        editor.cycle_tab(new_c == 't');
 
-                                // Push the visible rows of the document down
-                                editor.current_buffer().borrow_mut().push_vis_down();
+                        editor.change_mode(Modes::Normal);
+                    } else if new_c == 'n' {
+                        editor.next_buffer();
 
-                                // Reset the editor
-                                editor.reset_editor_view();
-                            }
+                        editor.change_mode(Modes::Normal);
+                    } else if new_c == 'p' {
+                        editor.prev_buffer();
 
-                            // Move to the cursor visually the left edge of the editor
-                            editor.move_cursor_vis_editor_left();
-                            // Make the cursor's doc_column value 0 and then move it to the right (increment it) because the cursor needs to hover over the second character of the row
-                            // in this particular case
-                            editor.move_cursor_doc_editor_left();
+                        editor.change_mode(Modes::Normal);
+                    } else {
+                        editor.change_mode(Modes::Normal);
+                    }
+                }
+                // Move the view around the cursor, or scroll sideways while lines are not wrapped
+                Z_LOWER if editor.curr_mode == Modes::Normal => {
+                    let left_col = editor.left_col();
+                    let column = editor.get_cursor_column_in_doc_disp();
+
+                    match editor.await_key("z") {
+                        'z' => editor.align_view(Align::Center),
+                        't' => editor.align_view(Align::Top),
+                        'b' => editor.align_view(Align::Bottom),
+                        'h' => editor.scroll_sideways_to(left_col.saturating_sub(1)),
+                        'l' => editor.scroll_sideways_to(left_col + 1),
+                        's' => editor.scroll_sideways_to(column),
+                        'e' => editor.scroll_sideways_to(
+                            (column + 1).saturating_sub(editor.doc_disp_width()),
+                        ),
+                        _ => (),
+                    }
+                }
+                // Go back to the buffer shown before the current one
+                CTRL_CARET if editor.curr_mode == Modes::Normal => {
+                    if let Err(e) = editor.switch_to_alternate_buffer() {
+                        editor.notify(Level::Error, e);
+                    }
+                }
+                // Split, close, resize and move between windows
+                CTRL_W if editor.curr_mode == Modes::Normal => {
+                    let result = match editor.await_key("^W") {
+                        's' | 'S' | '\x13' => editor.split_window(Direction::Stacked, None),
+                        'v' | '\x16' => editor.split_window(Direction::SideBySide, None),
+                        'c' | 'q' | '\x11' => editor.close_window(),
+                        'T' => editor.move_window_to_new_tab(),
+                        'o' | '\x0f' => {
+                            editor.only_window();
+                            Ok(())
+                        }
+                        'w' | '\x17' => {
+                            editor.cycle_window(true);
+                            Ok(())
+                        }
+                        'W' => {
+                            editor.cycle_window(false);
+                            Ok(())
+                        }
+                        key @ ('h' | 'j' | 'k' | 'l' | '\x08' | '\n' | '\x0b' | '\x0c') => {
+                            editor.move_to_window(match key {
+                                'h' | '\x08' => Heading::Left,
+                                'j' | '\n' => Heading::Down,
+                                'k' | '\x0b' => Heading::Up,
+                                _ => Heading::Right,
+                            });
+                            Ok(())
+                        }
+                        '+' => {
+                            editor.resize_window(Direction::Stacked, 1);
+                            Ok(())
+                        }
+                        '-' => {
+                            editor.resize_window(Direction::Stacked, -1);
+                            Ok(())
+                        }
+                        '>' => {
+                            editor.resize_window(Direction::SideBySide, 1);
+                            Ok(())
+                        }
+                        '<' => {
+                            editor.resize_window(Direction::SideBySide, -1);
+                            Ok(())
+                        }
+                        '=' => {
+                            editor.equalize_windows();
+                            Ok(())
+                        }
+                        _ => Ok(()),
+                    };
 
-                            // Because the end of the previous line is included within the conditions of the previous if clause, move the cursor to the right of the immediate next
-                            // chracter in the line
-                            editor.move_cursor_right();
+                    if let Err(e) = result {
+                        editor.notify(Level::Error, e);
+                    }
+                }
+                // Scroll by half a page or a whole page, the cursor moves along with the view
+                CTRL_D if editor.curr_mode == Modes::Normal => {
+                    editor.scroll_half_page(true);
+                }
+                CTRL_U if editor.curr_mode == Modes::Normal => {
+                    editor.scroll_half_page(false);
+                }
+                CTRL_F if editor.curr_mode == Modes::Normal => {
+                    editor.scroll_page(true);
+                }
+                CTRL_B if editor.curr_mode == Modes::Normal => {
+                    editor.scroll_page(false);
+                }
+                // Put a bookmark letter next to the cursor's line
+                M_LOWER if editor.curr_mode == Modes::Normal => {
+                    let letter = editor.await_key("m");
 
-                            // Set the place of the cursor within the document properly
-                            editor.move_cursor_doc_down();
-                        }
+                    if letter.is_ascii_alphabetic() {
+                        editor.set_bookmark(letter);
                     }
-                    // Move up
-                    K_LOWER if editor.curr_mode == Modes::Normal => {
-                        let cursor_pos = editor.get_cursor_pos_in_line();
-
-                        if editor.current_buffer().borrow().visible_rows.0 != 0 {
-                            // If the document's visible rows does not include the first row
-
-                            if editor.get_cursor_vis_row() - 1 > editor.doc_disp_home_row() {
-                                // If moving the cursor visually updwards will not be the home row of the editor
-
-                                editor.move_cursor_up();
-
-                                if cursor_pos
-                                    > editor
-                                        .current_buffer()
-                                        .borrow()
-                                        .get_line_at_cursor(editor.get_cursor_doc_row())
-                                        .1
-                                        .len()
-                                        + 1
-                                {
-                                    // If moving up would be outside of the bounds of the previos line
+                }
+                // Refuse to change a buffer that is read only
+                X_LOWER | I_LOWER | O_LOWER | O_UPPER | GREATER | LESS | EQUALS
+                    if editor.curr_mode == Modes::Normal && editor.current_buffer_read_only() =>
+                {
+                    editor.notify(Level::Error, "Cannot make changes, buffer is read only");
+                }
+                // Shift the cursor's line a level of indentation in with >> or out with <<
+                GREATER | LESS if editor.curr_mode == Modes::Normal => {
+                    let (key, levels) = if c as u8 == GREATER {
+                        (">", 1)
+                    } else {
+                        ("<", -1)
+                    };
+
+                    if editor.await_key(key) == c {
+                        let line_ind = editor.get_cursor_line_ind();
+
+                        editor.shift_lines(line_ind..line_ind + 1, levels);
+                    }
+                }
+                // Indent lines again, == for the cursor's line, =G down to the last line and =gg up
+                // to the first
+                EQUALS if editor.curr_mode == Modes::Normal => {
+                    let line_ind = editor.get_cursor_line_ind();
+
+                    let lines = match editor.await_key("=") {
+                        '=' => Some(line_ind..line_ind + 1),
+                        'G' => Some(line_ind..editor.current_buffer().borrow().get_number_lines()),
+                        'g' if editor.await_key("=g") == 'g' => Some(0..line_ind + 1),
+                        _ => None,
+                    };
+
+                    if let Some(lines) = lines {
+                        editor.reindent_lines(lines);
+                    }
+                }
+                // Leave straight away while used as a pager
+                Q_LOWER if editor.curr_mode == Modes::Normal && editor.is_view_mode() => {
+                    break;
+                }
+                X_LOWER
+                    if editor.curr_mode == Modes::Normal
+                    // todo!("Reimplement for scrolling");
+                    && editor.await_key("x") == 'd' =>
+                {
+                    editor.move_cursor_to_start_line();
+
+                    // The key combination xd will delete a line
+                    // Remove the line from the document
+                    let wrap_width = editor.wrap_width();
+
+                    editor
+                        .current_buffer()
+                        .borrow_mut()
+                        .remove_line_from_doc(editor.get_cursor_doc_row(), wrap_width);
+
+                    if editor.current_buffer().borrow().num_rows() > 0 {
+                        if editor.get_cursor_doc_row() > 0 {
+                            editor.move_cursor_doc_up();
 
-                                    editor.move_cursor_to_end_line();
-                                }
+                            if editor.get_cursor_vis_row() == editor.doc_disp_home_row() {
+                                // Move the cursor to the previous row
+                                editor.move_cursor_to_start_line();
                             } else {
-                                // If the cursor is visually below the editor's home row
-
-                                editor.move_cursor_doc_up();
-                                editor
-                                    .current_buffer()
-                                    .borrow_mut()
-                                    .push_vis_up(editor.doc_disp_height());
-
-                                editor.reset_editor_view();
-
-                                if cursor_pos
-                                    > editor
-                                        .current_buffer()
-                                        .borrow()
-                                        .get_line_at_cursor(editor.get_cursor_doc_row())
-                                        .1
-                                        .len()
-                                        + 1
-                                {
-                                    // If moving up would be outside of the bounds of the previos line
-
-                                    editor.move_cursor_to_end_line();
-                                }
+                                editor.move_cursor_vis_up();
+                                editor.move_cursor_to_start_line();
                             }
-                        } else if editor.get_cursor_vis_row() != editor.doc_disp_home_row() {
-                            // If the cursor is not visually on the editor's home row
-
-                            // Get the current position of the cursor
-                            let cursor_pos = editor.get_cursor_pos_in_line();
-
-                            editor.move_cursor_up();
+                        }
 
-                            if editor
+                        if editor.current_buffer().borrow().viewport.top() != 0
+                            && editor.get_cursor_vis_row() == editor.doc_disp_home_row()
+                        {
+                            let curr_line_inds = editor
                                 .current_buffer()
                                 .borrow()
                                 .get_line_at_cursor(editor.get_cursor_doc_row())
                                 .0
-                                .len()
-                                == 1
-                                && cursor_pos
-                                    > editor
-                                        .current_buffer()
-                                        .borrow()
-                                        .get_line_at_cursor(editor.get_cursor_doc_row())
-                                        .1
-                                        .len()
+                                .clone();
+
+                            while curr_line_inds[0]
+                                != editor.current_buffer().borrow().viewport.top()
                             {
-                                // If the new row is only one row long and the cursor's position is outside the bounds of the row
-                                editor.move_cursor_to_end_line();
+                                editor
+                                    .current_buffer()
+                                    .borrow_mut()
+                                    .push_vis_up(editor.doc_disp_height());
                             }
                         }
-
-                        editor.multi_row_bump();
                     }
-                    // Move left
-                    H_LOWER if editor.curr_mode == Modes::Normal => {
-                        let cursor_pos = editor.get_cursor_pos_in_line();
 
-                        if editor.get_cursor_column_in_doc_disp() > 1 || cursor_pos == 1 {
-                            // If moving the cursor left does not reach the first column of the editor's field (i.e. the cursor will not be moved to the first possible column where characters can be printed to)
-                            // or the cursor is at the second position of the line
+                    editor.reset_editor_view();
+                }
+                // Enter insert mode
+                I_LOWER if editor.curr_mode == Modes::Normal => {
+                    // Change mode to insert
+                    editor.change_mode(Modes::Insert);
 
-                            editor.move_cursor_left();
-                        } else if cursor_pos / editor.doc_disp_width() != 0 && cursor_pos != 0 {
-                            // If the row in the line where the cursor is is not the first row of the line and the cursor is not at the first position of the line
+                    if editor.current_buffer().borrow().get_number_lines() == 0 {
+                        editor.current_buffer().borrow_mut().add_scratch_line();
+                    }
 
-                            if editor.current_buffer().borrow().visible_rows.0 == 0
-                                || editor.get_cursor_vis_row() > editor.doc_disp_home_row()
-                            {
-                                // If the document's visible rows does include the first row
+                    let cursor_pos = editor.get_cursor_pos_in_line();
 
-                                editor.move_cursor_vis_up();
-                            } else {
-                                // If the document's visible rows does not include the first row
+                    // Start editing the line at the current cursor position
+                    editor
+                        .current_buffer()
+                        .borrow_mut()
+                        .begin_edit(editor.get_cursor_doc_row(), cursor_pos);
+                }
+                // Create a new empty line below current position of the cursor
+                O_LOWER if editor.curr_mode == Modes::Normal => {
+                    // Change mode to insert
+                    editor.change_mode(Modes::Insert);
 
-                                editor
-                                    .current_buffer()
-                                    .borrow_mut()
-                                    .push_vis_up(editor.doc_disp_height());
+                    let indentation = editor.line_below_indentation();
 
-                                editor.reset_editor_view();
-                            }
+                    // Move to the beginning of the next possible line
+                    editor.move_cursor_to_end_line();
 
-                            editor.move_cursor_doc_to_editor_right();
+                    if editor.get_cursor_vis_row() < editor.doc_disp_bottom() - 1 {
+                        // If the cursor's row is less than the editor's height
 
-                            editor.move_cursor_vis_to_editor_right();
+                        // Move down to the next row
+                        editor.move_cursor_vis_down();
+                    } else if editor.get_cursor_doc_row()
+                        != editor.current_buffer().borrow().last_row()
+                    {
+                        // If the cursor's row is at the editor's height
 
-                            editor.move_cursor_doc_up();
-                        }
+                        // Push the visible rows of the document down
+                        editor.current_buffer().borrow_mut().push_vis_down();
                     }
-                    G_LOWER if editor.curr_mode == Modes::Normal => {
-                        editor.change_mode(Modes::MoveTo);
 
-                        // This flush is necessary because otherwise the new mode is not printed
-                        editor.flush_pen();
+                    editor.move_cursor_vis_editor_left();
+                    editor.move_cursor_doc_editor_left();
+                    editor.move_cursor_doc_down();
 
-                        let new_c = get_char();
+                    // Add the new line to the document, it starts out with only its indentation
+                    editor
+                        .current_buffer()
+                        .borrow_mut()
+                        .add_line_at_row(indentation.clone(), editor.get_cursor_doc_row());
 
-                        if new_c == 'l' {
-                            editor.move_cursor_to_end_line();
+                    editor
+                        .current_buffer()
+                        .borrow_mut()
+                        .begin_edit(editor.get_cursor_doc_row(), indentation.len());
 
-                            editor.change_mode(Modes::Normal);
-                        } else if new_c == 'h' {
-                            editor.move_cursor_to_start_line();
+                    editor.move_cursor_to_pos(indentation.len());
 
-                            editor.change_mode(Modes::Normal);
-                        } else if new_c == 'g' {
-                            editor.move_cursor_vis_to(
-                                editor.doc_disp_home_row(),
-                                editor.doc_disp_left_edge(),
-                            );
-                            editor.move_cursor_doc_to(0, 0);
+                    // Reset view
+                    editor.reset_editor_view();
+                }
+                // Create new empty line at the current cursor position, push all other contents down
+                O_UPPER if editor.curr_mode == Modes::Normal => {
+                    // Change mode to insert
+                    editor.change_mode(Modes::Insert);
 
-                            editor.current_buffer().borrow_mut().visible_rows.0 = 0;
-                            editor.current_buffer().borrow_mut().visible_rows.1 =
-                                editor.doc_disp_height();
+                    let indentation = editor.line_above_indentation();
 
-                            editor.reset_editor_view();
+                    // The new line will be inserted at the current position and will not change
+                    // the position of the cursor visually or within the document
 
-                            editor.change_mode(Modes::Normal);
-                        } else if new_c == 'e' {
-                            editor.move_cursor_vis_to(
-                                editor.doc_disp_height(),
-                                editor.doc_disp_left_edge(),
-                            );
+                    // Move to the beginning of the current line
+                    editor.move_cursor_to_start_line();
 
-                            editor.move_cursor_doc_to(
-                                *editor
-                                    .current_buffer()
-                                    .borrow()
-                                    .lines
-                                    .last()
-                                    .unwrap()
-                                    .0
-                                    .last()
-                                    .unwrap(),
-                                0,
-                            );
-
-                            editor.current_buffer().borrow_mut().visible_rows.0 =
-                                (editor.current_buffer().borrow().num_rows() + 1)
-                                    - editor.doc_disp_height();
-                            editor.current_buffer().borrow_mut().visible_rows.1 =
-                                editor.current_buffer().borrow().num_rows();
-
-                            editor.reset_editor_view();
-
-                            editor.change_mode(Modes::Normal);
-                        } else if new_c == 'n' {
-                            editor.next_buffer();
-
-                            editor.change_mode(Modes::Normal);
-                        } else if new_c == 'p' {
-                            editor.prev_buffer();
-
-                            editor.change_mode(Modes::Normal);
-                        } else {
-                            editor.change_mode(Modes::Normal);
-                        }
-                    }
-                    X_LOWER if editor.curr_mode == Modes::Normal => {
-                        // todo!("Reimplement for scrolling");
-                        if get_char() == 'd' {
-                            editor.move_cursor_to_start_line();
-
-                            // The key combination xd will delete a line
-                            // Remove the line from the document
-                            editor.current_buffer().borrow_mut().remove_line_from_doc(
-                                editor.get_cursor_doc_row(),
-                                editor.doc_disp_width(),
-                            );
-
-                            if editor.current_buffer().borrow().num_rows() > 0 {
-                                if editor.get_cursor_doc_row() > 0 {
-                                    editor.move_cursor_doc_up();
-
-                                    if editor.get_cursor_vis_row() == editor.doc_disp_home_row() {
-                                        // Move the cursor to the previous row
-                                        editor.move_cursor_to_start_line();
-                                    } else {
-                                        editor.move_cursor_vis_up();
-                                        editor.move_cursor_to_start_line();
-                                    }
-                                }
+                    // Move the cursor visually and within the document to the leftmost position
+                    editor.move_cursor_vis_editor_left();
+                    editor.move_cursor_doc_editor_left();
 
-                                if editor.current_buffer().borrow().visible_rows.0 != 0
-                                    && editor.get_cursor_vis_row() == editor.doc_disp_home_row()
-                                {
-                                    let curr_line_inds = editor
-                                        .current_buffer()
-                                        .borrow()
-                                        .get_line_at_cursor(editor.get_cursor_doc_row())
-                                        .0
-                                        .clone();
-
-                                    while curr_line_inds[0]
-                                        != editor.current_buffer().borrow().visible_rows.0
-                                    {
-                                        editor
-                                            .current_buffer()
-                                            .borrow_mut()
-                                            .push_vis_up(editor.doc_disp_height());
-                                    }
-                                }
-                            }
+                    // Add the new line to the document at the cursor's current row, it starts out
+                    // with only its indentation
+                    editor
+                        .current_buffer()
+                        .borrow_mut()
+                        .add_line_at_row(indentation.clone(), editor.get_cursor_doc_row());
 
-                            editor.reset_editor_view();
-                        }
-                    }
-                    // Enter insert mode
-                    I_LOWER if editor.curr_mode == Modes::Normal => {
-                        // Change mode to insert
-                        editor.change_mode(Modes::Insert);
-
-                        if editor.current_buffer().borrow().lines.len() > 0 {
-                            // Create a new gap buffer from the string at the current cursor position
-                            gap_buf = GapBuf::from_str(
-                                editor
-                                    .current_buffer()
-                                    .borrow()
-                                    .get_str_at_cursor(editor.get_cursor_doc_row())
-                                    .to_owned(),
-                                editor.get_cursor_pos_in_line(),
-                            );
-                        } else {
-                            gap_buf = GapBuf::new();
-
-                            editor.current_buffer().borrow_mut().add_scratch_line();
-                        }
-                    }
-                    // Create a new empty line below current position of the cursor
-                    O_LOWER if editor.curr_mode == Modes::Normal => {
-                        let mut new_line = Line::new();
+                    editor
+                        .current_buffer()
+                        .borrow_mut()
+                        .begin_edit(editor.get_cursor_doc_row(), indentation.len());
 
-                        // Change mode to insert
-                        editor.change_mode(Modes::Insert);
+                    editor.move_cursor_to_pos(indentation.len());
 
-                        // Add the last index of the current line incremented to the new line's index list
-                        new_line.0.push(editor.get_cursor_doc_row() + 1);
-
-                        // Move to the beginning of the next possible line
-                        editor.move_cursor_to_end_line();
+                    // Reset view
+                    editor.reset_editor_view();
+                }
+                // Exit insert mode
+                ESC if editor.curr_mode == Modes::Insert => {
+                    // Change mode to normal
+                    editor.change_mode(Modes::Normal);
 
-                        if editor.get_cursor_vis_row() < editor.doc_disp_height() {
-                            // If the cursor's row is less than the editor's height
+                    // Write the edited lines back into the document
+                    editor.current_buffer().borrow_mut().end_edit();
+                }
+                // Cancel entering a command
+                ESC if editor.curr_mode == Modes::Command || editor.curr_mode == Modes::Search => {
+                    editor.exit_command_mode::<String>(None);
 
-                            // Move down to the next row
-                            editor.move_cursor_vis_down();
-                        } else if editor.get_cursor_doc_row()
-                            != *editor
-                                .current_buffer()
-                                .borrow()
-                                .lines
-                                .last()
-                                .unwrap()
-                                .0
-                                .last()
-                                .unwrap()
-                        {
-                            // If the cursor's row is at the editor's height
+                    // Change mode to normal
+                    editor.change_mode(Modes::Normal);
 
-                            // Push the visible rows of the document down
-                            editor.current_buffer().borrow_mut().push_vis_down();
+                    editor.revert_cursor_vis_pos();
+                }
+                // Edit at the cursor's position in the line, the rows it is drawn on are worked out
+                // from there
+                // Delete a character while in insert mode
+                BCKSP if editor.curr_mode == Modes::Insert => {
+                    let line_ind = editor.get_cursor_line_ind();
+
+                    if let Some(pos) = editor.pos_before_cursor() {
+                        // Whitespace goes back to the previous soft tab stop
+                        if !editor.delete_soft_tab() {
+                            editor.current_buffer().borrow_mut().edit_pop();
+
+                            editor.move_cursor_into_view(line_ind, pos);
                         }
-
-                        editor.move_cursor_vis_editor_left();
-                        editor.move_cursor_doc_down();
-
-                        // Add the new line to the document
-                        editor
+                    } else if line_ind > 0 {
+                        let prev_len = editor
                             .current_buffer()
-                            .borrow_mut()
-                            .add_line_at_row(new_line, editor.get_cursor_doc_row());
+                            .borrow()
+                            .get_line(line_ind - 1)
+                            .1
+                            .len();
 
-                        // Crate an empty gap buffer since the line will be empty guaranteed
-                        gap_buf = GapBuf::new();
+                        // Join the line onto the end of the previous line
+                        editor.current_buffer().borrow_mut().edit_pop();
 
-                        // Reset view
-                        editor.reset_editor_view();
+                        editor.move_cursor_into_view(line_ind - 1, prev_len);
                     }
-                    // Create new empty line at the current cursor position, push all other contents down
-                    O_UPPER if editor.curr_mode == Modes::Normal => {
-                        let mut new_line = Line::new();
 
-                        // Change mode to insert
-                        editor.change_mode(Modes::Insert);
+                    editor.reset_editor_view();
+                }
+                // Insert a character while in insert mode
+                c if editor.curr_mode == Modes::Insert
+                    && (c as char == ' ' || !(c as char).is_whitespace()) =>
+                {
+                    let text = (c as char).to_string();
 
-                        // The new line will be inserted at the current position and will not change
-                        // the position of the cursor visually or within the document
-                        new_line.0.push(editor.get_cursor_doc_row());
+                    editor.indent_closing_bracket(c as char);
+                    editor.break_at_textwidth(c as char);
 
-                        // Move to the beginning of the current line
-                        editor.move_cursor_to_start_line();
+                    let cursor_pos = editor.get_cursor_pos_in_line();
+                    let line_ind = editor.get_cursor_line_ind();
 
-                        // Move the cursor visually and within the document to the leftmost position
-                        editor.move_cursor_vis_editor_left();
-                        editor.move_cursor_doc_editor_left();
+                    let num_line_rows = editor.current_buffer().borrow().line_row_count(line_ind);
 
-                        // Add the new line to the document at the cursor's current row
-                        editor
-                            .current_buffer()
-                            .borrow_mut()
-                            .add_line_at_row(new_line, editor.get_cursor_doc_row());
+                    editor.current_buffer().borrow_mut().edit_insert_str(&text);
 
-                        // Crate an empty gap buffer since the line will be empty guaranteed
-                        gap_buf = GapBuf::new();
+                    // Positions in a line are counted in bytes, as the text is stored
+                    editor.move_cursor_into_view(line_ind, cursor_pos + text.len());
 
-                        // Reset view
+                    // Typing can move words onto other rows of the line, so the whole line is drawn
+                    if num_line_rows == editor.current_buffer().borrow().line_row_count(line_ind) {
+                        editor.print_line();
+                    } else {
                         editor.reset_editor_view();
                     }
-                    // Exit insert mode
-                    ESC if editor.curr_mode == Modes::Insert => {
-                        // Change mode to normal
-                        editor.change_mode(Modes::Normal);
-
-                        // Set the the to the string representation of the current gap buffer, reculculating the row indices for the line
-                        editor.current_buffer().borrow_mut().set_line_at_cursor(
-                            editor.get_cursor_doc_row(),
-                            gap_buf.to_string(),
-                            editor.doc_disp_width(),
-                        );
-                    }
-                    // Cancel entering a command
-                    ESC if editor.curr_mode == Modes::Command => {
-                        editor.exit_command_mode::<String>(None);
+                }
+                // Insert a new line character to break line while in insert mode
+                c if editor.curr_mode == Modes::Insert && c == RETURN => {
+                    let line_ind = editor.get_cursor_line_ind();
 
-                        // Change mode to normal
-                        editor.change_mode(Modes::Normal);
+                    let indentation = editor.new_line_indentation();
 
-                        editor.revert_cursor_vis_pos();
-                    }
-                    // Delete a character while in insert mode
-                    BCKSP if editor.curr_mode == Modes::Insert => {
-                        let cursor_pos = editor.get_cursor_pos_in_line();
+                    // Break the line at the cursor, the new line is indented by the indent settings
+                    editor.current_buffer().borrow_mut().edit_insert('\n');
+                    editor
+                        .current_buffer()
+                        .borrow_mut()
+                        .edit_insert_str(&indentation);
 
-                        let curr_num_rows = editor.current_buffer().borrow().num_rows();
+                    editor.move_cursor_into_view(line_ind + 1, indentation.len());
 
-                        if editor.get_cursor_doc_col() > 1 || cursor_pos == 1 {
-                            // If the cursor is one space away from being on top of the first column of characters (i.e. the cursor is within the line)
+                    editor.reset_editor_view();
+                }
+                // A tab character or whitespace up to the next soft tab stop
+                c if editor.curr_mode == Modes::Insert && c as char == '\t' => {
+                    editor.insert_tab();
+                }
+                // Enter a pattern to search for, it is typed in the same row as commands
+                SLASH if editor.curr_mode == Modes::Normal => {
+                    editor.change_mode(Modes::Search);
 
-                            let num_leading_spaces = (editor
-                                .current_buffer()
-                                .borrow()
-                                .get_line_at_cursor(editor.get_cursor_doc_row())
-                                .1
-                                .chars()
-                                .take_while(|c| *c == ' ')
-                                .count()
-                                / 4)
-                                * 4;
-
-                            if num_leading_spaces == editor.get_cursor_pos_in_line()
-                                && num_leading_spaces % 4 == 0
-                            {
-                                // If the number of leading spaces is equivalent to the cursor's current position and
-                                // the number of leading spaces is divisible by 4
+                    editor.initialize_search_row();
+                }
+                // Search for the last pattern again
+                N_LOWER if editor.curr_mode == Modes::Normal => {
+                    editor.search_next();
+                }
+                // Enter command mode
+                COLON if editor.curr_mode == Modes::Normal => {
+                    // Change to command mode
+                    editor.change_mode(Modes::Command);
 
-                                gap_buf.pop_tab();
+                    editor.initialize_command_row();
+                }
+                // Execute command while in command mdoe
+                RETURN if editor.curr_mode == Modes::Command => {
+                    let input = editor.command_buf.borrow().clone();
+                    let mut input_iter = input
+                        .as_str()
+                        .split_whitespace()
+                        .collect::<Vec<&str>>()
+                        .into_iter();
+
+                    if let Some(command) = input_iter.next() {
+                        match command {
+                            "w" | "w!" => {
+                                let new_name = input_iter.next();
+
+                                match editor.write_current_buffer_to_file(new_name, command == "w!")
+                                {
+                                    Ok(_) => {
+                                        editor.exit_command_mode::<String>(None);
+
+                                        let file_name = new_name.map_or_else(
+                                            || editor.current_buffer().borrow().file_name.clone(),
+                                            str::to_string,
+                                        );
+
+                                        editor.notify(
+                                            Level::Info,
+                                            format!("\"{file_name}\" written"),
+                                        );
+                                    }
+                                    Err(e) => {
+                                        editor.exit_command_mode(Some(format!("Write failed: {e}")))
+                                    }
+                                }
 
-                                for _ in 0..4 {
-                                    editor.move_cursor_left();
+                                editor.change_mode(Modes::Normal);
+                            }
+                            "sav" | "saveas" => {
+                                let result = match input_iter.next() {
+                                    Some(file_name) => editor
+                                        .save_current_buffer_as(file_name)
+                                        .map(|_| file_name)
+                                        .map_err(|e| format!("Write failed: {e}")),
+                                    None => Err("Argument required".to_string()),
+                                };
+
+                                match result {
+                                    Ok(file_name) => {
+                                        editor.exit_command_mode::<String>(None);
+
+                                        editor.notify(
+                                            Level::Info,
+                                            format!("\"{file_name}\" written"),
+                                        );
+                                    }
+                                    Err(e) => editor.exit_command_mode(Some(e)),
                                 }
-                            } else {
-                                // Remove the next character in the gap buffer
-                                gap_buf.pop();
 
-                                editor.move_cursor_left();
+                                editor.change_mode(Modes::Normal);
                             }
+                            "ene" | "enew" | "scratch" => {
+                                editor.exit_command_mode::<String>(None);
 
-                            editor.current_buffer().borrow_mut().set_line_at_cursor(
-                                editor.get_cursor_doc_row(),
-                                gap_buf.to_string(),
-                                editor.doc_disp_width(),
-                            );
-                        } else if cursor_pos / editor.doc_disp_width() != 0 {
-                            // If the cursor is not in the first row of the line
+                                editor.change_mode(Modes::Normal);
 
-                            // Remove the next character in the gap buffer
-                            gap_buf.pop();
+                                editor.new_buffer(if command == "scratch" {
+                                    BufferKind::Scratch
+                                } else {
+                                    BufferKind::Unnamed
+                                });
+                            }
+                            "wa" => {
+                                match editor.write_all_buffers_to_file() {
+                                    Ok(_) => editor.exit_command_mode::<String>(None),
+                                    Err(e) => {
+                                        editor.exit_command_mode(Some(format!("Write failed: {e}")))
+                                    }
+                                }
 
-                            if editor.current_buffer().borrow().visible_rows.0 == 0
-                                || editor.get_cursor_vis_row() > editor.doc_disp_home_row()
-                            {
-                                // If the document's visible rows does include the first row
+                                editor.change_mode(Modes::Normal);
+                            }
+                            // Closing one of several windows leaves its buffer open
+                            "q" | "q!" | "clo" | "close" if editor.window_count() > 1 => {
+                                editor.exit_command_mode::<String>(None);
 
-                                // Move the cursor to the previous row
-                                editor.move_cursor_vis_up();
-                            } else {
-                                // If the document's visible rows does not include the first row
+                                editor.change_mode(Modes::Normal);
 
-                                editor
-                                    .current_buffer()
-                                    .borrow_mut()
-                                    .push_vis_up(editor.doc_disp_height());
+                                if let Err(e) = editor.close_window() {
+                                    editor.notify(Level::Error, e);
+                                }
                             }
+                            "clo" | "close" => {
+                                editor.exit_command_mode(Some("Cannot close last window"));
 
-                            // Move the cursor to the end of the previous row
-                            editor.move_cursor_vis_to_editor_right();
+                                editor.change_mode(Modes::Normal);
+                            }
+                            "tabnew" | "tabe" | "tabedit" => {
+                                editor.exit_command_mode::<String>(None);
 
-                            editor.move_cursor_doc_up();
-                            editor.move_cursor_doc_to_editor_right();
+                                editor.change_mode(Modes::Normal);
 
-                            editor.current_buffer().borrow_mut().set_line_at_cursor(
-                                editor.get_cursor_doc_row(),
-                                gap_buf.to_string(),
-                                editor.doc_disp_width(),
-                            );
+                                editor.new_tab(input_iter.next());
+                            }
+                            "tabc" | "tabclose" => {
+                                editor.exit_command_mode::<String>(None);
 
-                            // Reset the view
-                        } else if cursor_pos == 0
-                            && editor.get_cursor_vis_row() != editor.doc_disp_home_row()
-                        {
-                            // If the cursor is at the first positon of the line and it is not in the first line of the document
-                            // (note: cursor's doc row field is not used during checking because editor_top starts at the same
-                            // index that cursor's row starts at)
+                                editor.change_mode(Modes::Normal);
 
-                            // Get the current line's string
-                            let curr_str = editor
-                                .current_buffer()
-                                .borrow()
-                                .get_str_at_cursor(editor.get_cursor_doc_row())
-                                .to_owned();
+                                if let Err(e) = editor.close_tab() {
+                                    editor.notify(Level::Error, e);
+                                }
+                            }
+                            "tabo" | "tabonly" => {
+                                editor.exit_command_mode::<String>(None);
 
-                            // Remove the current line from the document
-                            editor.current_buffer().borrow_mut().remove_line_from_doc(
-                                editor.get_cursor_doc_row(),
-                                editor.doc_disp_width(),
-                            );
+                                editor.change_mode(Modes::Normal);
 
-                            // Move to the previous line
-                            editor.move_cursor_up();
+                                editor.only_tab();
+                            }
+                            "tabn" | "tabnext" | "tabp" | "tabprevious" | "tabN" | "tabNext" => {
+                                let number = input_iter.next().map(str::parse::<usize>);
 
-                            // Move to the end of the previous line
-                            editor.move_cursor_to_end_line();
+                                editor.exit_command_mode::<String>(None);
 
-                            editor.current_buffer().borrow_mut().append_to_line(
-                                editor.get_cursor_doc_row(),
-                                &curr_str,
-                                editor.doc_disp_width(),
-                            );
+                                editor.change_mode(Modes::Normal);
 
-                            // Create a new gap buffer based on the new string at the cursor position
-                            gap_buf = GapBuf::from_str(
-                                editor
-                                    .current_buffer()
-                                    .borrow()
-                                    .get_str_at_cursor(editor.get_cursor_doc_row())
-                                    .to_owned(),
-                                editor.get_cursor_pos_in_line(),
-                            );
-
-                            // Reset the view
-                        } else if cursor_pos == 0
-                            && editor.current_buffer().borrow().visible_rows.0 != 0
-                        {
-                            // If the cursor is at the first positon of the line and the first visible row is not the first row of the document
+                                match number {
+                                    Some(Ok(number))
+                                        if command == "tabn" || command == "tabnext" =>
+                                    {
+                                        if let Err(e) = editor.go_to_tab(number) {
+                                            editor.notify(Level::Error, e);
+                                        }
+                                    }
+                                    Some(_) => editor.notify(Level::Error, "Invalid argument"),
+                                    None => editor.cycle_tab(command.starts_with("tabn")),
+                                }
+                            }
+                            "tabm" | "tabmove" => {
+                                let number = input_iter.next().map(str::parse::<usize>);
 
-                            // Get the current line's string
-                            let curr_str = editor
-                                .current_buffer()
-                                .borrow()
-                                .get_str_at_cursor(editor.get_cursor_doc_row())
-                                .to_owned();
+                                editor.exit_command_mode::<String>(None);
 
-                            // Remove the current line from the document
-                            editor.current_buffer().borrow_mut().remove_line_from_doc(
-                                editor.get_cursor_doc_row(),
-                                editor.doc_disp_width(),
-                            );
+                                editor.change_mode(Modes::Normal);
 
-                            editor
-                                .current_buffer()
-                                .borrow_mut()
-                                .push_vis_up(editor.doc_disp_height());
+                                match number {
+                                    Some(Ok(number)) => editor.move_tab(Some(number)),
+                                    Some(Err(_)) => editor.notify(Level::Error, "Invalid argument"),
+                                    None => editor.move_tab(None),
+                                }
+                            }
+                            "on" | "only" => {
+                                editor.exit_command_mode::<String>(None);
 
-                            // Move to the previous line
-                            editor.move_cursor_doc_up();
+                                editor.change_mode(Modes::Normal);
 
-                            // Move to the end of the previous line
-                            editor.move_cursor_to_end_line();
+                                editor.only_window();
+                            }
+                            "sp" | "split" | "vs" | "vsplit" => {
+                                let direction = if command.starts_with('v') {
+                                    Direction::SideBySide
+                                } else {
+                                    Direction::Stacked
+                                };
 
-                            editor.current_buffer().borrow_mut().append_to_line(
-                                editor.get_cursor_doc_row(),
-                                &curr_str,
-                                editor.doc_disp_width(),
-                            );
+                                editor.exit_command_mode::<String>(None);
 
-                            // Create a new gap buffer based on the new string at the cursor position
-                            gap_buf = GapBuf::from_str(
-                                editor
-                                    .current_buffer()
-                                    .borrow()
-                                    .get_str_at_cursor(editor.get_cursor_doc_row())
-                                    .to_owned(),
-                                editor.get_cursor_pos_in_line(),
-                            );
+                                editor.change_mode(Modes::Normal);
 
-                            // Reset the view
-                        }
+                                if let Err(e) = editor.split_window(direction, input_iter.next()) {
+                                    editor.notify(Level::Error, e);
+                                }
+                            }
+                            "res" | "resize" => {
+                                let arg = input_iter.next().unwrap_or("");
 
-                        let new_num_rows = editor.current_buffer().borrow().num_rows();
+                                editor.exit_command_mode::<String>(None);
 
-                        if curr_num_rows == new_num_rows {
-                            editor.print_line();
-                        } else {
-                            editor.reset_editor_view();
-                        }
-                    }
-                    // Insert a new line character to break line while in insert mode
-                    c if editor.curr_mode == Modes::Insert
-                        && (c as char == ' ' || !(c as char).is_whitespace()) =>
-                    {
-                        // Here, c can only be a non whitespace character except for space
-                        if editor.get_cursor_doc_col() < editor.doc_disp_width() {
-                            // If adding a new character on the current row will not move past the editor's right edge
+                                editor.change_mode(Modes::Normal);
 
-                            // Add the character
-                            gap_buf.insert(c as char);
+                                match (arg.parse::<isize>(), arg.starts_with(['+', '-'])) {
+                                    (Ok(delta), true) => {
+                                        editor.resize_window(Direction::Stacked, delta)
+                                    }
+                                    (Ok(height), false) => editor.resize_window(
+                                        Direction::Stacked,
+                                        height - editor.doc_disp_height() as isize,
+                                    ),
+                                    _ => {
+                                        editor.notify(Level::Error, format!("Invalid size: {arg}"))
+                                    }
+                                }
+                            }
+                            // Quitting the last window exits, so every buffer has to be saved
+                            "q" | "qa" if editor.any_buffer_dirty() => {
+                                let name = editor.dirty_buffer_name().unwrap_or_default();
 
-                            // Move the cursor to the right
-                            editor.move_cursor_right();
+                                editor.exit_command_mode(Some(format!(
+                                    "No write since last change for buffer \"{name}\" (add ! to override)"
+                                )));
 
-                            let curr_line_ind = editor
-                                .current_buffer()
-                                .borrow()
-                                .get_index_at_cursor(editor.get_cursor_doc_row());
-
-                            let num_line_rows = editor.current_buffer().borrow().lines
-                                [curr_line_ind]
-                                .rows(editor.doc_disp_width())
-                                .count();
-
-                            // Set the current line's string content to the gap buffer
-                            editor.current_buffer().borrow_mut().set_line_at_cursor(
-                                editor.get_cursor_doc_row(),
-                                gap_buf.to_string(),
-                                editor.doc_disp_width(),
-                            );
-
-                            // Reset the view
-                            if num_line_rows
-                                == editor.current_buffer().borrow().lines[curr_line_ind]
-                                    .rows(editor.doc_disp_width())
-                                    .count()
-                            {
-                                editor.print_line();
-                            } else {
-                                editor.reset_editor_view();
+                                editor.change_mode(Modes::Normal);
                             }
-                        } else {
-                            // If inserting a character will go beyond the editor's right edge (i.e. if the character should begin a new row)
-
-                            // Insert the character into the gap buffer
-                            gap_buf.insert(c as char);
+                            "q" | "q!" | "qa" | "qa!" => {
+                                break;
+                            }
+                            "wq" | "wq!" => match editor
+                                .write_current_buffer_to_file(input_iter.next(), command == "wq!")
+                            {
+                                Ok(_) => break,
+                                Err(e) => {
+                                    editor.exit_command_mode(Some(format!("Write failed: {e}")));
 
-                            let curr_line_ind = editor
-                                .current_buffer()
-                                .borrow()
-                                .get_index_at_cursor(editor.get_cursor_doc_row());
+                                    editor.change_mode(Modes::Normal);
+                                }
+                            },
+                            "wqa" => match editor.write_all_buffers_to_file() {
+                                Ok(_) => break,
+                                Err(e) => {
+                                    editor.exit_command_mode(Some(format!("Write failed: {e}")));
 
-                            let num_line_rows = editor.current_buffer().borrow().lines
-                                [curr_line_ind]
-                                .rows(editor.doc_disp_width())
-                                .count();
+                                    editor.change_mode(Modes::Normal);
+                                }
+                            },
+                            "vie" | "view" => {
+                                editor.exit_command_mode::<String>(None);
 
-                            // Set the current line's string content to the gap buffer
-                            editor.current_buffer().borrow_mut().set_line_at_cursor(
-                                editor.get_cursor_doc_row(),
-                                gap_buf.to_string(),
-                                editor.doc_disp_width(),
-                            );
+                                editor.change_mode(Modes::Normal);
 
-                            if editor.get_cursor_vis_row() < editor.doc_disp_height() {
-                                // If the cursor's row is less than the editor's height
+                                editor.view_file(input_iter.next());
 
-                                // Move down to the next row
-                                editor.move_cursor_vis_down();
-                            } else {
-                                editor.current_buffer().borrow_mut().push_vis_down();
+                                editor.warn_if_lossy();
                             }
+                            "o" => {
+                                for new_buf in input_iter {
+                                    editor.add_file_buffer(new_buf);
+                                }
 
-                            // Move the cursor to the left edge of the editor
-                            editor.move_cursor_vis_editor_left();
-
-                            // Move the cursor to the right to provide space for the character that was inserted
-                            editor.move_cursor_vis_right();
-
-                            editor.move_cursor_doc_down();
-                            editor.move_cursor_doc_editor_left();
+                                editor.change_mode(Modes::Normal);
 
-                            editor.move_cursor_doc_right();
+                                editor.exit_command_mode::<String>(None);
 
-                            // Reset the view
-                            if num_line_rows
-                                == editor.current_buffer().borrow().lines[curr_line_ind]
-                                    .rows(editor.doc_disp_width())
-                                    .count()
-                            {
-                                editor.print_line();
-                            } else {
                                 editor.reset_editor_view();
-                            }
-                        }
-                    }
-                    // Insert a character while in insert mode
-                    c if editor.curr_mode == Modes::Insert && c == RETURN => {
-                        // Collect the two sides of the gap buffer
-                        let (lhs, mut rhs) = gap_buf.collect_to_pieces();
 
-                        let num_spaces = (editor
-                            .current_buffer()
-                            .borrow()
-                            .get_line_at_cursor(editor.get_cursor_doc_row())
-                            .1
-                            .chars()
-                            .take_while(|c| *c == ' ')
-                            .count()
-                            / 4)
-                            * 4;
-
-                        rhs = (0..num_spaces).into_iter().map(|_| ' ').collect::<String>() + &rhs;
-
-                        // Set the current line to the left hand side of the gap buffer
-                        editor.current_buffer().borrow_mut().set_line_at_cursor(
-                            editor.get_cursor_doc_row(),
-                            lhs,
-                            editor.doc_disp_right_edge(),
-                        );
-
-                        // Move to the start of the new line to be created from the right hand side of the gap buffer
-                        editor.move_cursor_to_end_line();
-
-                        if editor.get_cursor_vis_row() < editor.doc_disp_height() {
-                            // If the cursor's row is less than the editor's height
+                                editor.warn_if_lossy();
+                            }
+                            "bc" if editor.current_buffer_dirty() => {
+                                editor.exit_command_mode(Some(
+                                    "No write since last change (add ! to override)",
+                                ));
 
-                            // Move down to the next row
-                            editor.move_cursor_vis_down();
-                        } else {
-                            // If the cursor's row is at the editor's height
+                                editor.change_mode(Modes::Normal);
+                            }
+                            "set" => {
+                                // Leave the command row first, some options move the cursor
+                                editor.exit_command_mode::<String>(None);
 
-                            // Push the visible rows of the document down
-                            editor.current_buffer().borrow_mut().push_vis_down();
-                        }
+                                for option in input_iter {
+                                    if let Err(e) = editor.set_option(option) {
+                                        editor.notify(Level::Error, e);
+                                    }
+                                }
 
-                        editor.move_cursor_doc_down();
-                        editor.move_cursor_vis_editor_left();
-                        editor.move_cursor_doc_editor_left();
+                                editor.change_mode(Modes::Normal);
+                            }
+                            "delm" | "delmarks" => {
+                                let letters = input_iter.collect::<String>();
 
-                        // This ind_counter variable is created in such a way as to conform with the Line struct's from_str method requiring a mutable reference to a usize variable
-                        // this will be addressed later
-                        #[allow(unused_mut)]
-                        let mut ind_counter = editor.get_cursor_doc_row();
+                                editor.delete_bookmarks(Some(&letters));
 
-                        let new_line =
-                            Line::from_str(rhs, &mut ind_counter, editor.doc_disp_width());
+                                editor.exit_command_mode::<String>(None);
 
-                        editor
-                            .current_buffer()
-                            .borrow_mut()
-                            .add_line_at_row(new_line, editor.get_cursor_doc_row());
+                                editor.change_mode(Modes::Normal);
+                            }
+                            "delm!" | "delmarks!" => {
+                                editor.delete_bookmarks(None);
 
-                        gap_buf = GapBuf::from_line(
-                            editor
-                                .current_buffer()
-                                .borrow()
-                                .get_line_at_cursor(editor.get_cursor_doc_row()),
-                            num_spaces,
-                        );
+                                editor.exit_command_mode::<String>(None);
 
-                        editor.move_cursor_to_pos(num_spaces);
+                                editor.change_mode(Modes::Normal);
+                            }
+                            "mes" | "messages" => {
+                                editor.exit_command_mode::<String>(None);
 
-                        editor.reset_editor_view();
-                    }
-                    c if editor.curr_mode == Modes::Insert && c as char == '\t' => {
-                        // For now, a tab is represented as four spaces
+                                if input_iter.next() == Some("clear") {
+                                    editor.clear_message_history();
+                                } else {
+                                    editor.show_message_history();
+                                }
 
-                        for _ in 0..4 {
-                            gap_buf.insert(' ');
-                        }
+                                editor.change_mode(Modes::Normal);
+                            }
+                            "ls" | "buffers" | "files" => {
+                                editor.exit_command_mode::<String>(None);
 
-                        let curr_pos = editor.get_cursor_pos_in_line();
+                                editor.change_mode(Modes::Normal);
 
-                        editor.current_buffer().borrow_mut().set_line_at_cursor(
-                            editor.get_cursor_doc_row(),
-                            gap_buf.to_string(),
-                            editor.doc_disp_width(),
-                        );
+                                editor.list_buffers();
+                            }
+                            "b" | "buffer" => {
+                                let spec = input_iter.collect::<Vec<_>>().join(" ");
 
-                        editor.move_cursor_to_pos(curr_pos + 4);
+                                editor.exit_command_mode::<String>(None);
 
-                        editor.reset_editor_view();
-                    }
-                    // Enter command mode
-                    COLON if editor.curr_mode == Modes::Normal => {
-                        // Change to command mode
-                        editor.change_mode(Modes::Command);
+                                editor.change_mode(Modes::Normal);
 
-                        editor.initialize_command_row();
-                    }
-                    // Execute command while in command mdoe
-                    RETURN if editor.curr_mode == Modes::Command => {
-                        let input = editor.command_buf.borrow().clone();
-                        let mut input_iter = input
-                            .as_str()
-                            .split_whitespace()
-                            .collect::<Vec<&str>>()
-                            .into_iter();
-
-                        if let Some(command) = input_iter.next() {
-                            match command {
-                                "w" => {
-                                    editor.write_current_buffer_to_file(input_iter.next());
-
-                                    editor.exit_command_mode::<String>(None);
+                                let result = if spec.is_empty() {
+                                    Ok(())
+                                } else {
+                                    editor.switch_to_buffer(&spec)
+                                };
 
-                                    editor.change_mode(Modes::Normal);
+                                if let Err(e) = result {
+                                    editor.notify(Level::Error, e);
                                 }
-                                "q" => {
-                                    break;
-                                }
-                                "wq" => {
-                                    editor.write_current_buffer_to_file(input_iter.next());
+                            }
+                            "bd" | "bdelete" | "bd!" | "bdelete!" => {
+                                let spec = input_iter.collect::<Vec<_>>().join(" ");
 
-                                    break;
-                                }
-                                "o" => {
-                                    while let Some(new_buf) = input_iter.next() {
-                                        editor.add_file_buffer(new_buf);
-                                    }
+                                editor.exit_command_mode::<String>(None);
 
-                                    editor.change_mode(Modes::Normal);
+                                editor.change_mode(Modes::Normal);
 
-                                    editor.exit_command_mode::<String>(None);
+                                let spec = (!spec.is_empty()).then_some(spec.as_str());
 
-                                    editor.reset_editor_view();
+                                if let Err(e) = editor.delete_buffer(spec, command.ends_with('!')) {
+                                    editor.notify(Level::Error, e);
                                 }
-                                "bc" => {
-                                    editor.remove_file_buffer();
+                            }
+                            "bc" | "bc!" => {
+                                editor.remove_file_buffer();
 
-                                    editor.change_mode(Modes::Normal);
+                                editor.change_mode(Modes::Normal);
 
-                                    editor.exit_command_mode::<String>(None);
+                                editor.exit_command_mode::<String>(None);
 
-                                    editor.reset_editor_view();
-                                }
-                                _ => {
-                                    editor.revert_cursor_vis_pos();
-                                    editor.print_command_message("Invalid Command");
-
-                                    editor.command_buf.borrow_mut().clear();
+                                editor.reset_editor_view();
+                            }
+                            _ => {
+                                editor.exit_command_mode(Some(format!(
+                                    "Not an editor command: {command}"
+                                )));
 
-                                    editor.change_mode(Modes::Normal);
-                                }
+                                editor.change_mode(Modes::Normal);
                             }
                         }
                     }
-                    // Delete character while in command mode
-                    BCKSP if editor.curr_mode == Modes::Command => {
-                        if editor.command_buf.borrow().len() > 0 {
-                            // If the buffer is not empty
-
-                            editor.move_cursor_vis_to(
-                                editor.command_row(),
-                                editor.doc_disp_left_edge() + editor.command_buf.borrow().len() - 1,
-                            );
-
-                            editor.pop_command_buf();
-
-                            // Move cursor to just after the original buffer minus the last character
-                            editor.move_cursor_vis_to(
-                                editor.command_row(),
-                                editor.doc_disp_left_edge() + editor.command_buf.borrow().len(),
-                            );
-                        }
+                }
+                // Search for the pattern entered
+                RETURN if editor.curr_mode == Modes::Search => {
+                    // An empty pattern searches for the last pattern again
+                    let pattern = if editor.command_buf.borrow().is_empty() {
+                        editor.last_search()
+                    } else {
+                        editor.command_buf.borrow().clone()
+                    };
+
+                    editor.exit_command_mode::<String>(None);
+
+                    if !editor.search_forward(&pattern) {
+                        editor.notify(Level::Error, format!("Pattern not found: {pattern}"));
                     }
-                    // Insert character while in command mode
-                    c if editor.curr_mode == Modes::Command => {
-                        // Push the pressed character to the buffer
-                        // Display the character to the screen
-                        editor.print_char(c as char);
 
-                        editor.move_cursor_vis_right();
+                    editor.change_mode(Modes::Normal);
+                }
+                // Delete character while in command mode
+                BCKSP
+                    if editor.curr_mode == Modes::Command || editor.curr_mode == Modes::Search =>
+                {
+                    // Kept out of the guard, an empty command line would let it fall through and
+                    // be inserted
+                    let len = editor.command_buf.borrow().len();
+
+                    // If the buffer is not empty
+                    if len > 0 {
+                        editor.move_cursor_vis_to(
+                            editor.command_row(),
+                            editor.command_text_column() + len - 1,
+                        );
+
+                        editor.pop_command_buf();
+
+                        // Move cursor to just after the original buffer minus the last character
+                        editor.move_cursor_vis_to(
+                            editor.command_row(),
+                            editor.command_text_column() + editor.command_buf.borrow().len(),
+                        );
                     }
+                }
+                // Insert character while in command mode
+                c if editor.curr_mode == Modes::Command || editor.curr_mode == Modes::Search => {
+                    // Push the pressed character to the buffer
+                    // Display the character to the screen
+                    editor.print_char(c as char);
 
-                    _ => (),
+                    editor.move_cursor_vis_right();
                 }
+
+                _ => (),
             }
-            _ => (),
+
+            if was_dirty != editor.current_buffer_dirty() {
+                editor.print_title();
+            }
+
+            editor.keep_cursor_in_view();
+
+            editor.update_gutter();
+
+            editor.update_horizontal_scroll();
+
+            editor.update_windows();
+
+            editor.print_status_line();
         }
 
         editor.flush_pen();
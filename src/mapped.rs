@@ -0,0 +1,259 @@
+use crate::storage::TextStorage;
+use std::borrow::Cow;
+use std::ffi::{c_char, c_ulonglong, CString};
+use std::fmt;
+use std::io;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+// A read only view of a file that is mapped into memory rather than read, meant for browsing files
+// too big to comfortably hold as Strings. The file is scanned for new line characters on a
+// background thread and the line ends found are handed over in batches, so the first lines can be
+// shown straight away while the rest of the file is still being indexed.
+
+// How many bytes the background thread scans before handing over the line ends it found
+const INDEX_CHUNK: usize = 4 * 1024 * 1024;
+
+extern "C" {
+    fn map_file(path: *const c_char, len: *mut c_ulonglong) -> *const c_char;
+    fn unmap_file(data: *const c_char, len: c_ulonglong);
+}
+
+/// A file mapped into memory as read only, unmapped when dropped
+pub struct MappedFile {
+    data: *const c_char,
+    len: usize,
+}
+
+// The mapping is never written to, so reading it from several threads at once is fine
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+impl MappedFile {
+    pub fn open(path: &str) -> io::Result<Self> {
+        //! Fails if the file can not be opened or is empty, there is nothing to map in an empty file
+
+        let c_path =
+            CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let mut len: c_ulonglong = 0;
+
+        let data = unsafe { map_file(c_path.as_ptr(), &mut len) };
+
+        if data.is_null() {
+            return Err(io::Error::other(format!(
+                "could not map {path} into memory"
+            )));
+        }
+
+        Ok(Self {
+            data,
+            len: len as usize,
+        })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.data as *const u8, self.len) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        unsafe { unmap_file(self.data, self.len as c_ulonglong) }
+    }
+}
+
+impl fmt::Debug for MappedFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MappedFile")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// Line ends found by the background thread in one chunk of the file
+struct IndexBatch {
+    ends: Vec<usize>,
+    /// Number of bytes from the start of the file scanned so far
+    scanned: usize,
+}
+
+fn spawn_indexer(file: Arc<MappedFile>) -> Receiver<IndexBatch> {
+    let (to_storage, from_thread) = mpsc::channel::<IndexBatch>();
+
+    thread::spawn(move || {
+        let bytes = file.bytes();
+
+        let mut start = 0;
+
+        while start < bytes.len() {
+            let end = (start + INDEX_CHUNK).min(bytes.len());
+
+            let mut ends: Vec<usize> = bytes[start..end]
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .map(|(ind, _)| start + ind)
+                .collect();
+
+            // The last line of the file does not need a new line character to end it
+            if end == bytes.len() && bytes[end - 1] != b'\n' {
+                ends.push(end);
+            }
+
+            if to_storage.send(IndexBatch { ends, scanned: end }).is_err() {
+                // The storage was dropped, nobody is waiting on the rest of the index
+                break;
+            }
+
+            start = end;
+        }
+    });
+
+    from_thread
+}
+
+/// TextStorage over a memory mapped file, lines become available as they are indexed and can not
+/// be changed
+///
+/// Lines are only turned into strings when asked for, so the whole file is never held in memory
+/// twice. The file is expected not to change while it is open, truncating it from elsewhere will
+/// make reading the lines past the new end fail
+#[derive(Debug)]
+pub struct MappedLines {
+    file: Arc<MappedFile>,
+    /// The byte offset at which each line found so far ends, the new line character is not part
+    /// of the line
+    ends: Vec<usize>,
+    /// Number of bytes the background thread has scanned
+    scanned: usize,
+    /// None once the whole file has been indexed
+    batches: Option<Receiver<IndexBatch>>,
+}
+
+impl MappedLines {
+    pub fn open(path: &str) -> io::Result<Self> {
+        //! Maps the file and starts indexing it, only returns once at least the first line is known
+
+        let file = Arc::new(MappedFile::open(path)?);
+
+        let mut new = Self {
+            file: Arc::clone(&file),
+            ends: Vec::new(),
+            scanned: 0,
+            batches: Some(spawn_indexer(file)),
+        };
+
+        while new.ends.is_empty() {
+            match new.batches.as_ref().map(|b| b.recv()) {
+                Some(Ok(batch)) => new.absorb(batch),
+                _ => {
+                    new.batches = None;
+                    break;
+                }
+            }
+        }
+
+        Ok(new)
+    }
+
+    fn absorb(&mut self, batch: IndexBatch) {
+        self.ends.extend(batch.ends);
+        self.scanned = batch.scanned;
+    }
+
+    fn line_bounds(&self, ind: usize) -> (usize, usize) {
+        //! The byte range of the line at ind, a carriage return before the new line is left out
+
+        // A file without any lines is shown as a single empty one
+        if self.ends.is_empty() {
+            return (0, 0);
+        }
+
+        let start = if ind == 0 { 0 } else { self.ends[ind - 1] + 1 };
+        let end = self.ends[ind];
+
+        if end > start && self.file.bytes()[end - 1] == b'\r' {
+            (start, end - 1)
+        } else {
+            (start, end)
+        }
+    }
+}
+
+impl TextStorage for MappedLines {
+    fn len_lines(&self) -> usize {
+        self.ends.len().max(1)
+    }
+
+    fn line(&self, ind: usize) -> Cow<'_, str> {
+        let (start, end) = self.line_bounds(ind);
+
+        String::from_utf8_lossy(&self.file.bytes()[start..end])
+    }
+
+    fn line_len(&self, ind: usize) -> usize {
+        let (start, end) = self.line_bounds(ind);
+
+        end - start
+    }
+
+    fn line_lens_from(&self, ind: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new((ind..self.len_lines()).map(|i| self.line_len(i)))
+    }
+
+    fn set_line(&mut self, _ind: usize, _content: String) {
+        panic!("a memory mapped file is read only");
+    }
+
+    fn insert_line(&mut self, _ind: usize, _content: String) {
+        panic!("a memory mapped file is read only");
+    }
+
+    fn remove_line(&mut self, _ind: usize) -> String {
+        panic!("a memory mapped file is read only");
+    }
+
+    fn lines_from(&self, ind: usize) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        Box::new((ind..self.len_lines()).map(|i| self.line(i)))
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn poll_lines(&mut self) -> usize {
+        let before = self.len_lines();
+
+        // Put back only while the indexer is still going
+        if let Some(batches) = self.batches.take() {
+            loop {
+                match batches.try_recv() {
+                    Ok(batch) => self.absorb(batch),
+                    Err(TryRecvError::Empty) => {
+                        self.batches = Some(batches);
+                        break;
+                    }
+                    Err(TryRecvError::Disconnected) => break,
+                }
+            }
+        }
+
+        self.len_lines() - before
+    }
+
+    fn index_progress(&self) -> Option<usize> {
+        self.batches
+            .as_ref()
+            .map(|_| self.scanned * 100 / self.file.len().max(1))
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+
+// Messages are what the editor tells the user, e.g. that a file was written or that a command
+// failed. Every message is kept in a history that :messages shows again, so one that was drawn over
+// before it could be read is not lost.
+
+/// The most messages kept in the history, the oldest are forgotten first
+pub const MAX_MESSAGES: usize = 200;
+
+/// How serious a message is, each level is drawn in its own color of the theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub level: Level,
+    /// Can span several lines
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+pub struct MessageHistory {
+    messages: VecDeque<Message>,
+}
+
+impl MessageHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, message: Message) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+
+        self.messages.push_back(message);
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn lines(&self) -> Vec<(Level, String)> {
+        //! Every line of every message from oldest to newest, along with the level of the message
+        //! it belongs to
+
+        self.messages
+            .iter()
+            .flat_map(|message| {
+                message
+                    .text
+                    .lines()
+                    .map(|line| (message.level, line.to_string()))
+            })
+            .collect()
+    }
+}
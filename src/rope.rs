@@ -0,0 +1,150 @@
+use crate::storage::TextStorage;
+use crate::treap::{Summarize, Treap};
+use std::borrow::Cow;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A rope of lines kept in an implicit treap, see treap.rs, with every node holding one line. Lines
+// are only ever looked up by their position, so nothing is summed up about them beyond how many
+// there are.
+
+impl Summarize for String {
+    type Summary = ();
+
+    fn summarize(&self) {}
+}
+
+#[derive(Debug)]
+pub struct LineRope {
+    lines: Treap<String>,
+}
+
+impl LineRope {
+    pub fn new() -> Self {
+        //! A rope holding a single empty line
+        Self::from_lines(Vec::new())
+    }
+
+    pub fn from_lines(mut lines: Vec<String>) -> Self {
+        //! Builds the rope in linear time, the lines keep the order they are given in and no lines
+        //! at all give a single empty line
+
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0x2545_f491_4f6c_dd1d, |d| d.as_nanos() as u64);
+
+        Self {
+            lines: Treap::from_items(lines, seed),
+        }
+    }
+}
+
+impl Default for LineRope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextStorage for LineRope {
+    fn len_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn line(&self, ind: usize) -> Cow<'_, str> {
+        Cow::Borrowed(self.lines.get(ind))
+    }
+
+    fn set_line(&mut self, ind: usize, content: String) {
+        self.lines.update(ind, |line| *line = content);
+    }
+
+    fn insert_line(&mut self, ind: usize, content: String) {
+        self.lines.insert(ind, content);
+    }
+
+    fn remove_line(&mut self, ind: usize) -> String {
+        if self.len_lines() == 1 {
+            return self.lines.update(ind, std::mem::take);
+        }
+
+        self.lines.remove(ind)
+    }
+
+    fn lines_from(&self, ind: usize) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        Box::new(
+            self.lines
+                .iter_from(ind)
+                .map(|line| Cow::Borrowed(line.as_str())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::treap::Rng;
+
+    fn lines(rope: &LineRope) -> Vec<String> {
+        rope.lines_from(0).map(Cow::into_owned).collect()
+    }
+
+    #[test]
+    fn no_lines_give_one_empty_line() {
+        let rope = LineRope::new();
+
+        assert_eq!(rope.len_lines(), 1);
+        assert_eq!(lines(&rope), vec![String::new()]);
+    }
+
+    #[test]
+    fn removing_the_only_line_empties_it() {
+        let mut rope = LineRope::from_lines(vec!["only".to_string()]);
+
+        assert_eq!(rope.remove_line(0), "only");
+        assert_eq!(lines(&rope), vec![String::new()]);
+    }
+
+    #[test]
+    fn edits_match_a_vec_of_lines() {
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+
+        let mut model: Vec<String> = (0..100).map(|i| format!("line {i}")).collect();
+        let mut rope = LineRope::from_lines(model.clone());
+
+        for step in 0..5000 {
+            match rng.below(4) {
+                0 => {
+                    let ind = rng.below(model.len() + 1);
+
+                    model.insert(ind, format!("inserted {step}"));
+                    rope.insert_line(ind, format!("inserted {step}"));
+                }
+                1 if model.len() > 1 => {
+                    let ind = rng.below(model.len());
+
+                    assert_eq!(rope.remove_line(ind), model.remove(ind));
+                }
+                2 => {
+                    let ind = rng.below(model.len());
+
+                    model[ind] = format!("set {step}");
+                    rope.set_line(ind, format!("set {step}"));
+                }
+                _ => {
+                    let ind = rng.below(model.len());
+
+                    assert_eq!(rope.line(ind), model[ind].as_str());
+                    assert_eq!(rope.line_len(ind), model[ind].len());
+                    assert!(rope.lines_from(ind).eq(model[ind..].iter().map(Cow::from)));
+                }
+            }
+
+            assert_eq!(rope.len_lines(), model.len());
+        }
+
+        assert_eq!(lines(&rope), model);
+    }
+}
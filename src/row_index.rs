@@ -0,0 +1,296 @@
+use crate::treap::{Summarize, Summary, Treap};
+
+// Keeps track of how many rows each line of a document spans in an implicit treap, see treap.rs,
+// the same structure LineRope keeps the lines in. Every node holds the row count of one line and
+// sums up the rows of its subtree, so the row a line starts at, the line a row belongs to, changing
+// the rows of a line and inserting or removing a line all walk a single path from the root.
+//
+// A row count can be kept as an estimate, for lines that have not been laid out since the width
+// changed. Estimates are counted like any other rows until they are set to what the line really
+// spans.
+
+/// The rows of one line
+#[derive(Debug, Clone, Copy)]
+struct Line {
+    rows: usize,
+    /// Whether rows is only an estimate
+    estimated: bool,
+}
+
+/// What a run of lines spans
+#[derive(Debug, Clone, Copy, Default)]
+struct Rows {
+    rows: usize,
+    /// Number of the lines whose rows are estimates
+    estimates: usize,
+}
+
+impl Summary for Rows {
+    fn combine(self, other: Self) -> Self {
+        Self {
+            rows: self.rows + other.rows,
+            estimates: self.estimates + other.estimates,
+        }
+    }
+}
+
+impl Summarize for Line {
+    type Summary = Rows;
+
+    fn summarize(&self) -> Rows {
+        Rows {
+            rows: self.rows,
+            estimates: self.estimated as usize,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RowIndex {
+    lines: Treap<Line>,
+}
+
+impl RowIndex {
+    pub fn new() -> Self {
+        Self::from_counts(Vec::new())
+    }
+
+    pub fn from_counts(counts: Vec<usize>) -> Self {
+        Self::build(counts, false)
+    }
+
+    pub fn from_estimates(counts: Vec<usize>) -> Self {
+        //! Builds the index with every count taken as an estimate
+        Self::build(counts, true)
+    }
+
+    fn build(counts: Vec<usize>, estimated: bool) -> Self {
+        //! Builds the index in linear time
+
+        let lines = counts
+            .into_iter()
+            .map(|rows| Line { rows, estimated })
+            .collect();
+
+        // Priorities only have to keep the tree balanced, they do not have to differ between runs
+        Self {
+            lines: Treap::from_items(lines, 0x2545_f491_4f6c_dd1d),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        //! Number of lines being tracked
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn total(&self) -> usize {
+        //! Number of rows spanned by all lines together
+        self.lines.summary().rows
+    }
+
+    pub fn has_estimates(&self) -> bool {
+        self.lines.summary().estimates > 0
+    }
+
+    pub fn rows_of(&self, line_ind: usize) -> usize {
+        self.lines.get(line_ind).rows
+    }
+
+    pub fn is_estimated(&self, line_ind: usize) -> bool {
+        self.lines.get(line_ind).estimated
+    }
+
+    pub fn first_row_of(&self, line_ind: usize) -> usize {
+        //! The row at which the line at line_ind begins, i.e. the rows spanned by every line before it
+        self.lines.prefix(line_ind).rows
+    }
+
+    pub fn line_at_row(&self, row: usize) -> usize {
+        //! The index of the line spanning row, rows past the end give the last line
+
+        // Lines spanning no rows at all at the very end are never found either
+        self.lines
+            .find(row, |rows| rows.rows)
+            .unwrap_or(self.len().saturating_sub(1))
+    }
+
+    pub fn set(&mut self, line_ind: usize, rows: usize) {
+        //! Changes the number of rows the line at line_ind spans, which are no longer an estimate
+
+        let line = self.lines.get(line_ind);
+
+        if line.rows == rows && !line.estimated {
+            return;
+        }
+
+        self.lines.update(line_ind, |line| {
+            *line = Line {
+                rows,
+                estimated: false,
+            }
+        });
+    }
+
+    pub fn insert(&mut self, line_ind: usize, rows: usize) {
+        //! Adds a line spanning rows so that it ends up at line_ind
+        self.insert_line(line_ind, rows, false);
+    }
+
+    pub fn insert_estimate(&mut self, line_ind: usize, rows: usize) {
+        //! Adds a line whose rows are only estimated so that it ends up at line_ind
+        self.insert_line(line_ind, rows, true);
+    }
+
+    fn insert_line(&mut self, line_ind: usize, rows: usize, estimated: bool) {
+        self.lines.insert(line_ind, Line { rows, estimated });
+    }
+
+    pub fn remove(&mut self, line_ind: usize) -> usize {
+        //! Removes the line at line_ind and returns the number of rows it spanned
+        self.lines.remove(line_ind).rows
+    }
+}
+
+impl Default for RowIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::treap::Rng;
+
+    /// The rows of every line and whether they are an estimate, looked through one line at a time
+    struct Model(Vec<(usize, bool)>);
+
+    impl Model {
+        fn total(&self) -> usize {
+            self.0.iter().map(|(rows, _)| rows).sum()
+        }
+
+        fn first_row_of(&self, line_ind: usize) -> usize {
+            self.0[..line_ind].iter().map(|(rows, _)| rows).sum()
+        }
+
+        fn line_at_row(&self, row: usize) -> usize {
+            let mut first_row = 0;
+
+            for (line_ind, (rows, _)) in self.0.iter().enumerate() {
+                first_row += rows;
+
+                if row < first_row {
+                    return line_ind;
+                }
+            }
+
+            self.0.len().saturating_sub(1)
+        }
+    }
+
+    fn check(index: &RowIndex, model: &Model) {
+        assert_eq!(index.len(), model.0.len());
+        assert_eq!(index.total(), model.total());
+        assert_eq!(
+            index.has_estimates(),
+            model.0.iter().any(|(_, estimated)| *estimated)
+        );
+
+        for (line_ind, (rows, estimated)) in model.0.iter().enumerate() {
+            assert_eq!(index.rows_of(line_ind), *rows);
+            assert_eq!(index.is_estimated(line_ind), *estimated);
+            assert_eq!(index.first_row_of(line_ind), model.first_row_of(line_ind));
+        }
+
+        for row in 0..model.total() + 2 {
+            assert_eq!(index.line_at_row(row), model.line_at_row(row));
+        }
+    }
+
+    #[test]
+    fn built_index_matches_its_counts() {
+        let counts = vec![1, 3, 2, 1, 5, 1];
+
+        let model = Model(counts.iter().map(|rows| (*rows, false)).collect());
+        check(&RowIndex::from_counts(counts.clone()), &model);
+
+        let model = Model(counts.iter().map(|rows| (*rows, true)).collect());
+        check(&RowIndex::from_estimates(counts), &model);
+    }
+
+    #[test]
+    fn empty_index_has_no_rows() {
+        let index = RowIndex::new();
+
+        assert!(index.is_empty());
+        assert_eq!(index.total(), 0);
+        assert_eq!(index.line_at_row(3), 0);
+    }
+
+    #[test]
+    fn setting_an_estimate_makes_it_exact() {
+        let mut index = RowIndex::from_estimates(vec![2, 2, 2]);
+
+        // The same count as estimated still counts as laid out
+        index.set(1, 2);
+
+        check(&index, &Model(vec![(2, true), (2, false), (2, true)]));
+    }
+
+    #[test]
+    fn edits_match_a_vec_of_counts() {
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+
+        let mut model = Model((0..50).map(|ind| (1 + ind % 4, ind % 3 == 0)).collect());
+        let mut index = RowIndex::from_counts(model.0.iter().map(|(rows, _)| *rows).collect());
+
+        for (line_ind, (rows, estimated)) in model.0.iter().enumerate() {
+            if *estimated {
+                index.remove(line_ind);
+                index.insert_estimate(line_ind, *rows);
+            }
+        }
+
+        for step in 0..2000 {
+            let rows = 1 + rng.below(5);
+
+            match rng.below(4) {
+                0 => {
+                    let line_ind = rng.below(model.0.len() + 1);
+
+                    model.0.insert(line_ind, (rows, false));
+                    index.insert(line_ind, rows);
+                }
+                1 => {
+                    let line_ind = rng.below(model.0.len() + 1);
+
+                    model.0.insert(line_ind, (rows, true));
+                    index.insert_estimate(line_ind, rows);
+                }
+                2 if model.0.len() > 1 => {
+                    let line_ind = rng.below(model.0.len());
+
+                    assert_eq!(index.remove(line_ind), model.0.remove(line_ind).0);
+                }
+                _ => {
+                    let line_ind = rng.below(model.0.len());
+
+                    model.0[line_ind] = (rows, false);
+                    index.set(line_ind, rows);
+                }
+            }
+
+            // Checking every line and row is slow enough to only be done now and then
+            if step % 50 == 0 {
+                check(&index, &model);
+            }
+        }
+
+        check(&index, &model);
+    }
+}
@@ -0,0 +1,820 @@
+use crate::term::{begin_synchronized_update, end_synchronized_update, hide_cursor, show_cursor};
+use std::io::{self, Write};
+use std::mem;
+use std::str;
+
+// The editor draws by writing text and escape sequences to the draw buffer as if it were talking
+// to the terminal directly. Instead of passing them straight on, Screen plays them onto an off
+// screen grid of cells, the back buffer, and on flush compares it against the front buffer, which
+// holds what the terminal is showing. Only the cells that differ are sent, so redrawing the whole
+// document after an edit costs no more than the cells the edit changed. The rows written to since
+// the last flush are remembered, and only those are compared, so a flush with nothing drawn since
+// costs next to nothing.
+//
+// Each frame is sent with the cursor hidden, and as a synchronized update when the terminal
+// supports it, so the cursor is not seen jumping around while the frame is drawn.
+//
+// A part of the screen can be scrolled sideways through a ScrollWindow. The editor keeps drawing
+// lines from their first column as if the screen were wide enough for them, and the columns written
+// inside the window are shifted left by the window's offset, with whatever ends up outside of it
+// cut off.
+//
+// When the screen is shared by several windows, a Clip keeps what is drawn for one of them inside it,
+// so a window can be drawn the same way as when it had every column of its rows to itself.
+//
+// Only the sequences the editor uses are understood: moving the cursor, erasing lines or the screen
+// and setting colors. Anything else is handed to the terminal as is, after the cells changed before
+// it have been sent.
+//
+// Every character is given one cell, as the editor lays lines out with one column to a character.
+// Terminals draw wide characters, most CJK and emoji, over two cells and combining marks over none,
+// which would put the terminal out of step with the grid, so those are drawn as PLACEHOLDER instead.
+
+const ESC: char = '\u{001b}';
+
+// Erasing the rest of a row takes three bytes, below this many changed blanks they are sent as is
+const ERASE_MIN: usize = 3;
+
+/// Drawn in place of a character that does not take exactly one cell
+pub const PLACEHOLDER: char = '?';
+
+/// Characters terminals draw over two cells, the wide and fullwidth ones of Unicode's east asian
+/// widths and emoji, close enough for the blocks they come in
+const WIDE: [(char, char); 15] = [
+    ('\u{1100}', '\u{115f}'),
+    ('\u{231a}', '\u{231b}'),
+    ('\u{2e80}', '\u{303e}'),
+    ('\u{3041}', '\u{33ff}'),
+    ('\u{3400}', '\u{4dbf}'),
+    ('\u{4e00}', '\u{9fff}'),
+    ('\u{a000}', '\u{a4cf}'),
+    ('\u{ac00}', '\u{d7a3}'),
+    ('\u{f900}', '\u{faff}'),
+    ('\u{fe30}', '\u{fe4f}'),
+    ('\u{ff00}', '\u{ff60}'),
+    ('\u{ffe0}', '\u{ffe6}'),
+    ('\u{1f300}', '\u{1f64f}'),
+    ('\u{1f680}', '\u{1f9ff}'),
+    ('\u{20000}', '\u{3fffd}'),
+];
+
+/// Characters terminals draw over no cells of their own, combining marks, joiners and variation
+/// selectors
+const ZERO_WIDTH: [(char, char); 8] = [
+    ('\u{0300}', '\u{036f}'),
+    ('\u{0483}', '\u{0489}'),
+    ('\u{1ab0}', '\u{1aff}'),
+    ('\u{1dc0}', '\u{1dff}'),
+    ('\u{200b}', '\u{200f}'),
+    ('\u{20d0}', '\u{20ff}'),
+    ('\u{fe00}', '\u{fe0f}'),
+    ('\u{fe20}', '\u{fe2f}'),
+];
+
+fn cell_char(c: char) -> char {
+    //! What is drawn in the cell c is written to
+
+    let within =
+        |ranges: &[(char, char)]| ranges.iter().any(|(from, to)| (*from..=*to).contains(&c));
+
+    if within(&WIDE) || within(&ZERO_WIDTH) {
+        PLACEHOLDER
+    } else {
+        c
+    }
+}
+
+/// The colors of a cell, None leaves the terminal's default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub fg: Option<[u8; 3]>,
+    pub bg: Option<[u8; 3]>,
+}
+
+impl Style {
+    fn sgr(&self, from: &Style) -> String {
+        //! The SGR sequence that turns the from style into this one
+
+        let mut params = Vec::new();
+
+        if (from.fg.is_some() && self.fg.is_none()) || (from.bg.is_some() && self.bg.is_none()) {
+            params.push("0".to_string());
+        }
+
+        if let Some([r, g, b]) = self.fg {
+            params.push(format!("38;2;{r};{g};{b}"));
+        }
+
+        if let Some([r, g, b]) = self.bg {
+            params.push(format!("48;2;{r};{g};{b}"));
+        }
+
+        format!("{ESC}[{}m", params.join(";"))
+    }
+
+    fn apply_sgr(&mut self, params: &str) {
+        //! Changes the style the way the terminal would for an SGR sequence with the given
+        //! parameters
+
+        let mut nums = params.split(';').map(|p| p.parse::<u8>().unwrap_or(0));
+
+        while let Some(num) = nums.next() {
+            match num {
+                0 => *self = Style::default(),
+                38 | 48 => {
+                    if nums.next() != Some(2) {
+                        continue;
+                    }
+
+                    let rgb = [
+                        nums.next().unwrap_or(0),
+                        nums.next().unwrap_or(0),
+                        nums.next().unwrap_or(0),
+                    ];
+
+                    if num == 38 {
+                        self.fg = Some(rgb);
+                    } else {
+                        self.bg = Some(rgb);
+                    }
+                }
+                39 => self.fg = None,
+                49 => self.bg = None,
+                _ => (),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: Style,
+}
+
+impl Cell {
+    fn blank(style: Style) -> Self {
+        Self { ch: ' ', style }
+    }
+
+    // Stands in for cells whose content on the terminal is not known, it never matches a cell that
+    // was drawn so those cells are always sent
+    const UNKNOWN: Cell = Cell {
+        ch: '\0',
+        style: Style { fg: None, bg: None },
+    };
+}
+
+/// A screenful of cells, rows and columns are counted from 0 here unlike on the terminal
+#[derive(Debug, Clone)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, fill: Cell) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Option<&Cell> {
+        if row < self.height && column < self.width {
+            self.cells.get(row * self.width + column)
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, row: usize, column: usize, cell: Cell) {
+        if row < self.height && column < self.width {
+            self.cells[row * self.width + column] = cell;
+        }
+    }
+
+    fn fill_row(&mut self, row: usize, columns: impl Iterator<Item = usize>, cell: Cell) {
+        for column in columns {
+            self.set(row, column, cell);
+        }
+    }
+
+    fn fill(&mut self, cell: Cell) {
+        self.cells.fill(cell);
+    }
+}
+
+/// A block of rows whose columns from left on are scrolled sideways, counted from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrollWindow {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub width: usize,
+    /// How many columns the contents are scrolled to the left
+    pub offset: usize,
+}
+
+impl ScrollWindow {
+    fn contains(&self, row: usize, column: usize) -> bool {
+        (self.top..=self.bottom).contains(&row) && column >= self.left
+    }
+
+    fn map(&self, column: usize) -> Option<usize> {
+        //! Where a column written inside the window ends up, None if it is scrolled out of view
+
+        column
+            .checked_sub(self.offset)
+            .filter(|c| (self.left..self.left + self.width).contains(c))
+    }
+}
+
+/// Keeps writes to the rows from area_top to area_bottom inside a block of them, counted from 0,
+/// writes to the rest of those rows are dropped while rows outside of them are left alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clip {
+    pub area_top: usize,
+    pub area_bottom: usize,
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl Clip {
+    fn allows(&self, row: usize, column: usize) -> bool {
+        !(self.area_top..=self.area_bottom).contains(&row)
+            || ((self.top..=self.bottom).contains(&row)
+                && (self.left..=self.right).contains(&column))
+    }
+}
+
+/// Writes to the terminal through out, sending only what changed between frames
+pub struct Screen<W: Write> {
+    out: W,
+    /// What the terminal is showing
+    front: Grid,
+    /// What the editor has drawn since the last flush
+    back: Grid,
+    /// The rows of the back buffer written to since they were last compared with the front buffer
+    damaged: Vec<bool>,
+    /// Where the editor's writes go, as (row, column)
+    pen: (usize, usize),
+    pen_style: Style,
+    /// Where the terminal's cursor is, None when it is not known
+    cursor: Option<(usize, usize)>,
+    /// The style the terminal is drawing with
+    style: Style,
+    /// Bytes written that do not form a whole character or escape sequence yet
+    pending: Vec<u8>,
+    /// Bytes ready to be sent to out
+    frame: String,
+    /// Whether frames are wrapped in a synchronized update
+    synchronized: bool,
+    scroll_window: Option<ScrollWindow>,
+    clip: Option<Clip>,
+}
+
+impl<W: Write> Screen<W> {
+    pub fn new(out: W, width: usize, height: usize) -> Self {
+        Self {
+            out,
+            // Nothing is sent for the screen the editor was started on, drawing begins once it
+            // switches to the alternate screen
+            front: Grid::new(width, height, Cell::blank(Style::default())),
+            back: Grid::new(width, height, Cell::blank(Style::default())),
+            damaged: vec![false; height],
+            pen: (0, 0),
+            pen_style: Style::default(),
+            cursor: None,
+            style: Style::default(),
+            pending: Vec::new(),
+            frame: String::new(),
+            synchronized: false,
+            scroll_window: None,
+            clip: None,
+        }
+    }
+
+    pub fn set_scroll_window(&mut self, scroll_window: Option<ScrollWindow>) {
+        //! Only changes where later writes go, what was already drawn stays where it is
+        self.scroll_window = scroll_window;
+    }
+
+    pub fn set_clip(&mut self, clip: Option<Clip>) {
+        //! Like set_scroll_window, only later writes are kept in
+        self.clip = clip;
+    }
+
+    fn screen_column(&self, row: usize, column: usize) -> Option<usize> {
+        //! The column on screen that a write to column of row lands on, None if it is dropped
+
+        match self.scroll_window {
+            Some(window) if window.contains(row, column) => window.map(column),
+            _ => Some(column),
+        }
+        .filter(|column| self.allowed(row, *column))
+    }
+
+    fn allowed(&self, row: usize, column: usize) -> bool {
+        self.clip.is_none_or(|clip| clip.allows(row, column))
+    }
+
+    pub fn set_synchronized(&mut self, synchronized: bool) {
+        //! Only turn on for terminals that support synchronized updates, others may print the
+        //! sequences as text
+        self.synchronized = synchronized;
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        //! Starts over with grids of the new size, everything drawn afterwards is sent in full since
+        //! the terminal may have moved its contents around while resizing
+
+        self.front = Grid::new(width, height, Cell::UNKNOWN);
+        self.back = Grid::new(width, height, Cell::blank(Style::default()));
+        self.damaged = vec![true; height];
+        self.cursor = None;
+    }
+
+    pub fn invalidate(&mut self) {
+        //! Forgets what the terminal is showing so that the next flush sends every cell
+        self.front.fill(Cell::UNKNOWN);
+        self.damaged.fill(true);
+        self.cursor = None;
+    }
+
+    fn damage(&mut self, row: usize) {
+        if let Some(damaged) = self.damaged.get_mut(row) {
+            *damaged = true;
+        }
+    }
+
+    // ==================== PLAYING WRITES ONTO THE BACK BUFFER ====================
+
+    fn interpret(&mut self, text: &str) -> usize {
+        //! Plays text onto the back buffer, returns the number of bytes used, an escape sequence cut
+        //! off at the end of text is left for the next write
+
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            if c != ESC {
+                self.put(c);
+                continue;
+            }
+
+            match chars.peek() {
+                None => return start,
+                Some((_, '[')) => {
+                    chars.next();
+                }
+                Some(_) => {
+                    // Not a control sequence, hand the escape and the character after it over
+                    let (_, next) = chars.next().unwrap();
+                    self.pass_through(&format!("{ESC}{next}"));
+                    continue;
+                }
+            }
+
+            // Parameters and intermediates up to the final byte
+            let Some((end, last)) = chars.find(|(_, c)| ('@'..='~').contains(c)) else {
+                return start;
+            };
+
+            self.control(&text[start..end + last.len_utf8()]);
+        }
+
+        text.len()
+    }
+
+    fn put(&mut self, c: char) {
+        match c {
+            '\r' => self.pen.1 = 0,
+            '\n' => self.pen.0 += 1,
+            c if c.is_control() => (),
+            c => {
+                if let Some(column) = self.screen_column(self.pen.0, self.pen.1) {
+                    self.damage(self.pen.0);
+
+                    self.back.set(
+                        self.pen.0,
+                        column,
+                        Cell {
+                            ch: cell_char(c),
+                            style: self.pen_style,
+                        },
+                    );
+                }
+
+                self.pen.1 += 1;
+            }
+        }
+    }
+
+    fn control(&mut self, sequence: &str) {
+        //! Handles a whole control sequence, beginning with the escape and ending with the final byte
+
+        let body = &sequence[2..];
+        let (params, last) = body.split_at(body.len() - 1);
+
+        let nums: Vec<usize> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+
+        let nth = |n: usize| nums.get(n).copied().unwrap_or(0);
+
+        let blank = Cell::blank(self.pen_style);
+        let row = self.pen.0;
+        let width = self.back.width;
+        let column = match self.scroll_window {
+            Some(window) if window.contains(row, self.pen.1) => window.map(self.pen.1),
+            _ => Some(self.pen.1),
+        }
+        .unwrap_or(self.pen.1)
+        .min(width.saturating_sub(1));
+        let clip = self.clip;
+        let allowed = |c: &usize| clip.is_none_or(|clip| clip.allows(row, *c));
+
+        match last {
+            _ if params.starts_with('?') => {
+                self.pass_through(sequence);
+
+                // The alternate screen is cleared when switched to, so all of it has to be sent
+                // again. Once switched back to the normal screen nothing drawn is meant for it
+                match sequence.strip_prefix(&format!("{ESC}[?1049")) {
+                    Some("h") => self.invalidate(),
+                    Some("l") => self.front = self.back.clone(),
+                    _ => (),
+                }
+            }
+            "H" | "f" => {
+                // Columns are not kept on screen here, inside a scroll window they may be past its
+                // right edge until scrolled into view
+                self.pen = (nth(0).max(1).min(self.back.height) - 1, nth(1).max(1) - 1);
+            }
+            "K" => {
+                self.damage(row);
+
+                match nth(0) {
+                    0 => self
+                        .back
+                        .fill_row(row, (column..width).filter(allowed), blank),
+                    1 => self.back.fill_row(row, (0..=column).filter(allowed), blank),
+                    _ => self.back.fill_row(row, (0..width).filter(allowed), blank),
+                }
+            }
+            "J" if nth(0) == 2 => {
+                self.back.fill(blank);
+                self.damaged.fill(true);
+            }
+            "m" => self.pen_style.apply_sgr(params),
+            _ => self.pass_through(sequence),
+        }
+    }
+
+    fn pass_through(&mut self, sequence: &str) {
+        //! Sends a sequence the grid does not model, the cells drawn before it are sent first so
+        //! that it reaches the terminal in the same order it was written
+
+        self.render();
+
+        self.frame.push_str(sequence);
+    }
+
+    // ==================== SENDING THE DIFFERENCE ====================
+
+    fn render(&mut self) {
+        //! Adds the changes from the front buffer to the back buffer to the frame, afterwards the
+        //! front buffer matches the back buffer
+
+        let width = self.back.width;
+
+        for row in 0..self.back.height {
+            if !mem::take(&mut self.damaged[row]) {
+                continue;
+            }
+
+            let blank_tail = self.blank_tail(row);
+
+            for column in 0..width {
+                let cell = *self.back.get(row, column).unwrap();
+
+                if self.front.get(row, column) == Some(&cell) {
+                    continue;
+                }
+
+                self.move_to(row, column);
+
+                if cell.style != self.style {
+                    let sgr = cell.style.sgr(&self.style);
+                    self.frame.push_str(&sgr);
+                    self.style = cell.style;
+                }
+
+                if column >= blank_tail
+                    && (column..width)
+                        .filter(|c| self.front.get(row, *c) != self.back.get(row, *c))
+                        .count()
+                        > ERASE_MIN
+                {
+                    // The rest of the row is blank, erasing it is shorter than sending the blanks
+                    self.frame.push_str(&format!("{ESC}[K"));
+                    self.front.fill_row(row, column..width, cell);
+                    break;
+                }
+
+                self.frame.push(cell.ch);
+
+                self.front.set(row, column, cell);
+
+                // Past the last column the terminal's cursor waits to wrap, where it ends up next
+                // is up to the terminal
+                self.cursor = (column + 1 < self.back.width).then_some((row, column + 1));
+            }
+        }
+    }
+
+    fn blank_tail(&self, row: usize) -> usize {
+        //! The column from which the row of the back buffer is blank to its end in a single style
+
+        let width = self.back.width;
+
+        let Some(last) = self.back.get(row, width.saturating_sub(1)).copied() else {
+            return width;
+        };
+
+        if last.ch != ' ' {
+            return width;
+        }
+
+        (0..width)
+            .rev()
+            .take_while(|c| self.back.get(row, *c) == Some(&last))
+            .last()
+            .unwrap_or(width)
+    }
+
+    fn move_to(&mut self, row: usize, column: usize) {
+        //! Moves the terminal's cursor with the shortest of the ways available
+
+        let jump = format!("{ESC}[{};{}H", row + 1, column + 1);
+
+        match self.cursor {
+            Some(cursor) if cursor == (row, column) => (),
+            Some((cursor_row, cursor_column)) if cursor_row == row && cursor_column < column => {
+                // Sending the cells skipped over again is often shorter than any move, as long as
+                // they do not need another style
+                let skipped: Option<String> = (cursor_column..column)
+                    .map(|c| {
+                        self.front
+                            .get(row, c)
+                            .filter(|cell| cell.style == self.style && **cell != Cell::UNKNOWN)
+                            .map(|cell| cell.ch)
+                    })
+                    .collect();
+
+                let forward = format!("{ESC}[{}C", column - cursor_column);
+
+                let shortest = [skipped, Some(forward), Some(jump)]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|s| s.len())
+                    .unwrap();
+
+                self.frame.push_str(&shortest);
+            }
+            _ => self.frame.push_str(&jump),
+        }
+
+        self.cursor = Some((row, column));
+    }
+
+    fn place_cursor(&mut self) {
+        //! Leaves the terminal's cursor where the editor's pen is
+
+        let row = self.pen.0;
+
+        let column = match self.scroll_window {
+            Some(window) if window.contains(row, self.pen.1) => self
+                .pen
+                .1
+                .saturating_sub(window.offset)
+                .clamp(window.left, window.left + window.width),
+            _ => self.pen.1,
+        }
+        .min(self.back.width.saturating_sub(1));
+
+        if self.cursor != Some((row, column)) {
+            let jump = format!("{ESC}[{};{}H", row + 1, column + 1);
+            self.frame.push_str(&jump);
+            self.cursor = Some((row, column));
+        }
+    }
+}
+
+impl<W: Write> Write for Screen<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+
+        let pending = mem::take(&mut self.pending);
+
+        let valid = match str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            Err(e) => e.valid_up_to(),
+        };
+
+        // Checked above, only whole characters are interpreted
+        let used = self.interpret(str::from_utf8(&pending[..valid]).unwrap());
+
+        self.pending = pending[used..].to_vec();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.render();
+        self.place_cursor();
+
+        let frame = mem::take(&mut self.frame);
+
+        if !frame.is_empty() {
+            if self.synchronized {
+                self.out.write_all(begin_synchronized_update().as_bytes())?;
+            }
+
+            self.out.write_all(hide_cursor().as_bytes())?;
+            self.out.write_all(frame.as_bytes())?;
+            self.out.write_all(show_cursor().as_bytes())?;
+
+            if self.synchronized {
+                self.out.write_all(end_synchronized_update().as_bytes())?;
+            }
+        }
+
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen() -> Screen<Vec<u8>> {
+        Screen::new(Vec::new(), 10, 4)
+    }
+
+    fn draw(screen: &mut Screen<Vec<u8>>, text: &str) -> String {
+        //! Writes text and flushes it, giving what was sent to the terminal without the hiding and
+        //! showing of the cursor around it
+
+        screen.write_all(text.as_bytes()).unwrap();
+        screen.flush().unwrap();
+
+        let sent = String::from_utf8(mem::take(&mut screen.out)).unwrap();
+
+        sent.strip_prefix(hide_cursor())
+            .and_then(|sent| sent.strip_suffix(show_cursor()))
+            .unwrap_or(&sent)
+            .to_string()
+    }
+
+    fn shown(screen: &Screen<Vec<u8>>, row: usize) -> String {
+        //! The row as the terminal shows it
+        (0..screen.front.width)
+            .map(|column| screen.front.get(row, column).unwrap().ch)
+            .collect()
+    }
+
+    #[test]
+    fn an_unchanged_frame_sends_nothing() {
+        let mut screen = screen();
+
+        assert_eq!(draw(&mut screen, "\x1b[1;1Hhello"), "\x1b[1;1Hhello");
+        assert_eq!(draw(&mut screen, "\x1b[1;1Hhello"), "");
+        assert_eq!(draw(&mut screen, ""), "");
+    }
+
+    #[test]
+    fn a_changed_cell_is_sent_alone() {
+        let mut screen = screen();
+
+        draw(&mut screen, "\x1b[2;1Hhello");
+
+        // Moved to, then back to where the pen was left
+        assert_eq!(draw(&mut screen, "\x1b[2;1Hhallo"), "\x1b[2;2Ha\x1b[2;6H");
+        assert_eq!(shown(&screen, 1), "hallo     ");
+
+        // A cell just after the cursor is reached by sending the one in between again
+        draw(&mut screen, "\x1b[2;1H");
+        assert_eq!(draw(&mut screen, "\x1b[2;1Hhaxlo\x1b[2;1H"), "hax\x1b[2;1H");
+    }
+
+    #[test]
+    fn a_blank_tail_is_erased() {
+        let mut screen = screen();
+
+        draw(&mut screen, "\x1b[1;1Hhello worl");
+
+        assert_eq!(draw(&mut screen, "\x1b[1;1Hhe\x1b[K"), "\x1b[1;3H\x1b[K");
+        assert_eq!(shown(&screen, 0), "he        ");
+
+        // A few blanks are shorter sent as they are
+        draw(&mut screen, "\x1b[1;1Hhello");
+        assert_eq!(draw(&mut screen, "\x1b[1;4H\x1b[K"), "\x1b[1;4H  \x1b[1;4H");
+    }
+
+    #[test]
+    fn styles_are_only_sent_when_they_change() {
+        let mut screen = screen();
+
+        assert_eq!(
+            draw(&mut screen, "\x1b[1;1H\x1b[38;2;1;2;3mab\x1b[0mc"),
+            "\x1b[1;1H\x1b[38;2;1;2;3mab\x1b[0mc"
+        );
+        assert_eq!(draw(&mut screen, "\x1b[1;1H\x1b[38;2;1;2;3mab\x1b[0mc"), "");
+    }
+
+    #[test]
+    fn scroll_windows_shift_their_columns() {
+        let mut screen = screen();
+
+        screen.set_scroll_window(Some(ScrollWindow {
+            top: 1,
+            bottom: 2,
+            left: 2,
+            width: 5,
+            offset: 3,
+        }));
+
+        draw(&mut screen, "\x1b[1;1Habcdefghij\x1b[2;1Habcdefghijkl");
+
+        // Only the rows of the window are scrolled, and what is scrolled past its edges is cut off
+        assert_eq!(shown(&screen, 0), "abcdefghij");
+        assert_eq!(shown(&screen, 1), "abfghij   ");
+
+        // Erasing inside the window erases where the pen ends up on screen
+        draw(&mut screen, "\x1b[2;7H\x1b[K");
+        assert_eq!(shown(&screen, 1), "abf       ");
+
+        // Cells drawn before the window changes stay where they are
+        screen.set_scroll_window(None);
+        draw(&mut screen, "\x1b[3;1Habc");
+        assert_eq!(shown(&screen, 1), "abf       ");
+        assert_eq!(shown(&screen, 2), "abc       ");
+    }
+
+    #[test]
+    fn clips_keep_writes_inside_them() {
+        let mut screen = screen();
+
+        draw(
+            &mut screen,
+            "\x1b[1;1H..........\x1b[2;1H..........\x1b[4;1H..........",
+        );
+
+        screen.set_clip(Some(Clip {
+            area_top: 0,
+            area_bottom: 2,
+            top: 1,
+            bottom: 2,
+            left: 3,
+            right: 5,
+        }));
+
+        draw(
+            &mut screen,
+            "\x1b[1;1H0123456789\x1b[2;1H0123456789\x1b[2;1H\x1b[2K\x1b[4;1H0123456789",
+        );
+
+        // Rows of the area outside the clip are left alone, rows outside the area are not clipped
+        assert_eq!(shown(&screen, 0), "..........");
+        assert_eq!(shown(&screen, 1), "...   ....");
+        assert_eq!(shown(&screen, 3), "0123456789");
+
+        screen.set_clip(None);
+    }
+
+    #[test]
+    fn characters_that_are_not_one_cell_wide_are_replaced() {
+        let mut screen = screen();
+
+        draw(&mut screen, "\x1b[1;1Ha日b😀e\u{301}é");
+
+        assert_eq!(shown(&screen, 0), "a?b?e?é   ");
+    }
+
+    #[test]
+    fn resizing_sends_every_cell_again() {
+        let mut screen = screen();
+
+        draw(&mut screen, "\x1b[1;1Hab");
+
+        screen.resize(4, 2);
+
+        assert_eq!(
+            draw(&mut screen, "\x1b[1;1Hab"),
+            "\x1b[1;1Hab  \x1b[2;1H\x1b[K\x1b[1;3H"
+        );
+    }
+}
@@ -0,0 +1,545 @@
+use crate::diff;
+use crate::document::{BufferKind, Document};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+
+// Signs are glyphs drawn in a column to the left of the line numbers to point out something about a
+// line, e.g. a diagnostic, a change since the last commit, a bookmark or a search hit. Each sign
+// comes from a provider, and when several signs land on the same line the one whose provider has
+// the highest priority is drawn.
+//
+// Providers can give their signs in two ways: placed ahead of time into a document, where they are
+// kept in a SignTable and moved along with their lines as the document is edited, or worked out
+// from the document every time the lines in view are drawn.
+//
+// The editor comes with providers for bookmarks, search hits and changes against git or the other
+// buffer of -d. Nothing in the editor produces diagnostics, a provider for them would place its
+// signs with DIAGNOSTIC_PRIORITY.
+
+// Suggested priorities for the kinds of providers, so that providers written separately agree on
+// which sign is more important
+pub const DIAGNOSTIC_PRIORITY: u32 = 40;
+pub const VCS_PRIORITY: u32 = 30;
+pub const BOOKMARK_PRIORITY: u32 = 20;
+pub const SEARCH_PRIORITY: u32 = 10;
+
+/// Number of columns the sign column takes up when it is shown, the glyph and a space
+pub const SIGN_COLUMN_WIDTH: usize = 2;
+
+/// Handed out by Editor::register_sign_provider to tell providers apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SignProviderId(pub usize);
+
+pub trait SignProvider {
+    fn name(&self) -> &str;
+
+    /// Used to pick which sign is drawn when several are on the same line, the highest wins
+    fn priority(&self) -> u32;
+
+    /// Signs worked out from the document for the lines in the range, as (line index, glyph)
+    /// pairs. Providers that only place their signs ahead of time can leave this out
+    fn signs(&self, _document: &Document, _lines: Range<usize>) -> Vec<(usize, char)> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlacedSign {
+    pub provider: SignProviderId,
+    pub glyph: char,
+}
+
+/// The signs placed in a document, keyed by the index of the line they are on
+///
+/// The document tells the table when lines are added or removed so that the signs stay on the
+/// same text
+#[derive(Debug, Default)]
+pub struct SignTable {
+    lines: BTreeMap<usize, Vec<PlacedSign>>,
+}
+
+impl SignTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn on_line(&self, line_ind: usize) -> &[PlacedSign] {
+        self.lines
+            .get(&line_ind)
+            .map_or(&[], |signs| signs.as_slice())
+    }
+
+    pub fn place(&mut self, line_ind: usize, sign: PlacedSign) {
+        //! Puts a sign on the line, replacing the sign the same provider had on it before
+
+        let signs = self.lines.entry(line_ind).or_default();
+
+        signs.retain(|s| s.provider != sign.provider);
+        signs.push(sign);
+    }
+
+    pub fn unplace(&mut self, line_ind: usize, provider: SignProviderId) {
+        if let Some(signs) = self.lines.get_mut(&line_ind) {
+            signs.retain(|s| s.provider != provider);
+
+            if signs.is_empty() {
+                self.lines.remove(&line_ind);
+            }
+        }
+    }
+
+    pub fn unplace_where(&mut self, mut remove: impl FnMut(&PlacedSign) -> bool) {
+        //! Removes every sign for which remove returns true
+
+        self.lines.retain(|_, signs| {
+            signs.retain(|s| !remove(s));
+            !signs.is_empty()
+        });
+    }
+
+    pub fn lines_of(&self, provider: SignProviderId, glyph: char) -> Vec<usize> {
+        //! The lines holding the given sign of the given provider
+
+        self.lines
+            .iter()
+            .filter(|(_, signs)| {
+                signs
+                    .iter()
+                    .any(|s| s.provider == provider && s.glyph == glyph)
+            })
+            .map(|(ind, _)| *ind)
+            .collect()
+    }
+
+    // ==================== FOLLOWING EDITS ====================
+
+    pub fn lines_inserted(&mut self, line_ind: usize, count: usize) {
+        //! count lines were inserted so that the first of them is at line_ind, the signs on
+        //! line_ind and after move down with their lines
+
+        let moved = self.lines.split_off(&line_ind);
+
+        self.lines
+            .extend(moved.into_iter().map(|(ind, signs)| (ind + count, signs)));
+    }
+
+    pub fn line_removed(&mut self, line_ind: usize) {
+        //! The line at line_ind was removed along with its signs, the signs after it move up
+
+        let mut moved = self.lines.split_off(&line_ind);
+
+        moved.remove(&line_ind);
+
+        self.lines
+            .extend(moved.into_iter().map(|(ind, signs)| (ind - 1, signs)));
+    }
+
+    pub fn lines_joined(&mut self, line_ind: usize) {
+        //! The line after line_ind was joined onto the end of it, its signs are kept on the joined
+        //! line unless that line already has a sign from the same provider
+
+        let mut moved = self.lines.split_off(&(line_ind + 1));
+
+        if let Some(signs) = moved.remove(&(line_ind + 1)) {
+            for sign in signs {
+                if !self
+                    .on_line(line_ind)
+                    .iter()
+                    .any(|s| s.provider == sign.provider)
+                {
+                    self.place(line_ind, sign);
+                }
+            }
+        }
+
+        self.lines
+            .extend(moved.into_iter().map(|(ind, signs)| (ind - 1, signs)));
+    }
+}
+
+pub fn offer(
+    shown: &mut BTreeMap<usize, (u32, char)>,
+    line_ind: usize,
+    priority: u32,
+    glyph: char,
+) {
+    //! Offers a sign for a line given the signs to draw so far as (priority, glyph), it takes the
+    //! place of the line's sign only if its priority is higher, so the first of equals is kept
+
+    let best = shown.entry(line_ind).or_insert((priority, glyph));
+
+    if priority > best.0 {
+        *best = (priority, glyph);
+    }
+}
+
+// ==================== BUILT IN PROVIDERS ====================
+
+/// Letters put on lines with m, the letter itself is the glyph
+pub struct Bookmarks;
+
+impl SignProvider for Bookmarks {
+    fn name(&self) -> &str {
+        "bookmarks"
+    }
+
+    fn priority(&self) -> u32 {
+        BOOKMARK_PRIORITY
+    }
+}
+
+/// Marks the lines in view that contain the last pattern searched for
+pub struct SearchHits {
+    pub pattern: Rc<RefCell<String>>,
+}
+
+impl SignProvider for SearchHits {
+    fn name(&self) -> &str {
+        "search"
+    }
+
+    fn priority(&self) -> u32 {
+        SEARCH_PRIORITY
+    }
+
+    fn signs(&self, document: &Document, lines: Range<usize>) -> Vec<(usize, char)> {
+        let pattern = self.pattern.borrow();
+
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+
+        let first = lines.start;
+
+        document
+            .lines_from(first)
+            .take(lines.len())
+            .enumerate()
+            .filter(|(_, line)| line.contains(pattern.as_str()))
+            .map(|(offset, _)| (first + offset, '>'))
+            .collect()
+    }
+}
+
+/// Marks the lines that differ between two buffers being compared with -d, see diff::line_changes
+/// for the glyphs
+///
+/// Comparing the buffers takes a pass over both of them, so while text is being typed the signs
+/// worked out last are kept and the buffers are only compared again once Insert mode is left
+pub struct DiffChanges {
+    buffers: (Rc<RefCell<Document>>, Rc<RefCell<Document>>),
+    /// Set by the editor while it is in Insert mode
+    inserting: Rc<Cell<bool>>,
+    /// The changes of each buffer the diff was worked out at, along with the glyphs of each
+    cache: RefCell<Option<DiffCache>>,
+}
+
+struct DiffCache {
+    changes: (usize, usize),
+    glyphs: (BTreeMap<usize, char>, BTreeMap<usize, char>),
+}
+
+impl DiffChanges {
+    pub fn new(
+        buffers: (Rc<RefCell<Document>>, Rc<RefCell<Document>>),
+        inserting: Rc<Cell<bool>>,
+    ) -> Self {
+        Self {
+            buffers,
+            inserting,
+            cache: RefCell::new(None),
+        }
+    }
+}
+
+impl SignProvider for DiffChanges {
+    fn name(&self) -> &str {
+        "diff"
+    }
+
+    fn priority(&self) -> u32 {
+        VCS_PRIORITY
+    }
+
+    fn signs(&self, document: &Document, lines: Range<usize>) -> Vec<(usize, char)> {
+        let (first, second) = &self.buffers;
+
+        let (other, is_first) = if std::ptr::eq(document, first.as_ptr()) {
+            (second, true)
+        } else if std::ptr::eq(document, second.as_ptr()) {
+            (first, false)
+        } else {
+            return Vec::new();
+        };
+
+        // Both buffers are the same one when it is compared with itself
+        let Ok(other) = other.try_borrow() else {
+            return Vec::new();
+        };
+
+        let (first, second) = if is_first {
+            (document, &*other)
+        } else {
+            (&*other, document)
+        };
+
+        let changes = (first.changes(), second.changes());
+
+        let mut cache = self.cache.borrow_mut();
+
+        // The whole of both buffers is compared, so that is only done again once either changes and
+        // typing has stopped
+        if cache
+            .as_ref()
+            .is_none_or(|cache| cache.changes != changes && !self.inserting.get())
+        {
+            let first = first.lines_from(0).collect::<Vec<_>>();
+            let second = second.lines_from(0).collect::<Vec<_>>();
+
+            *cache = Some(DiffCache {
+                changes,
+                glyphs: diff::line_changes(&first, &second),
+            });
+        }
+
+        let glyphs = cache.as_ref().unwrap();
+
+        let glyphs = if is_first {
+            &glyphs.glyphs.0
+        } else {
+            &glyphs.glyphs.1
+        };
+
+        glyphs
+            .range(lines)
+            .map(|(ind, glyph)| (*ind, *glyph))
+            .collect()
+    }
+}
+
+/// Marks the lines of a file that differ from the file as it was last committed to git, see
+/// diff::line_changes for the glyphs
+///
+/// The committed file is asked of git once per file, and files that are not in a repository or
+/// not tracked get no signs. Like DiffChanges, the signs worked out last are kept while text is
+/// being typed
+pub struct GitChanges {
+    /// Set by the editor while it is in Insert mode
+    inserting: Rc<Cell<bool>>,
+    files: RefCell<HashMap<String, GitFile>>,
+}
+
+struct GitFile {
+    /// The lines of the file at HEAD, None when git has no copy of it
+    committed: Option<Vec<String>>,
+    /// The changes of the buffer the glyphs were worked out at
+    changes: Option<usize>,
+    glyphs: BTreeMap<usize, char>,
+}
+
+impl GitChanges {
+    pub fn new(inserting: Rc<Cell<bool>>) -> Self {
+        Self {
+            inserting,
+            files: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+fn committed_lines(file_name: &str) -> Option<Vec<String>> {
+    //! The lines of the file as of the last commit, git is run from the directory of the file so
+    //! that the repository the file is in is the one asked
+
+    let path = Path::new(file_name);
+
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = path.file_name()?.to_str()?;
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("HEAD:./{name}"))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+
+    // A document always has a line, even when its file is empty
+    if text.is_empty() {
+        return Some(vec![String::new()]);
+    }
+
+    Some(text.lines().map(str::to_string).collect())
+}
+
+impl SignProvider for GitChanges {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn priority(&self) -> u32 {
+        VCS_PRIORITY
+    }
+
+    fn signs(&self, document: &Document, lines: Range<usize>) -> Vec<(usize, char)> {
+        // A file still being indexed would be compared against only the part of it read so far
+        if document.kind != BufferKind::File || document.index_progress().is_some() {
+            return Vec::new();
+        }
+
+        let mut files = self.files.borrow_mut();
+
+        let file = files
+            .entry(document.file_name.clone())
+            .or_insert_with(|| GitFile {
+                committed: committed_lines(&document.file_name),
+                changes: None,
+                glyphs: BTreeMap::new(),
+            });
+
+        let Some(committed) = &file.committed else {
+            return Vec::new();
+        };
+
+        if file
+            .changes
+            .is_none_or(|changes| changes != document.changes() && !self.inserting.get())
+        {
+            let committed = committed.iter().map(String::as_str).collect::<Vec<_>>();
+            let current = document.lines_from(0).collect::<Vec<_>>();
+            let current = current.iter().map(|line| line.as_ref()).collect::<Vec<_>>();
+
+            file.glyphs = diff::line_changes(&committed, &current).1;
+            file.changes = Some(document.changes());
+        }
+
+        file.glyphs
+            .range(lines)
+            .map(|(ind, glyph)| (*ind, *glyph))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOKMARK: SignProviderId = SignProviderId(0);
+    const SEARCH: SignProviderId = SignProviderId(1);
+
+    fn sign(provider: SignProviderId, glyph: char) -> PlacedSign {
+        PlacedSign { provider, glyph }
+    }
+
+    fn table(signs: &[(usize, SignProviderId, char)]) -> SignTable {
+        let mut table = SignTable::new();
+
+        for (line_ind, provider, glyph) in signs {
+            table.place(*line_ind, sign(*provider, *glyph));
+        }
+
+        table
+    }
+
+    fn placed(table: &SignTable) -> Vec<(usize, char)> {
+        //! Every sign in the table as (line index, glyph), in the order of the lines
+        table
+            .lines
+            .iter()
+            .flat_map(|(ind, signs)| signs.iter().map(|s| (*ind, s.glyph)))
+            .collect()
+    }
+
+    #[test]
+    fn a_provider_has_one_sign_per_line() {
+        let mut table = table(&[(3, BOOKMARK, 'a'), (3, SEARCH, '>')]);
+
+        table.place(3, sign(BOOKMARK, 'b'));
+
+        assert_eq!(table.on_line(3), [sign(SEARCH, '>'), sign(BOOKMARK, 'b')]);
+
+        table.unplace(3, SEARCH);
+        table.unplace(3, BOOKMARK);
+
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn signs_are_found_and_removed_by_what_they_are() {
+        let mut table = table(&[(1, BOOKMARK, 'a'), (4, BOOKMARK, 'a'), (4, SEARCH, 'a')]);
+
+        assert_eq!(table.lines_of(BOOKMARK, 'a'), [1, 4]);
+        assert_eq!(table.lines_of(SEARCH, 'b'), []);
+
+        table.unplace_where(|s| s.provider == BOOKMARK);
+
+        assert_eq!(placed(&table), [(4, 'a')]);
+        assert_eq!(table.on_line(1), []);
+    }
+
+    #[test]
+    fn signs_move_with_inserted_and_removed_lines() {
+        let mut table = table(&[(1, BOOKMARK, 'a'), (2, BOOKMARK, 'b'), (5, BOOKMARK, 'c')]);
+
+        table.lines_inserted(2, 3);
+        assert_eq!(placed(&table), [(1, 'a'), (5, 'b'), (8, 'c')]);
+
+        table.line_removed(5);
+        assert_eq!(placed(&table), [(1, 'a'), (7, 'c')]);
+
+        table.line_removed(0);
+        assert_eq!(placed(&table), [(0, 'a'), (6, 'c')]);
+    }
+
+    #[test]
+    fn joining_lines_keeps_the_signs_of_both_unless_a_provider_has_one_already() {
+        let mut table = table(&[
+            (1, BOOKMARK, 'a'),
+            (2, BOOKMARK, 'b'),
+            (2, SEARCH, '>'),
+            (4, BOOKMARK, 'c'),
+        ]);
+
+        table.lines_joined(1);
+
+        assert_eq!(table.on_line(1), [sign(BOOKMARK, 'a'), sign(SEARCH, '>')]);
+        assert_eq!(placed(&table), [(1, 'a'), (1, '>'), (3, 'c')]);
+    }
+
+    #[test]
+    fn the_sign_with_the_highest_priority_is_drawn() {
+        let mut shown = BTreeMap::new();
+
+        offer(&mut shown, 0, SEARCH_PRIORITY, '>');
+        offer(&mut shown, 0, BOOKMARK_PRIORITY, 'a');
+        offer(&mut shown, 0, VCS_PRIORITY, '+');
+        offer(&mut shown, 0, BOOKMARK_PRIORITY, 'b');
+
+        // Of equal priorities the first offered stays
+        offer(&mut shown, 1, BOOKMARK_PRIORITY, 'a');
+        offer(&mut shown, 1, BOOKMARK_PRIORITY, 'b');
+
+        assert_eq!(
+            shown.into_iter().collect::<Vec<_>>(),
+            [(0, (VCS_PRIORITY, '+')), (1, (BOOKMARK_PRIORITY, 'a'))]
+        );
+    }
+}
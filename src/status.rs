@@ -0,0 +1,330 @@
+use std::str::FromStr;
+
+// The status line is the row under the document display window. It is made of segments, each
+// showing one thing about the editor or the current buffer, some lined up against the left edge
+// and the rest against the right edge.
+//
+// When the terminal is too narrow for all of them, the least important segments are dropped first,
+// then the file name is cut short from the front, and only then are the important segments dropped.
+
+/// Something the status line can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Mode,
+    FileName,
+    /// [+] while the buffer has changes that have not been written
+    Dirty,
+    /// The cursor's line and column, counted from 1
+    Position,
+    /// How far through the file the cursor's line is
+    Percent,
+    FileType,
+    Encoding,
+    /// unix or dos, depending on how the lines of the file end
+    LineEnding,
+    /// The keys typed so far of a command that takes more than one key
+    PendingKeys,
+}
+
+impl Segment {
+    pub fn from_name(name: &str) -> Option<Self> {
+        //! The name the segment goes by in the statusline option
+
+        Some(match name {
+            "mode" => Segment::Mode,
+            "file" | "filename" => Segment::FileName,
+            "dirty" | "modified" => Segment::Dirty,
+            "position" | "pos" => Segment::Position,
+            "percent" => Segment::Percent,
+            "filetype" | "ft" => Segment::FileType,
+            "encoding" | "enc" => Segment::Encoding,
+            "endings" | "fileformat" | "ff" => Segment::LineEnding,
+            "pending" | "keys" => Segment::PendingKeys,
+            _ => return None,
+        })
+    }
+
+    fn priority(&self) -> u8 {
+        //! How readily the segment is dropped when the status line does not fit, the higher the
+        //! sooner
+        match self {
+            Segment::Mode => 0,
+            Segment::FileName | Segment::Dirty => 1,
+            Segment::Position | Segment::PendingKeys => 2,
+            Segment::Percent => 3,
+            Segment::FileType => 4,
+            Segment::LineEnding => 5,
+            Segment::Encoding => 6,
+        }
+    }
+}
+
+// Segments of this priority or higher are dropped before the file name is cut short
+const SHORTEN_BELOW: u8 = 3;
+
+/// Which segments the status line shows and on which side, set with the statusline option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusLayout {
+    pub left: Vec<Segment>,
+    pub right: Vec<Segment>,
+}
+
+impl Default for StatusLayout {
+    fn default() -> Self {
+        Self {
+            left: vec![Segment::Mode, Segment::FileName, Segment::Dirty],
+            right: vec![
+                Segment::PendingKeys,
+                Segment::FileType,
+                Segment::Encoding,
+                Segment::LineEnding,
+                Segment::Position,
+                Segment::Percent,
+            ],
+        }
+    }
+}
+
+impl FromStr for StatusLayout {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        //! Segment names separated by commas, the ones before a | go on the left and the ones
+        //! after it on the right, e.g. "mode,file,dirty|position,percent"
+
+        let (left, right) = spec.split_once('|').unwrap_or((spec, ""));
+
+        let parse_side = |side: &str| {
+            side.split(',')
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    Segment::from_name(name)
+                        .ok_or_else(|| format!("Unknown status segment: {name}"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Self {
+            left: parse_side(left)?,
+            right: parse_side(right)?,
+        })
+    }
+}
+
+impl StatusLayout {
+    pub fn render(&self, width: usize, text_of: impl Fn(Segment) -> String) -> String {
+        //! Lays the segments out on a row width columns wide, text_of gives what each segment
+        //! shows and the segments it gives nothing for are left out
+
+        let shown = |segments: &[Segment]| {
+            segments
+                .iter()
+                .map(|segment| (*segment, text_of(*segment)))
+                .filter(|(_, text)| !text.is_empty())
+                .collect::<Vec<_>>()
+        };
+
+        let mut left = shown(&self.left);
+        let mut right = shown(&self.right);
+
+        while row_width(&left, &right) > width {
+            let Some(least) = least_important(&left, &right) else {
+                break;
+            };
+
+            if least.priority() < SHORTEN_BELOW && shorten_file_name(&mut left, &mut right, width) {
+                continue;
+            }
+
+            // Of the segments that matter the least, the one closest to the middle goes first
+            if let Some(ind) = left.iter().rposition(|(segment, _)| *segment == least) {
+                left.remove(ind);
+            } else if let Some(ind) = right.iter().position(|(segment, _)| *segment == least) {
+                right.remove(ind);
+            }
+        }
+
+        let join = |side: &[(Segment, String)]| {
+            side.iter()
+                .map(|(_, text)| text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let left = join(&left);
+        let right = join(&right);
+
+        let gap = width
+            .saturating_sub(left.chars().count() + right.chars().count() + 2)
+            .max(1);
+
+        format!(" {left}{}{right} ", " ".repeat(gap))
+            .chars()
+            .take(width)
+            .collect()
+    }
+}
+
+fn side_width(side: &[(Segment, String)]) -> usize {
+    //! The columns taken up by the segments of one side and the spaces between them
+    side.iter()
+        .map(|(_, text)| text.chars().count() + 1)
+        .sum::<usize>()
+        - side.len().min(1)
+}
+
+fn row_width(left: &[(Segment, String)], right: &[(Segment, String)]) -> usize {
+    //! The narrowest the status line can be drawn with these segments, a space at either edge and
+    //! at least one between the two sides
+    let gap = usize::from(!left.is_empty() && !right.is_empty());
+
+    side_width(left) + side_width(right) + gap + 2
+}
+
+fn least_important(left: &[(Segment, String)], right: &[(Segment, String)]) -> Option<Segment> {
+    left.iter()
+        .chain(right)
+        .map(|(segment, _)| *segment)
+        .max_by_key(|segment| segment.priority())
+}
+
+fn shorten_file_name(
+    left: &mut [(Segment, String)],
+    right: &mut [(Segment, String)],
+    width: usize,
+) -> bool {
+    //! Cuts the front off the file name so that the status line fits, marking the cut with a <,
+    //! returns false once it cannot be cut any shorter
+
+    let excess = row_width(left, right).saturating_sub(width);
+
+    let Some((_, name)) = left
+        .iter_mut()
+        .chain(right.iter_mut())
+        .find(|(segment, _)| *segment == Segment::FileName)
+    else {
+        return false;
+    };
+
+    let len = name.chars().count();
+
+    // Keep the < and at least the last character of the name
+    if len <= 2 {
+        return false;
+    }
+
+    let keep = len.saturating_sub(excess + 1).max(1);
+
+    *name = format!("<{}", name.chars().skip(len - keep).collect::<String>());
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_of(segment: Segment) -> String {
+        //! What each segment shows for a changed buffer named src/main.rs
+        match segment {
+            Segment::Mode => "NOR",
+            Segment::FileName => "src/main.rs",
+            Segment::Dirty => "[+]",
+            Segment::Position => "12:5",
+            Segment::Percent => "40%",
+            Segment::FileType => "rust",
+            Segment::Encoding => "utf-8",
+            Segment::LineEnding => "unix",
+            Segment::PendingKeys => "",
+        }
+        .to_string()
+    }
+
+    fn render(layout: &str, width: usize) -> String {
+        layout
+            .parse::<StatusLayout>()
+            .unwrap()
+            .render(width, text_of)
+    }
+
+    #[test]
+    fn segments_before_the_bar_go_left_and_the_rest_right() {
+        assert_eq!(
+            "mode,file|pos".parse(),
+            Ok(StatusLayout {
+                left: vec![Segment::Mode, Segment::FileName],
+                right: vec![Segment::Position],
+            })
+        );
+        assert_eq!(
+            "mode".parse(),
+            Ok(StatusLayout {
+                left: vec![Segment::Mode],
+                right: vec![],
+            })
+        );
+        assert_eq!(
+            "|ft,enc".parse(),
+            Ok(StatusLayout {
+                left: vec![],
+                right: vec![Segment::FileType, Segment::Encoding],
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_segments_are_an_error() {
+        assert_eq!(
+            "mode,colour".parse::<StatusLayout>(),
+            Err("Unknown status segment: colour".to_string())
+        );
+    }
+
+    #[test]
+    fn the_sides_are_lined_up_against_the_edges() {
+        assert_eq!(
+            render("mode,file,dirty|pos,percent", 40),
+            " NOR src/main.rs [+]           12:5 40% "
+        );
+        assert_eq!(render("mode|", 10), " NOR      ");
+        assert_eq!(render("|pos", 10), "     12:5 ");
+    }
+
+    #[test]
+    fn segments_with_nothing_to_show_are_left_out() {
+        assert_eq!(render("mode,pending,dirty|", 12), " NOR [+]    ");
+    }
+
+    const FULL: &str = "mode,file,dirty|ft,enc,ff,pos,percent";
+
+    #[test]
+    fn the_least_important_segments_are_dropped_first() {
+        assert_eq!(
+            render(FULL, 46),
+            " NOR src/main.rs [+] rust utf-8 unix 12:5 40% "
+        );
+        assert_eq!(
+            render(FULL, 44),
+            " NOR src/main.rs [+]     rust unix 12:5 40% "
+        );
+        assert_eq!(render(FULL, 35), " NOR src/main.rs [+] rust 12:5 40% ");
+        assert_eq!(render(FULL, 30), " NOR src/main.rs [+] 12:5 40% ");
+    }
+
+    #[test]
+    fn the_file_name_is_cut_short_before_important_segments_are_dropped() {
+        assert_eq!(render(FULL, 25), " NOR <c/main.rs [+] 12:5 ");
+        assert_eq!(render(FULL, 18), " NOR <rs [+] 12:5 ");
+
+        // Once only the last character of the name is left, the position goes
+        assert_eq!(render(FULL, 15), " NOR <s [+]    ");
+        assert_eq!(render(FULL, 10), " NOR <s   ");
+    }
+
+    #[test]
+    fn the_row_is_always_as_wide_as_the_window() {
+        for width in 0..60 {
+            assert_eq!(render(FULL, width).chars().count(), width, "{width}");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+/// The interface a Document uses to reach its text, so that the way lines are kept in memory can
+/// be changed without touching the editing code
+///
+/// Lines never contain new line characters, those are implied between each line
+///
+/// There is always at least one line, empty text is held as a single empty line and removing the
+/// only line leaves an empty one in its place
+pub trait TextStorage: Debug {
+    /// Number of lines held, never 0
+    fn len_lines(&self) -> usize;
+
+    /// Content of the line at ind
+    fn line(&self, ind: usize) -> Cow<'_, str>;
+
+    /// Length in bytes of the line at ind
+    fn line_len(&self, ind: usize) -> usize {
+        self.line(ind).len()
+    }
+
+    /// Replaces the content of the line at ind
+    fn set_line(&mut self, ind: usize, content: String);
+
+    /// Inserts a new line so that it ends up at ind, ind may be equal to len_lines to append
+    fn insert_line(&mut self, ind: usize, content: String);
+
+    /// Removes the line at ind and returns its content, the only line is emptied instead
+    fn remove_line(&mut self, ind: usize) -> String;
+
+    /// Iterates over the lines in order starting at the line at ind
+    fn lines_from(&self, ind: usize) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_>;
+
+    /// Iterates over the lengths in bytes of the lines starting at the line at ind
+    fn line_lens_from(&self, ind: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new(self.lines_from(ind).map(|line| line.len()))
+    }
+
+    /// Whether the lines can be changed at all, the editing methods must not be called if not
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Takes in lines that were loaded since the last call and returns how many there were, for
+    /// storage that loads its lines in the background
+    fn poll_lines(&mut self) -> usize {
+        0
+    }
+
+    /// How far through loading its lines the storage is as a percentage, None once every line is
+    /// available
+    fn index_progress(&self) -> Option<usize> {
+        None
+    }
+}
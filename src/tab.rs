@@ -0,0 +1,233 @@
+use crate::window::Windows;
+
+// Tab pages each hold their own layout of windows, only the windows of the current tab page are
+// shown. While there is more than one tab page the title line becomes a tab bar with a label for
+// every tab page.
+
+/// Every tab page and which one is current, there is always at least one
+#[derive(Debug)]
+pub struct TabPages {
+    pages: Vec<Windows>,
+    current: usize,
+}
+
+impl TabPages {
+    pub fn new(windows: Windows) -> Self {
+        Self {
+            pages: vec![windows],
+            current: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> &Windows {
+        &self.pages[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Windows {
+        &mut self.pages[self.current]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Windows> {
+        self.pages.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Windows> {
+        self.pages.iter_mut()
+    }
+
+    pub fn open(&mut self, windows: Windows) {
+        //! Adds a tab page after the current one and makes it current
+
+        self.current += 1;
+
+        self.pages.insert(self.current, windows);
+    }
+
+    pub fn close(&mut self) -> Result<(), String> {
+        //! Closes the current tab page, the one before it becomes current
+
+        if self.pages.len() == 1 {
+            return Err("Cannot close last tab page".to_string());
+        }
+
+        self.pages.remove(self.current);
+
+        self.current = self.current.saturating_sub(1);
+
+        Ok(())
+    }
+
+    pub fn only(&mut self) {
+        //! Closes every tab page except the current one
+
+        let current = self.pages.swap_remove(self.current);
+
+        self.pages = vec![current];
+        self.current = 0;
+    }
+
+    pub fn cycle(&mut self, forward: bool) {
+        //! Makes the next tab page current, or the previous one, going round at the last one
+
+        let len = self.pages.len();
+
+        self.current = if forward {
+            (self.current + 1) % len
+        } else {
+            (self.current + len - 1) % len
+        };
+    }
+
+    pub fn go_to(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.pages.len() {
+            return Err(format!("No tab page {}", index + 1));
+        }
+
+        self.current = index;
+
+        Ok(())
+    }
+
+    pub fn move_current(&mut self, index: usize) {
+        //! Moves the current tab page to index among the others, past the last one if index is out
+        //! of range
+
+        let index = index.min(self.pages.len() - 1);
+
+        let page = self.pages.remove(self.current);
+
+        self.pages.insert(index, page);
+
+        self.current = index;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use crate::window::{Direction, Rect};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn page(num_windows: usize) -> Windows {
+        //! A tab page told apart from the others by its number of windows
+
+        let mut windows = Windows::new(Rc::new(RefCell::new(Document::new_unnamed(20))));
+
+        windows.arrange(Rect {
+            top: 2,
+            left: 1,
+            height: 20,
+            width: 80,
+        });
+
+        for split in 1..num_windows {
+            let direction = if split % 2 == 1 {
+                Direction::Stacked
+            } else {
+                Direction::SideBySide
+            };
+
+            windows.split(direction).unwrap();
+        }
+
+        windows
+    }
+
+    fn tabs(num_pages: usize) -> TabPages {
+        //! Tab pages with 1, 2, ... windows, the last one current
+
+        let mut tabs = TabPages::new(page(1));
+
+        for num_windows in 2..=num_pages {
+            tabs.open(page(num_windows));
+        }
+
+        tabs
+    }
+
+    fn pages(tabs: &TabPages) -> Vec<usize> {
+        tabs.iter().map(Windows::len).collect()
+    }
+
+    #[test]
+    fn tab_pages_open_after_the_current_one() {
+        let mut tabs = tabs(3);
+
+        tabs.go_to(0).unwrap();
+        tabs.open(page(4));
+
+        assert_eq!(pages(&tabs), [1, 4, 2, 3]);
+        assert_eq!(tabs.current_index(), 1);
+        assert_eq!(tabs.current().len(), 4);
+    }
+
+    #[test]
+    fn closing_a_tab_page_makes_the_one_before_it_current() {
+        let mut tabs = tabs(3);
+
+        tabs.go_to(1).unwrap();
+        tabs.close().unwrap();
+
+        assert_eq!(pages(&tabs), [1, 3]);
+        assert_eq!(tabs.current_index(), 0);
+
+        tabs.close().unwrap();
+
+        assert_eq!(pages(&tabs), [3]);
+        assert!(tabs.close().is_err());
+    }
+
+    #[test]
+    fn only_keeps_the_current_tab_page() {
+        let mut tabs = tabs(3);
+
+        tabs.go_to(1).unwrap();
+        tabs.only();
+
+        assert_eq!(pages(&tabs), [2]);
+        assert_eq!(tabs.current_index(), 0);
+    }
+
+    #[test]
+    fn switching_tab_pages_goes_round() {
+        let mut tabs = tabs(3);
+
+        tabs.cycle(true);
+        assert_eq!(tabs.current_index(), 0);
+
+        tabs.cycle(false);
+        assert_eq!(tabs.current_index(), 2);
+
+        assert_eq!(tabs.go_to(3), Err("No tab page 4".to_string()));
+        assert_eq!(tabs.current_index(), 2);
+    }
+
+    #[test]
+    fn the_current_tab_page_can_be_moved() {
+        let mut tabs = tabs(3);
+
+        tabs.move_current(0);
+
+        assert_eq!(pages(&tabs), [3, 1, 2]);
+        assert_eq!(tabs.current_index(), 0);
+
+        tabs.move_current(10);
+
+        assert_eq!(pages(&tabs), [1, 2, 3]);
+        assert_eq!(tabs.current_index(), 2);
+    }
+}
@@ -1,5 +1,17 @@
 #[allow(dead_code)]
-use std::ffi::{c_char, c_uint};
+use std::collections::VecDeque;
+use std::ffi::{c_char, c_int, c_uint};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long the terminal gets to answer a query before it is taken not to understand it
+const QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+// Keys typed while waiting for the terminal to answer a query, handed out by get_char before any
+// that are read after
+static PENDING: Mutex<VecDeque<char>> = Mutex::new(VecDeque::new());
 
 pub struct Wh {
     pub width: usize,
@@ -24,6 +36,7 @@ extern "C" {
     fn c_kbhit() -> c_uint;
     fn get_term_size() -> WidthHeight;
     fn get_ch() -> c_char;
+    fn use_tty() -> c_int;
 }
 
 #[cfg(target_os = "linux")]
@@ -47,12 +60,48 @@ pub fn set_cooked() {
 }
 
 pub fn get_char() -> char {
+    if let Some(c) = PENDING.lock().unwrap().pop_front() {
+        return c;
+    }
+
+    read_char()
+}
+
+fn read_char() -> char {
     unsafe { get_ch() as u8 as char }
 }
 
+pub fn use_controlling_terminal() -> io::Result<()> {
+    //! Reads keys from and sets up the terminal itself rather than stdin, so that stdin can be read
+    //! for what was piped into the editor
+    //!
+    //! Has to be called before the terminal is put into raw mode
+
+    if unsafe { use_tty() } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
 pub fn kbhit() -> bool {
     // If the result is 0, it should be false, so return the inverse
-    unsafe { c_kbhit() != 0 }
+    !PENDING.lock().unwrap().is_empty() || unsafe { c_kbhit() != 0 }
+}
+
+fn wait_for_input(deadline: Instant) -> bool {
+    //! Waits until there is something to read, false if nothing came before the deadline, which is
+    //! also the case once the input has been closed
+
+    while unsafe { c_kbhit() } == 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    true
 }
 
 pub fn term_size() -> Wh {
@@ -80,3 +129,109 @@ pub fn clear_line() -> &'static str {
 pub fn move_cursor_to(row: usize, column: usize) -> String {
     format!("\u{001b}[{};{}H", row, column)
 }
+
+pub fn hide_cursor() -> &'static str {
+    "\u{001b}[?25l"
+}
+
+pub fn show_cursor() -> &'static str {
+    "\u{001b}[?25h"
+}
+
+pub fn begin_synchronized_update() -> &'static str {
+    //! The terminal holds off showing what is drawn until end_synchronized_update, so a frame is
+    //! never seen half drawn
+    "\u{001b}[?2026h"
+}
+
+pub fn end_synchronized_update() -> &'static str {
+    "\u{001b}[?2026l"
+}
+
+pub fn cursor_shape_block() -> &'static str {
+    "\u{001b}[2 q"
+}
+
+pub fn cursor_shape_bar() -> &'static str {
+    "\u{001b}[6 q"
+}
+
+pub fn cursor_shape_default() -> &'static str {
+    "\u{001b}[0 q"
+}
+
+pub fn supports_synchronized_output() -> bool {
+    //! Asks the terminal whether it knows about synchronized updates, this has to be done in raw
+    //! mode before anything else starts reading input
+    //!
+    //! The primary device attributes are asked for right after, every terminal answers those so
+    //! once they arrive the whole answer has been read. Keys typed in the meantime are kept to be
+    //! read as input afterwards
+
+    let mut stdout = io::stdout();
+
+    if stdout
+        .write_all(b"\x1b[?2026$p\x1b[c")
+        .and_then(|_| stdout.flush())
+        .is_err()
+    {
+        return false;
+    }
+
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+
+    let mut supported = false;
+    let mut typed = Vec::new();
+    // The answer being read, from the ESC [ ? that starts it
+    let mut answer = String::new();
+
+    while wait_for_input(deadline) {
+        let c = read_char();
+
+        if answer.is_empty() {
+            if c == '\u{001b}' {
+                answer.push(c);
+            } else {
+                typed.push(c);
+            }
+
+            continue;
+        }
+
+        answer.push(c);
+
+        if !"\u{001b}[?".starts_with(answer.as_str()) && !answer.starts_with("\u{001b}[?") {
+            // Not an answer after all, though the last character may begin one itself
+            answer.pop();
+            typed.extend(answer.drain(..));
+
+            if c == '\u{001b}' {
+                answer.push(c);
+            } else {
+                typed.push(c);
+            }
+
+            continue;
+        }
+
+        // Any character from @ to ~ ends the answer
+        if answer.len() > 3 && ('@'..='~').contains(&c) {
+            // 1 and 2 report the mode as set or reset, either way the terminal knows it
+            supported |= answer == "\u{001b}[?2026;1$y" || answer == "\u{001b}[?2026;2$y";
+
+            let attributes = c == 'c';
+
+            answer.clear();
+
+            if attributes {
+                break;
+            }
+        }
+    }
+
+    typed.extend(answer.chars());
+
+    PENDING.lock().unwrap().extend(typed);
+
+    supported
+}
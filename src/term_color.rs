@@ -1,12 +1,12 @@
 use std::{num::ParseIntError, str::FromStr};
 
-struct RGB {
+struct Rgb {
     r: u8,
     g: u8,
     b: u8,
 }
 
-impl FromStr for RGB {
+impl FromStr for Rgb {
     type Err = ParseIntError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let color_values: Vec<&str> = s.split(';').collect();
@@ -20,11 +20,18 @@ impl FromStr for RGB {
 }
 
 pub struct Theme {
-    body_fonts: RGB,
-    font_accents: RGB,
-    editor_background: RGB,
-    mode_line: RGB,
-    title_line: RGB,
+    body_fonts: Rgb,
+    font_accents: Rgb,
+    editor_background: Rgb,
+    mode_line: Rgb,
+    title_line: Rgb,
+    gutter_fonts: Rgb,
+    gutter_current_font: Rgb,
+    gutter_background: Rgb,
+    sign_fonts: Rgb,
+    info_fonts: Rgb,
+    warning_fonts: Rgb,
+    error_fonts: Rgb,
 }
 
 impl Theme {
@@ -83,6 +90,78 @@ impl Theme {
         )
     }
 
+    pub fn gutter_text_color(&self) -> String {
+        format!(
+            "\u{001b}[38;2;{};{};{}m\u{001b}[48;2;{};{};{}m",
+            self.gutter_fonts.r,
+            self.gutter_fonts.g,
+            self.gutter_fonts.b,
+            self.gutter_background.r,
+            self.gutter_background.g,
+            self.gutter_background.b,
+        )
+    }
+
+    pub fn gutter_current_text_color(&self) -> String {
+        format!(
+            "\u{001b}[38;2;{};{};{}m\u{001b}[48;2;{};{};{}m",
+            self.gutter_current_font.r,
+            self.gutter_current_font.g,
+            self.gutter_current_font.b,
+            self.gutter_background.r,
+            self.gutter_background.g,
+            self.gutter_background.b,
+        )
+    }
+
+    pub fn sign_text_color(&self) -> String {
+        format!(
+            "\u{001b}[38;2;{};{};{}m\u{001b}[48;2;{};{};{}m",
+            self.sign_fonts.r,
+            self.sign_fonts.g,
+            self.sign_fonts.b,
+            self.gutter_background.r,
+            self.gutter_background.g,
+            self.gutter_background.b,
+        )
+    }
+
+    pub fn info_text_color(&self) -> String {
+        format!(
+            "\u{001b}[38;2;{};{};{}m\u{001b}[48;2;{};{};{}m",
+            self.info_fonts.r,
+            self.info_fonts.g,
+            self.info_fonts.b,
+            self.editor_background.r,
+            self.editor_background.g,
+            self.editor_background.b,
+        )
+    }
+
+    pub fn warning_text_color(&self) -> String {
+        format!(
+            "\u{001b}[38;2;{};{};{}m\u{001b}[48;2;{};{};{}m",
+            self.warning_fonts.r,
+            self.warning_fonts.g,
+            self.warning_fonts.b,
+            self.editor_background.r,
+            self.editor_background.g,
+            self.editor_background.b,
+        )
+    }
+
+    pub fn error_text_color(&self) -> String {
+        format!(
+            "\u{001b}[38;2;{};{};{}m\u{001b}[48;2;{};{};{}m",
+            self.error_fonts.r,
+            self.error_fonts.g,
+            self.error_fonts.b,
+            self.editor_background.r,
+            self.editor_background.g,
+            self.editor_background.b,
+        )
+    }
+
     pub fn mode_line_color(&self) -> String {
         format!(
             "\u{001b}[48;2;{};{};{}m",
@@ -99,11 +178,24 @@ impl Theme {
 }
 
 pub struct ThemeBuilder {
-    body_fonts: Option<RGB>,
-    font_accents: Option<RGB>,
-    editor_background: Option<RGB>,
-    mode_line: Option<RGB>,
-    title_line: Option<RGB>,
+    body_fonts: Option<Rgb>,
+    font_accents: Option<Rgb>,
+    editor_background: Option<Rgb>,
+    mode_line: Option<Rgb>,
+    title_line: Option<Rgb>,
+    gutter_fonts: Option<Rgb>,
+    gutter_current_font: Option<Rgb>,
+    gutter_background: Option<Rgb>,
+    sign_fonts: Option<Rgb>,
+    info_fonts: Option<Rgb>,
+    warning_fonts: Option<Rgb>,
+    error_fonts: Option<Rgb>,
+}
+
+impl Default for ThemeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ThemeBuilder {
@@ -114,31 +206,73 @@ impl ThemeBuilder {
             editor_background: None,
             mode_line: None,
             title_line: None,
+            gutter_fonts: None,
+            gutter_current_font: None,
+            gutter_background: None,
+            sign_fonts: None,
+            info_fonts: None,
+            warning_fonts: None,
+            error_fonts: None,
         }
     }
 
     pub fn font_body(mut self, color: impl AsRef<str>) -> Self {
-        self.body_fonts = Some(RGB::from_str(color.as_ref()).unwrap());
+        self.body_fonts = Some(Rgb::from_str(color.as_ref()).unwrap());
         self
     }
 
     pub fn font_accents(mut self, color: impl AsRef<str>) -> Self {
-        self.font_accents = Some(RGB::from_str(color.as_ref()).unwrap());
+        self.font_accents = Some(Rgb::from_str(color.as_ref()).unwrap());
         self
     }
 
     pub fn editor_background(mut self, color: impl AsRef<str>) -> Self {
-        self.editor_background = Some(RGB::from_str(color.as_ref()).unwrap());
+        self.editor_background = Some(Rgb::from_str(color.as_ref()).unwrap());
         self
     }
 
     pub fn mode_line(mut self, color: impl AsRef<str>) -> Self {
-        self.mode_line = Some(RGB::from_str(color.as_ref()).unwrap());
+        self.mode_line = Some(Rgb::from_str(color.as_ref()).unwrap());
         self
     }
 
     pub fn title_line(mut self, color: impl AsRef<str>) -> Self {
-        self.title_line = Some(RGB::from_str(color.as_ref()).unwrap());
+        self.title_line = Some(Rgb::from_str(color.as_ref()).unwrap());
+        self
+    }
+
+    pub fn gutter_font(mut self, color: impl AsRef<str>) -> Self {
+        self.gutter_fonts = Some(Rgb::from_str(color.as_ref()).unwrap());
+        self
+    }
+
+    pub fn gutter_current_font(mut self, color: impl AsRef<str>) -> Self {
+        self.gutter_current_font = Some(Rgb::from_str(color.as_ref()).unwrap());
+        self
+    }
+
+    pub fn gutter_background(mut self, color: impl AsRef<str>) -> Self {
+        self.gutter_background = Some(Rgb::from_str(color.as_ref()).unwrap());
+        self
+    }
+
+    pub fn sign_font(mut self, color: impl AsRef<str>) -> Self {
+        self.sign_fonts = Some(Rgb::from_str(color.as_ref()).unwrap());
+        self
+    }
+
+    pub fn info_font(mut self, color: impl AsRef<str>) -> Self {
+        self.info_fonts = Some(Rgb::from_str(color.as_ref()).unwrap());
+        self
+    }
+
+    pub fn warning_font(mut self, color: impl AsRef<str>) -> Self {
+        self.warning_fonts = Some(Rgb::from_str(color.as_ref()).unwrap());
+        self
+    }
+
+    pub fn error_font(mut self, color: impl AsRef<str>) -> Self {
+        self.error_fonts = Some(Rgb::from_str(color.as_ref()).unwrap());
         self
     }
 
@@ -150,23 +284,51 @@ impl ThemeBuilder {
         Theme {
             body_fonts: match self.body_fonts {
                 Some(color) => color,
-                None => RGB::from_str(default_font).unwrap(),
+                None => Rgb::from_str(default_font).unwrap(),
             },
             font_accents: match self.font_accents {
                 Some(color) => color,
-                None => RGB::from_str(default_font).unwrap(),
+                None => Rgb::from_str(default_font).unwrap(),
             },
             editor_background: match self.editor_background {
                 Some(color) => color,
-                None => RGB::from_str(default_background).unwrap(),
+                None => Rgb::from_str(default_background).unwrap(),
             },
             mode_line: match self.mode_line {
                 Some(color) => color,
-                None => RGB::from_str(default_mode).unwrap(),
+                None => Rgb::from_str(default_mode).unwrap(),
             },
             title_line: match self.title_line {
                 Some(color) => color,
-                None => RGB::from_str(default_background).unwrap(),
+                None => Rgb::from_str(default_background).unwrap(),
+            },
+            gutter_fonts: match self.gutter_fonts {
+                Some(color) => color,
+                None => Rgb::from_str(default_font).unwrap(),
+            },
+            gutter_current_font: match self.gutter_current_font {
+                Some(color) => color,
+                None => Rgb::from_str(default_font).unwrap(),
+            },
+            gutter_background: match self.gutter_background {
+                Some(color) => color,
+                None => Rgb::from_str(default_background).unwrap(),
+            },
+            sign_fonts: match self.sign_fonts {
+                Some(color) => color,
+                None => Rgb::from_str(default_font).unwrap(),
+            },
+            info_fonts: match self.info_fonts {
+                Some(color) => color,
+                None => Rgb::from_str(default_font).unwrap(),
+            },
+            warning_fonts: match self.warning_fonts {
+                Some(color) => color,
+                None => Rgb::from_str(default_font).unwrap(),
+            },
+            error_fonts: match self.error_fonts {
+                Some(color) => color,
+                None => Rgb::from_str(default_font).unwrap(),
             },
         }
     }
@@ -12,11 +12,34 @@ struct wh {
 #include <termios.h>
 #include <sys/ioctl.h>
 #include <unistd.h>
+#include <fcntl.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+
+// The terminal keys are read from and that is put into raw mode, stdin unless something was piped in
+static int term_fd = STDIN_FILENO;
+
+// Read keys from the controlling terminal instead of stdin, for when stdin is a pipe.
+// Returns 0 on success and -1 if the terminal could not be opened
+int use_tty() {
+  int fd = open("/dev/tty", O_RDWR);
+
+  if (fd < 0) {
+    return -1;
+  }
+
+  term_fd = fd;
+
+  return 0;
+}
 
 struct wh get_term_size() {
   struct winsize w;
 
-  ioctl(STDOUT_FILENO, TIOCGWINSZ, &w);
+  // stdout may be redirected while the terminal is still there to draw on
+  if (ioctl(term_fd, TIOCGWINSZ, &w) < 0) {
+    ioctl(STDOUT_FILENO, TIOCGWINSZ, &w);
+  }
 
   struct wh res = { .width = w.ws_col, .height = w.ws_row };
 
@@ -29,7 +52,7 @@ void set_raw_term() {
   struct termios cooked;
 
   // Get the current settings for the terminal
-  tcgetattr(0, &cooked);
+  tcgetattr(term_fd, &cooked);
 
   struct termios raw = cooked;
 
@@ -38,29 +61,29 @@ void set_raw_term() {
   raw.c_lflag &= ~ICANON; // Turn off canonical mode (i.e. enter raw mode terminal)
 
   // Set the new setting for the terminal now
-  tcsetattr(0, TCSANOW, &raw);
+  tcsetattr(term_fd, TCSANOW, &raw);
 }
 
 // Set the terminal into cooked mode (i.e. return the terminal to its original state), simply do the reverse of the above
 void set_cooked_term() {
   struct termios raw;
 
-  tcgetattr(0, &raw);
+  tcgetattr(term_fd, &raw);
 
   struct termios cooked = raw;
 
   cooked.c_lflag |= ECHO;
   cooked.c_lflag |= ICANON;
 
-  tcsetattr(0, TCSANOW, &cooked);;
+  tcsetattr(term_fd, TCSANOW, &cooked);
 
 }
 
 // Get a character from the user, easy enough to just implement in C
 char get_ch() {
-  char c;
+  char c = 0;
 
-  scanf("%c", &c);
+  read(term_fd, &c, 1);
 
   return c;
 }
@@ -69,11 +92,48 @@ char get_ch() {
 unsigned int c_kbhit() {
   int waiting;
 
-  ioctl(STDIN_FILENO, FIONREAD, &waiting);
+  ioctl(term_fd, FIONREAD, &waiting);
 
   return waiting > 0;
 }
 
+// Map the whole file at path into memory as read only, the length of the file is written to len.
+// Returns NULL if the file could not be opened or mapped, an empty file is not mapped either
+const char* map_file(const char* path, unsigned long long* len) {
+  int fd = open(path, O_RDONLY);
+
+  if (fd < 0) {
+    return NULL;
+  }
+
+  struct stat st;
+
+  if (fstat(fd, &st) < 0 || st.st_size == 0) {
+    close(fd);
+    return NULL;
+  }
+
+  void* data = mmap(NULL, st.st_size, PROT_READ, MAP_PRIVATE, fd, 0);
+
+  // The mapping keeps its own reference to the file, the descriptor is no longer needed
+  close(fd);
+
+  if (data == MAP_FAILED) {
+    return NULL;
+  }
+
+  // The file is going to be read from front to back while it is indexed
+  madvise(data, st.st_size, MADV_SEQUENTIAL);
+
+  *len = st.st_size;
+
+  return (const char*) data;
+}
+
+void unmap_file(const char* data, unsigned long long len) {
+  munmap((void*) data, len);
+}
+
 #endif
 
 #ifdef _WIN32
@@ -97,6 +157,11 @@ struct wh get_term_size() {
     return widthHeight;
 }
 
+// The console is read directly whatever stdin is, so there is nothing to reopen
+int use_tty() {
+  return 0;
+}
+
 char get_ch() {
   return (char) _getch();
 }
@@ -105,4 +170,44 @@ unsigned int c_kbhit() {
   return _kbhit();
 }
 
+const char* map_file(const char* path, unsigned long long* len) {
+  HANDLE file = CreateFileA(path, GENERIC_READ, FILE_SHARE_READ | FILE_SHARE_WRITE, NULL, OPEN_EXISTING, FILE_ATTRIBUTE_NORMAL, NULL);
+
+  if (file == INVALID_HANDLE_VALUE) {
+    return NULL;
+  }
+
+  LARGE_INTEGER size;
+
+  if (!GetFileSizeEx(file, &size) || size.QuadPart == 0) {
+    CloseHandle(file);
+    return NULL;
+  }
+
+  HANDLE mapping = CreateFileMappingA(file, NULL, PAGE_READONLY, 0, 0, NULL);
+
+  CloseHandle(file);
+
+  if (mapping == NULL) {
+    return NULL;
+  }
+
+  void* data = MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, 0);
+
+  // The view keeps the mapping alive until it is unmapped
+  CloseHandle(mapping);
+
+  if (data == NULL) {
+    return NULL;
+  }
+
+  *len = size.QuadPart;
+
+  return (const char*) data;
+}
+
+void unmap_file(const char* data, unsigned long long len) {
+  UnmapViewOfFile(data);
+}
+
 #endif
\ No newline at end of file
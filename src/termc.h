@@ -5,6 +5,7 @@ struct wh;
 
 unsigned int c_kbhit();
 char get_ch();
+int use_tty();
 
 #ifdef __linux__
 
@@ -20,4 +21,7 @@ void set_cooked_term();
 // Required overall
 struct wh get_term_size();
 
+const char* map_file(const char* path, unsigned long long* len);
+void unmap_file(const char* data, unsigned long long len);
+
 #endif
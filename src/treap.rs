@@ -0,0 +1,398 @@
+use std::fmt::Debug;
+
+// A list kept as an implicit treap: every node holds one item, and the place of an item in the list
+// is given by the number of nodes to its left rather than by a stored key. Each node caches the size
+// of its subtree, along with a summary of the items in it for lists that are searched by more than
+// position, so finding, inserting or removing an item only walks one path from the root, which is
+// O(log n) in expectation thanks to the random priorities keeping it balanced.
+//
+// LineRope keeps the lines of a document in one, and RowIndex the rows each of those lines spans.
+//
+// Reference for the treap data structure:
+// https://cp-algorithms.com/data_structures/treap.html
+
+/// What a node caches about the items of its subtree besides how many there are
+pub trait Summary: Copy + Default + Debug {
+    /// The summary of the items summed up by self followed by those summed up by other
+    fn combine(self, other: Self) -> Self;
+}
+
+impl Summary for () {
+    fn combine(self, _: Self) -> Self {}
+}
+
+/// An item of a treap
+pub trait Summarize: Debug {
+    type Summary: Summary;
+
+    fn summarize(&self) -> Self::Summary;
+}
+
+/// xorshift64, random enough to keep a treap balanced and to drive tests that have to be repeatable
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // A state of 0 would only ever give 0
+        Self(seed | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    #[cfg(test)]
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+type Link<T> = Option<Box<Node<T>>>;
+
+#[derive(Debug)]
+struct Node<T: Summarize> {
+    item: T,
+    priority: u64,
+    /// Number of items in the subtree
+    size: usize,
+    /// Summary of every item in the subtree
+    summary: T::Summary,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+impl<T: Summarize> Node<T> {
+    fn new(item: T, priority: u64) -> Self {
+        Self {
+            summary: item.summarize(),
+            item,
+            priority,
+            size: 1,
+            left: None,
+            right: None,
+        }
+    }
+
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+        self.summary = summary(&self.left)
+            .combine(self.item.summarize())
+            .combine(summary(&self.right));
+    }
+}
+
+fn size<T: Summarize>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |n| n.size)
+}
+
+fn summary<T: Summarize>(link: &Link<T>) -> T::Summary {
+    link.as_ref()
+        .map_or_else(T::Summary::default, |n| n.summary)
+}
+
+fn merge<T: Summarize>(left: Link<T>, right: Link<T>) -> Link<T> {
+    //! Joins two treaps, every item in left comes before every item in right
+
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut l), Some(mut r)) => {
+            if l.priority > r.priority {
+                l.right = merge(l.right.take(), Some(r));
+                l.update();
+                Some(l)
+            } else {
+                r.left = merge(Some(l), r.left.take());
+                r.update();
+                Some(r)
+            }
+        }
+    }
+}
+
+fn split<T: Summarize>(link: Link<T>, ind: usize) -> (Link<T>, Link<T>) {
+    //! Splits a treap in two, the first holding the first ind items and the second the rest
+
+    match link {
+        None => (None, None),
+        Some(mut node) => {
+            let left_size = size(&node.left);
+
+            if ind <= left_size {
+                let (l, r) = split(node.left.take(), ind);
+                node.left = r;
+                node.update();
+                (l, Some(node))
+            } else {
+                let (l, r) = split(node.right.take(), ind - left_size - 1);
+                node.right = l;
+                node.update();
+                (Some(node), r)
+            }
+        }
+    }
+}
+
+fn update_at<T: Summarize, R>(link: &mut Link<T>, ind: usize, f: impl FnOnce(&mut T) -> R) -> R {
+    //! Changes the item at ind, then the summaries on the way back up
+
+    let node = link.as_deref_mut().expect("index out of bounds");
+    let left_size = size(&node.left);
+
+    let changed = if ind < left_size {
+        update_at(&mut node.left, ind, f)
+    } else if ind == left_size {
+        f(&mut node.item)
+    } else {
+        update_at(&mut node.right, ind - left_size - 1, f)
+    };
+
+    node.update();
+
+    changed
+}
+
+#[derive(Debug)]
+pub struct Treap<T: Summarize> {
+    root: Link<T>,
+    /// The generator the node priorities are drawn from
+    rng: Rng,
+}
+
+impl<T: Summarize> Treap<T> {
+    pub fn from_items(items: Vec<T>, seed: u64) -> Self {
+        //! Builds the treap in linear time, the items keep the order they are given in
+        //!
+        //! The tree is built as a cartesian tree over the random priorities using a stack of the
+        //! rightmost path, the same shape repeated insertion would give but without the log factor
+
+        let mut rng = Rng::new(seed);
+
+        let priorities: Vec<u64> = items.iter().map(|_| rng.next_u64()).collect();
+
+        let count = items.len();
+        let mut left: Vec<Option<usize>> = vec![None; count];
+        let mut right: Vec<Option<usize>> = vec![None; count];
+        let mut stack: Vec<usize> = Vec::new();
+
+        for i in 0..count {
+            let mut last = None;
+
+            while let Some(&top) = stack.last() {
+                if priorities[top] >= priorities[i] {
+                    break;
+                }
+
+                last = stack.pop();
+            }
+
+            left[i] = last;
+
+            if let Some(&top) = stack.last() {
+                right[top] = Some(i);
+            }
+
+            stack.push(i);
+        }
+
+        let mut items: Vec<Option<T>> = items.into_iter().map(Some).collect();
+
+        let root = stack
+            .first()
+            .map(|&root| Self::build_node(root, &left, &right, &priorities, &mut items));
+
+        Self { root, rng }
+    }
+
+    fn build_node(
+        ind: usize,
+        left: &[Option<usize>],
+        right: &[Option<usize>],
+        priorities: &[u64],
+        items: &mut [Option<T>],
+    ) -> Box<Node<T>> {
+        let mut node = Box::new(Node::new(items[ind].take().unwrap(), priorities[ind]));
+
+        node.left = left[ind].map(|l| Self::build_node(l, left, right, priorities, items));
+        node.right = right[ind].map(|r| Self::build_node(r, left, right, priorities, items));
+
+        node.update();
+
+        node
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn summary(&self) -> T::Summary {
+        //! The summary of every item
+        summary(&self.root)
+    }
+
+    pub fn get(&self, mut ind: usize) -> &T {
+        let mut curr = self.root.as_deref().expect("index out of bounds");
+
+        loop {
+            let left_size = size(&curr.left);
+
+            if ind < left_size {
+                curr = curr.left.as_deref().unwrap();
+            } else if ind == left_size {
+                return &curr.item;
+            } else {
+                ind -= left_size + 1;
+                curr = curr.right.as_deref().expect("index out of bounds");
+            }
+        }
+    }
+
+    pub fn update<R>(&mut self, ind: usize, f: impl FnOnce(&mut T) -> R) -> R {
+        //! Changes the item at ind through f, returning what f does
+        update_at(&mut self.root, ind, f)
+    }
+
+    pub fn insert(&mut self, ind: usize, item: T) {
+        //! Adds an item so that it ends up at ind, ind may be equal to len to append
+
+        let node = Box::new(Node::new(item, self.rng.next_u64()));
+
+        let (lhs, rhs) = split(self.root.take(), ind);
+
+        self.root = merge(merge(lhs, Some(node)), rhs);
+    }
+
+    pub fn remove(&mut self, ind: usize) -> T {
+        let (lhs, rhs) = split(self.root.take(), ind);
+        let (removed, rhs) = split(rhs, 1);
+
+        self.root = merge(lhs, rhs);
+
+        removed.expect("index out of bounds").item
+    }
+
+    pub fn prefix(&self, mut ind: usize) -> T::Summary {
+        //! The summary of the items before ind
+        //!
+        //! Every time the path turns right, the items to the left of the turn are all before it
+
+        let mut before = T::Summary::default();
+        let mut curr = self.root.as_deref();
+
+        while let Some(node) = curr {
+            let left_size = size(&node.left);
+
+            if ind <= left_size {
+                curr = node.left.as_deref();
+            } else {
+                before = before
+                    .combine(summary(&node.left))
+                    .combine(node.item.summarize());
+                ind -= left_size + 1;
+                curr = node.right.as_deref();
+            }
+        }
+
+        before
+    }
+
+    pub fn find(&self, mut target: usize, measure: impl Fn(T::Summary) -> usize) -> Option<usize> {
+        //! The index of the item that target falls in when every item is measured out one after
+        //! the other, None when target is past them all
+
+        let mut ind = 0;
+        let mut curr = self.root.as_deref();
+
+        while let Some(node) = curr {
+            let left = measure(summary(&node.left));
+            let own = measure(node.item.summarize());
+
+            if target < left {
+                curr = node.left.as_deref();
+            } else if target < left + own {
+                return Some(ind + size(&node.left));
+            } else {
+                target -= left + own;
+                ind += size(&node.left) + 1;
+                curr = node.right.as_deref();
+            }
+        }
+
+        None
+    }
+
+    pub fn iter_from(&self, ind: usize) -> Iter<'_, T> {
+        //! Iterates over the items in order starting at the item at ind
+        Iter::new(self, ind)
+    }
+}
+
+impl<T: Summarize> Drop for Treap<T> {
+    fn drop(&mut self) {
+        // Dropping the boxes recursively could overflow the stack on a degenerate tree, so unlink
+        // the nodes one by one instead
+        let mut stack: Vec<Box<Node<T>>> = self.root.take().into_iter().collect();
+
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }
+    }
+}
+
+/// In order iterator over the items of a Treap
+pub struct Iter<'a, T: Summarize> {
+    stack: Vec<&'a Node<T>>,
+}
+
+impl<'a, T: Summarize> Iter<'a, T> {
+    fn new(treap: &'a Treap<T>, mut ind: usize) -> Self {
+        //! Walks down to the item at ind, keeping every node where the path turned left since those
+        //! nodes come next in order
+
+        let mut stack = Vec::new();
+        let mut curr = treap.root.as_deref();
+
+        while let Some(node) = curr {
+            let left_size = size(&node.left);
+
+            if ind < left_size {
+                stack.push(node);
+                curr = node.left.as_deref();
+            } else if ind == left_size {
+                stack.push(node);
+                break;
+            } else {
+                ind -= left_size + 1;
+                curr = node.right.as_deref();
+            }
+        }
+
+        Self { stack }
+    }
+}
+
+impl<'a, T: Summarize> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+
+        let mut curr = node.right.as_deref();
+
+        while let Some(next) = curr {
+            self.stack.push(next);
+            curr = next.left.as_deref();
+        }
+
+        Some(&node.item)
+    }
+}
@@ -0,0 +1,257 @@
+// The document display window shows a run of the rows of a document, its viewport. A viewport is
+// as tall as the window except once it has been pushed past the end of the document, where it
+// comes up short instead of showing rows that do not exist.
+//
+// The cursor is never put on the last row of the window, so of the rows in view the cursor can only
+// reach all but the last.
+
+/// Where zz, zt and zb put the cursor's row within the document display window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Top,
+    Center,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// The first row in view
+    top: usize,
+    /// One past the last row in view
+    bottom: usize,
+}
+
+impl Viewport {
+    pub fn new(top: usize, bottom: usize) -> Self {
+        Self { top, bottom }
+    }
+
+    pub fn with_height(top: usize, height: usize) -> Self {
+        Self::new(top, top + height)
+    }
+
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    pub fn bottom(&self) -> usize {
+        //! One past the last row in view
+        self.bottom
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.bottom - self.top
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        (self.top..self.bottom).contains(&row)
+    }
+
+    pub fn scroll_to(&mut self, top: usize, height: usize) {
+        //! Puts top at the top of the window
+        *self = Self::with_height(top, height);
+    }
+
+    pub fn shift_to(&mut self, top: usize) {
+        //! Moves the viewport to start at top, keeping the number of rows in view
+        *self = Self::new(top, top + self.num_rows());
+    }
+
+    pub fn resize(&mut self, height: usize) {
+        //! Keeps the same top row in view when the window becomes height rows tall
+        self.bottom = self.top + height;
+    }
+
+    // ==================== SCROLLING A ROW AT A TIME ====================
+
+    pub fn push_down(&mut self, num_doc_rows: usize) {
+        //! Scrolls down a row, once the last row of the document is in view the viewport keeps its
+        //! bottom and only gets shorter
+
+        if self.bottom < num_doc_rows {
+            self.top += 1;
+            self.bottom += 1;
+        } else if self.bottom == num_doc_rows {
+            self.top += 1;
+        }
+    }
+
+    pub fn push_up(&mut self, height: usize) {
+        //! Scrolls up a row, a viewport shorter than the window grows back first
+
+        if height > self.num_rows() {
+            self.top -= 1;
+        } else if self.top > 0 {
+            self.top -= 1;
+            self.bottom -= 1;
+        }
+    }
+
+    // ==================== SCROLLING AROUND THE CURSOR ====================
+
+    fn last_top(height: usize, num_doc_rows: usize) -> usize {
+        //! The furthest down the top can be scrolled with the cursor still able to reach the last
+        //! row of the document
+        (num_doc_rows + 1).saturating_sub(height)
+    }
+
+    pub fn scroll_down(&mut self, rows: usize, height: usize, num_doc_rows: usize) {
+        //! Scrolls down by up to rows rows, stopping once the last row of the document is on the
+        //! last row the cursor can reach
+
+        let last_top = Self::last_top(height, num_doc_rows).max(self.top);
+
+        self.scroll_to((self.top + rows).min(last_top), height);
+    }
+
+    pub fn scroll_up(&mut self, rows: usize, height: usize) {
+        self.scroll_to(self.top.saturating_sub(rows), height);
+    }
+
+    pub fn scroll_carrying(
+        &mut self,
+        row: usize,
+        down: bool,
+        rows: usize,
+        height: usize,
+        num_doc_rows: usize,
+    ) -> usize {
+        //! Scrolls down or up by rows rows like Ctrl-D, Ctrl-F and the like, returns the row the
+        //! cursor is carried to from row, which moves as far even where the viewport cannot
+
+        if down {
+            self.scroll_down(rows, height, num_doc_rows);
+
+            (row + rows).min(num_doc_rows - 1)
+        } else {
+            self.scroll_up(rows, height);
+
+            row.saturating_sub(rows)
+        }
+    }
+
+    pub fn align(&mut self, row: usize, align: Align, height: usize) {
+        //! Scrolls so that row is at the top, middle or bottom of the rows the cursor can reach
+
+        let reachable = height.saturating_sub(1);
+
+        let above = match align {
+            Align::Top => 0,
+            Align::Center => reachable.saturating_sub(1) / 2,
+            Align::Bottom => reachable.saturating_sub(1),
+        };
+
+        self.scroll_to(row.saturating_sub(above), height);
+    }
+
+    pub fn follow(
+        &mut self,
+        row: usize,
+        scrolloff: usize,
+        height: usize,
+        num_doc_rows: usize,
+    ) -> bool {
+        //! Scrolls as little as possible for row to have scrolloff rows in view above and below it,
+        //! returns whether the viewport moved
+        //!
+        //! Near the start and end of the document there are not that many rows to show, and the
+        //! scrolloff is cut down when the window is too short to fit it on both sides of row
+
+        let reachable = height.saturating_sub(1).max(1);
+        let scrolloff = scrolloff.min((reachable - 1) / 2);
+
+        let top = if row < self.top + scrolloff {
+            row.saturating_sub(scrolloff)
+        } else if row + scrolloff >= self.top + reachable {
+            // Scroll only as far as the end of the document allows, but always far enough for row
+            let wanted = row + scrolloff + 1 - reachable;
+            let needed = row + 1 - reachable.min(row + 1);
+
+            wanted
+                .min(Self::last_top(height, num_doc_rows).max(self.top))
+                .max(needed)
+        } else {
+            self.top
+        };
+
+        if top == self.top {
+            return false;
+        }
+
+        self.scroll_to(top, height);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrolling_down_stops_once_the_last_row_can_be_reached() {
+        // With 10 rows in view the cursor reaches 9 of them, so row 19 is reached from top 11
+        let mut viewport = Viewport::with_height(0, 10);
+
+        viewport.scroll_down(8, 10, 20);
+        assert_eq!(viewport, Viewport::new(8, 18));
+
+        viewport.scroll_down(8, 10, 20);
+        assert_eq!(viewport, Viewport::new(11, 21));
+
+        // A viewport already pushed further down is not pulled back up
+        let mut viewport = Viewport::new(15, 20);
+
+        viewport.scroll_down(8, 10, 20);
+        assert_eq!(viewport, Viewport::new(15, 25));
+    }
+
+    #[test]
+    fn scrolling_up_stops_at_the_first_row() {
+        let mut viewport = Viewport::with_height(5, 10);
+
+        viewport.scroll_up(8, 10);
+        assert_eq!(viewport, Viewport::new(0, 10));
+
+        viewport.scroll_up(8, 10);
+        assert_eq!(viewport, Viewport::new(0, 10));
+    }
+
+    #[test]
+    fn the_cursor_is_carried_as_far_as_the_scroll() {
+        let mut viewport = Viewport::with_height(0, 10);
+
+        assert_eq!(viewport.scroll_carrying(3, true, 8, 10, 100), 11);
+        assert_eq!(viewport.top(), 8);
+
+        assert_eq!(viewport.scroll_carrying(11, false, 8, 10, 100), 3);
+        assert_eq!(viewport.top(), 0);
+    }
+
+    #[test]
+    fn the_cursor_keeps_moving_where_the_viewport_cannot() {
+        let mut viewport = Viewport::with_height(0, 10);
+
+        // The viewport stops at top 11 but the cursor goes on to the last row
+        assert_eq!(viewport.scroll_carrying(12, true, 8, 10, 20), 19);
+        assert_eq!(viewport.top(), 8);
+        assert_eq!(viewport.scroll_carrying(19, true, 8, 10, 20), 19);
+        assert_eq!(viewport.top(), 11);
+
+        // and stops on the first row at the top
+        assert_eq!(viewport.scroll_carrying(15, false, 20, 10, 20), 0);
+        assert_eq!(viewport.top(), 0);
+    }
+
+    #[test]
+    fn following_the_cursor_keeps_scrolloff_rows_around_it() {
+        let mut viewport = Viewport::with_height(0, 10);
+
+        assert!(!viewport.follow(5, 2, 10, 100));
+        assert!(viewport.follow(8, 2, 10, 100));
+        assert_eq!(viewport.top(), 2);
+
+        assert!(viewport.follow(3, 2, 10, 100));
+        assert_eq!(viewport.top(), 1);
+    }
+}
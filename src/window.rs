@@ -0,0 +1,824 @@
+use crate::document::{BufferView, Document};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+// The rows between the title and the status line are shared by windows, each showing a buffer
+// through its own cursor and viewport, so one buffer can be shown in several windows at once.
+//
+// Windows are laid out by a tree of splits. A split either stacks its children one above the other
+// or puts them side by side, and each child is a window or another split. Every window that is not
+// at the bottom of the shared rows gets a bar along its last row with the name of its buffer, and
+// windows side by side are kept apart by a column of │.
+//
+// Only the active window's cursor and viewport are live. While a window is active they are kept by
+// the editor and the buffer like they would be with a single window, and they are copied back into
+// the window when another one is made active.
+
+/// The fewest rows of text a window can be given, the cursor is never put on the last row of a
+/// window so it needs two to be moved around
+pub const MIN_HEIGHT: usize = 2;
+
+/// The fewest columns a window can be given
+pub const MIN_WIDTH: usize = 8;
+
+/// A block of the screen, rows and columns are counted from 1 as on the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub top: usize,
+    pub left: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl Rect {
+    pub fn bottom(&self) -> usize {
+        //! The last row of the block
+        (self.top + self.height).saturating_sub(1)
+    }
+
+    pub fn right(&self) -> usize {
+        //! The last column of the block
+        (self.left + self.width).saturating_sub(1)
+    }
+
+    fn overlaps_rows(&self, other: &Rect) -> bool {
+        self.top <= other.bottom() && other.top <= self.bottom()
+    }
+
+    fn overlaps_columns(&self, other: &Rect) -> bool {
+        self.left <= other.right() && other.left <= self.right()
+    }
+}
+
+/// How the children of a split are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// One above the other, made by :split
+    Stacked,
+    /// Next to each other, made by :vsplit
+    SideBySide,
+}
+
+/// Which way to look for the next window from the active one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heading {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId(usize);
+
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub buffer: Rc<RefCell<Document>>,
+    /// Where the window's text and gutter are drawn, its bar is not part of it
+    pub rect: Rect,
+    /// Where the cursor and the view were when the window was last active
+    pub view: BufferView,
+}
+
+impl Window {
+    fn new(buffer: Rc<RefCell<Document>>) -> Self {
+        Self {
+            buffer,
+            rect: Rect::default(),
+            view: BufferView::default(),
+        }
+    }
+}
+
+/// What is drawn between windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    /// The row under a window with another one below it
+    Bar {
+        window: WindowId,
+        row: usize,
+        left: usize,
+        width: usize,
+    },
+    /// The column between windows side by side
+    Column {
+        column: usize,
+        top: usize,
+        height: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Layout {
+    Window(WindowId),
+    /// Each child along with the rows or columns it was last given
+    Split {
+        direction: Direction,
+        children: Vec<(Layout, usize)>,
+    },
+}
+
+impl Layout {
+    fn contains(&self, id: WindowId) -> bool {
+        match self {
+            Layout::Window(window) => *window == id,
+            Layout::Split { children, .. } => children.iter().any(|(child, _)| child.contains(id)),
+        }
+    }
+
+    fn first_window(&self) -> WindowId {
+        match self {
+            Layout::Window(window) => *window,
+            Layout::Split { children, .. } => children[0].0.first_window(),
+        }
+    }
+
+    fn collect_ids(&self, ids: &mut Vec<WindowId>) {
+        match self {
+            Layout::Window(window) => ids.push(*window),
+            Layout::Split { children, .. } => {
+                for (child, _) in children {
+                    child.collect_ids(ids);
+                }
+            }
+        }
+    }
+
+    fn min_size(&self, along: Direction) -> usize {
+        //! The fewest rows or columns the layout can be squeezed into, a window is counted with
+        //! room for its bar
+
+        match self {
+            Layout::Window(_) => match along {
+                Direction::Stacked => MIN_HEIGHT + 1,
+                Direction::SideBySide => MIN_WIDTH,
+            },
+            Layout::Split {
+                direction,
+                children,
+            } => {
+                let sizes = children.iter().map(|(child, _)| child.min_size(along));
+
+                if *direction == along {
+                    let separators = match along {
+                        Direction::Stacked => 0,
+                        Direction::SideBySide => children.len() - 1,
+                    };
+
+                    sizes.sum::<usize>() + separators
+                } else {
+                    sizes.max().unwrap_or(0)
+                }
+            }
+        }
+    }
+
+    fn arrange(
+        &mut self,
+        rect: Rect,
+        area: Rect,
+        windows: &mut BTreeMap<WindowId, Window>,
+        separators: &mut Vec<Separator>,
+    ) {
+        //! Hands rect out to the windows of the layout, area is all of the rows the windows share
+
+        match self {
+            Layout::Window(id) => {
+                let mut text = rect;
+
+                // A window above another one gives its last row to its bar
+                if rect.bottom() < area.bottom() && rect.height > 1 {
+                    text.height -= 1;
+
+                    separators.push(Separator::Bar {
+                        window: *id,
+                        row: rect.bottom(),
+                        left: rect.left,
+                        width: rect.width,
+                    });
+                }
+
+                if let Some(window) = windows.get_mut(id) {
+                    window.rect = text;
+                }
+            }
+            Layout::Split {
+                direction,
+                children,
+            } => {
+                let separators_len = match direction {
+                    Direction::Stacked => 0,
+                    Direction::SideBySide => children.len() - 1,
+                };
+
+                let total = match direction {
+                    Direction::Stacked => rect.height,
+                    Direction::SideBySide => rect.width,
+                }
+                .saturating_sub(separators_len);
+
+                let weights = children.iter().map(|(_, size)| *size).collect::<Vec<_>>();
+                let sizes = distribute(total, &weights);
+
+                let mut offset = 0;
+                let last = children.len() - 1;
+
+                for (ind, ((child, size), new_size)) in children.iter_mut().zip(sizes).enumerate() {
+                    *size = new_size;
+
+                    let child_rect = match direction {
+                        Direction::Stacked => Rect {
+                            top: rect.top + offset,
+                            height: new_size,
+                            ..rect
+                        },
+                        Direction::SideBySide => Rect {
+                            left: rect.left + offset,
+                            width: new_size,
+                            ..rect
+                        },
+                    };
+
+                    child.arrange(child_rect, area, windows, separators);
+
+                    offset += new_size;
+
+                    if *direction == Direction::SideBySide && ind != last {
+                        separators.push(Separator::Column {
+                            column: rect.left + offset,
+                            top: rect.top,
+                            height: rect.height,
+                        });
+
+                        offset += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn split(&mut self, id: WindowId, new: WindowId, direction: Direction) {
+        //! Puts the new window before the window id, sharing the room id had
+
+        match self {
+            Layout::Window(window) if *window == id => {
+                *self = Layout::Split {
+                    direction,
+                    children: vec![(Layout::Window(new), 1), (Layout::Window(id), 1)],
+                };
+            }
+            Layout::Window(_) => (),
+            Layout::Split {
+                direction: split_direction,
+                children,
+            } => {
+                let found = children.iter().position(
+                    |(child, _)| matches!(child, Layout::Window(window) if *window == id),
+                );
+
+                match found {
+                    // A split going the same way takes the new window in as another child
+                    Some(ind) if *split_direction == direction => {
+                        let size = children[ind].1;
+
+                        children[ind].1 = size - size / 2;
+                        children.insert(ind, (Layout::Window(new), size / 2));
+                    }
+                    _ => {
+                        for (child, _) in children.iter_mut() {
+                            if child.contains(id) {
+                                child.split(id, new, direction);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, id: WindowId) -> Option<WindowId> {
+        //! Takes the window id out of the layout, its room goes to the child before it, or after it
+        //! when it is the first, returns the window in that child that should become active
+
+        let Layout::Split { children, .. } = self else {
+            return None;
+        };
+
+        let Some(ind) = children
+            .iter()
+            .position(|(child, _)| matches!(child, Layout::Window(window) if *window == id))
+        else {
+            return children
+                .iter_mut()
+                .find(|(child, _)| child.contains(id))
+                .and_then(|(child, _)| child.remove(id));
+        };
+
+        let (_, size) = children.remove(ind);
+        let heir = ind.saturating_sub(1);
+
+        children[heir].1 += size;
+
+        let focus = children[heir].0.first_window();
+
+        // A split left with one child is replaced by that child
+        if children.len() == 1 {
+            *self = children.remove(0).0;
+        }
+
+        Some(focus)
+    }
+
+    fn resize(&mut self, id: WindowId, along: Direction, delta: isize) -> bool {
+        //! Grows the child holding id within the closest split going the right way, taking the
+        //! room from its neighbour, returns whether that split was found
+
+        let Layout::Split {
+            direction,
+            children,
+        } = self
+        else {
+            return false;
+        };
+
+        let Some(ind) = children.iter().position(|(child, _)| child.contains(id)) else {
+            return false;
+        };
+
+        if children[ind].0.resize(id, along, delta) {
+            return true;
+        }
+
+        if *direction != along || children.len() < 2 {
+            return false;
+        }
+
+        let other = if ind + 1 < children.len() {
+            ind + 1
+        } else {
+            ind - 1
+        };
+
+        let (grow, shrink) = if delta >= 0 {
+            (ind, other)
+        } else {
+            (other, ind)
+        };
+
+        let room = children[shrink]
+            .1
+            .saturating_sub(children[shrink].0.min_size(along));
+        let moved = delta.unsigned_abs().min(room);
+
+        children[shrink].1 -= moved;
+        children[grow].1 += moved;
+
+        true
+    }
+
+    fn equalize(&mut self) {
+        if let Layout::Split { children, .. } = self {
+            for (child, size) in children.iter_mut() {
+                *size = 1;
+                child.equalize();
+            }
+        }
+    }
+}
+
+fn distribute(total: usize, weights: &[usize]) -> Vec<usize> {
+    //! Shares total out in proportion to weights, what is left over from rounding down goes to
+    //! the last ones
+
+    let sum = weights.iter().sum::<usize>().max(1);
+
+    let mut sizes = weights
+        .iter()
+        .map(|weight| total * weight / sum)
+        .collect::<Vec<_>>();
+
+    let len = sizes.len();
+    let rest = total - sizes.iter().sum::<usize>();
+
+    for ind in 0..rest {
+        sizes[len - 1 - ind % len] += 1;
+    }
+
+    sizes
+}
+
+/// Every window along with how they are laid out and which one is active
+#[derive(Debug)]
+pub struct Windows {
+    layout: Layout,
+    windows: BTreeMap<WindowId, Window>,
+    next_id: usize,
+    active: WindowId,
+    separators: Vec<Separator>,
+    area: Rect,
+}
+
+impl Windows {
+    pub fn new(buffer: Rc<RefCell<Document>>) -> Self {
+        let id = WindowId(0);
+
+        Self {
+            layout: Layout::Window(id),
+            windows: BTreeMap::from([(id, Window::new(buffer))]),
+            next_id: 1,
+            active: id,
+            separators: Vec::new(),
+            area: Rect::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    pub fn active_id(&self) -> WindowId {
+        self.active
+    }
+
+    pub fn active(&self) -> &Window {
+        &self.windows[&self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Window {
+        self.windows.get_mut(&self.active).unwrap()
+    }
+
+    pub fn get(&self, id: WindowId) -> Option<&Window> {
+        self.windows.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        self.windows.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (WindowId, &Window)> {
+        self.windows.iter().map(|(id, window)| (*id, window))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Window> {
+        self.windows.values_mut()
+    }
+
+    pub fn separators(&self) -> &[Separator] {
+        &self.separators
+    }
+
+    pub fn ids(&self) -> Vec<WindowId> {
+        //! Every window from the top left to the bottom right, the order Ctrl-W w goes through them
+
+        let mut ids = Vec::new();
+
+        self.layout.collect_ids(&mut ids);
+
+        ids
+    }
+
+    pub fn arrange(&mut self, area: Rect) {
+        //! Lays the windows out over area, the rows between the title and the status line
+
+        self.area = area;
+        self.separators.clear();
+
+        self.layout
+            .arrange(area, area, &mut self.windows, &mut self.separators);
+    }
+
+    pub fn split(&mut self, direction: Direction) -> Result<WindowId, String> {
+        //! Splits the active window in two, the new window shows what the active one does and
+        //! becomes active itself
+
+        let rect = self.active().rect;
+
+        let (room, needed) = match direction {
+            Direction::Stacked => (rect.height, 2 * (MIN_HEIGHT + 1)),
+            Direction::SideBySide => (rect.width, 2 * MIN_WIDTH + 1),
+        };
+
+        if room < needed {
+            return Err("Not enough room".to_string());
+        }
+
+        let id = WindowId(self.next_id);
+        self.next_id += 1;
+
+        let window = self.active().clone();
+
+        self.windows.insert(id, window);
+        self.layout.split(self.active, id, direction);
+
+        self.active = id;
+
+        self.arrange(self.area);
+
+        Ok(id)
+    }
+
+    pub fn close(&mut self, id: WindowId) -> Result<(), String> {
+        //! Closes the window, the window that takes over its room becomes active if it was
+
+        if self.windows.len() == 1 {
+            return Err("Cannot close last window".to_string());
+        }
+
+        let focus = self.layout.remove(id);
+
+        self.windows.remove(&id);
+
+        if self.active == id {
+            self.active = focus.unwrap_or_else(|| self.layout.first_window());
+        }
+
+        self.arrange(self.area);
+
+        Ok(())
+    }
+
+    pub fn only(&mut self) {
+        //! Closes every window but the active one
+
+        self.windows.retain(|id, _| *id == self.active);
+        self.layout = Layout::Window(self.active);
+
+        self.arrange(self.area);
+    }
+
+    pub fn set_active(&mut self, id: WindowId) {
+        if self.windows.contains_key(&id) {
+            self.active = id;
+        }
+    }
+
+    pub fn cycle(&self, forward: bool) -> WindowId {
+        //! The window after the active one, or before it, going round from the last to the first
+
+        let ids = self.ids();
+        let ind = ids.iter().position(|id| *id == self.active).unwrap_or(0);
+
+        if forward {
+            ids[(ind + 1) % ids.len()]
+        } else {
+            ids[(ind + ids.len() - 1) % ids.len()]
+        }
+    }
+
+    pub fn neighbour(&self, heading: Heading) -> Option<WindowId> {
+        //! The closest window in the direction of heading that lines up with the active one, the
+        //! one nearest its top left corner when there are several
+
+        let rect = self.active().rect;
+
+        self.windows
+            .iter()
+            .filter(|(id, _)| **id != self.active)
+            .filter(|(_, window)| {
+                let other = window.rect;
+
+                match heading {
+                    Heading::Up => other.bottom() < rect.top && other.overlaps_columns(&rect),
+                    Heading::Down => other.top > rect.bottom() && other.overlaps_columns(&rect),
+                    Heading::Left => other.right() < rect.left && other.overlaps_rows(&rect),
+                    Heading::Right => other.left > rect.right() && other.overlaps_rows(&rect),
+                }
+            })
+            .min_by_key(|(_, window)| {
+                let other = window.rect;
+
+                let distance = match heading {
+                    Heading::Up => rect.top - other.bottom(),
+                    Heading::Down => other.top - rect.bottom(),
+                    Heading::Left => rect.left - other.right(),
+                    Heading::Right => other.left - rect.right(),
+                };
+
+                let offset = match heading {
+                    Heading::Up | Heading::Down => other.left.abs_diff(rect.left),
+                    Heading::Left | Heading::Right => other.top.abs_diff(rect.top),
+                };
+
+                (distance, offset)
+            })
+            .map(|(id, _)| *id)
+    }
+
+    pub fn resize(&mut self, along: Direction, delta: isize) {
+        //! Makes the active window delta rows taller, or delta columns wider when along is
+        //! SideBySide, as far as its neighbour can give up room
+
+        let active = self.active;
+
+        if self.layout.resize(active, along, delta) {
+            self.arrange(self.area);
+        }
+    }
+
+    pub fn equalize(&mut self) {
+        //! Gives the children of every split the same room
+
+        self.layout.equalize();
+
+        self.arrange(self.area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rows the windows share on a 24 by 80 terminal, under the title and above the status
+    /// line and command row
+    const AREA: Rect = Rect {
+        top: 2,
+        left: 1,
+        height: 20,
+        width: 80,
+    };
+
+    fn windows() -> Windows {
+        let mut windows = Windows::new(Rc::new(RefCell::new(Document::new_unnamed(20))));
+
+        windows.arrange(AREA);
+
+        windows
+    }
+
+    fn rect(windows: &Windows, id: WindowId) -> Rect {
+        windows.get(id).unwrap().rect
+    }
+
+    fn rect_at(top: usize, left: usize, height: usize, width: usize) -> Rect {
+        Rect {
+            top,
+            left,
+            height,
+            width,
+        }
+    }
+
+    #[test]
+    fn a_single_window_takes_the_whole_area() {
+        let windows = windows();
+
+        assert_eq!(windows.active().rect, AREA);
+        assert_eq!(windows.separators(), []);
+    }
+
+    #[test]
+    fn stacked_windows_share_the_rows_with_a_bar_under_the_top_one() {
+        let mut windows = windows();
+        let first = windows.active_id();
+
+        let second = windows.split(Direction::Stacked).unwrap();
+
+        assert_eq!(windows.active_id(), second);
+        assert_eq!(windows.ids(), [second, first]);
+
+        assert_eq!(rect(&windows, second), rect_at(2, 1, 9, 80));
+        assert_eq!(rect(&windows, first), rect_at(12, 1, 10, 80));
+        assert_eq!(
+            windows.separators(),
+            [Separator::Bar {
+                window: second,
+                row: 11,
+                left: 1,
+                width: 80,
+            }]
+        );
+    }
+
+    #[test]
+    fn windows_side_by_side_are_kept_apart_by_a_column() {
+        let mut windows = windows();
+        let first = windows.active_id();
+
+        let second = windows.split(Direction::SideBySide).unwrap();
+
+        // What does not share out evenly goes to the last window
+        assert_eq!(rect(&windows, second), rect_at(2, 1, 20, 39));
+        assert_eq!(rect(&windows, first), rect_at(2, 41, 20, 40));
+        assert_eq!(
+            windows.separators(),
+            [Separator::Column {
+                column: 40,
+                top: 2,
+                height: 20,
+            }]
+        );
+    }
+
+    #[test]
+    fn splitting_a_window_too_small_is_refused() {
+        let mut windows = windows();
+
+        windows.arrange(rect_at(2, 1, 5, 16));
+
+        assert!(windows.split(Direction::Stacked).is_err());
+        assert!(windows.split(Direction::SideBySide).is_err());
+        assert_eq!(windows.len(), 1);
+    }
+
+    #[test]
+    fn closing_a_window_gives_its_room_to_the_one_before_it() {
+        let mut windows = windows();
+        let first = windows.active_id();
+
+        let second = windows.split(Direction::Stacked).unwrap();
+        let third = windows.split(Direction::Stacked).unwrap();
+
+        assert_eq!(windows.ids(), [third, second, first]);
+
+        windows.close(second).unwrap();
+
+        // The active window stays so when another one is closed
+        assert_eq!(windows.active_id(), third);
+        assert_eq!(rect(&windows, third), rect_at(2, 1, 9, 80));
+        assert_eq!(rect(&windows, first), rect_at(12, 1, 10, 80));
+
+        windows.close(third).unwrap();
+
+        assert_eq!(windows.active_id(), first);
+        assert_eq!(rect(&windows, first), AREA);
+        assert!(windows.close(first).is_err());
+    }
+
+    #[test]
+    fn only_keeps_the_active_window() {
+        let mut windows = windows();
+
+        windows.split(Direction::Stacked).unwrap();
+        let kept = windows.split(Direction::SideBySide).unwrap();
+
+        windows.only();
+
+        assert_eq!(windows.ids(), [kept]);
+        assert_eq!(windows.active().rect, AREA);
+        assert_eq!(windows.separators(), []);
+    }
+
+    #[test]
+    fn resizing_takes_room_from_the_neighbour_down_to_its_smallest() {
+        let mut windows = windows();
+        let first = windows.active_id();
+
+        let second = windows.split(Direction::Stacked).unwrap();
+
+        windows.resize(Direction::Stacked, 4);
+
+        assert_eq!(rect(&windows, second), rect_at(2, 1, 13, 80));
+        assert_eq!(rect(&windows, first), rect_at(16, 1, 6, 80));
+
+        // The other window keeps the fewest rows a window can have
+        windows.resize(Direction::Stacked, 100);
+
+        assert_eq!(rect(&windows, first).height, MIN_HEIGHT + 1);
+
+        windows.equalize();
+
+        assert_eq!(rect(&windows, second), rect_at(2, 1, 9, 80));
+        assert_eq!(rect(&windows, first), rect_at(12, 1, 10, 80));
+    }
+
+    #[test]
+    fn resizing_across_a_split_that_goes_the_other_way_does_nothing() {
+        let mut windows = windows();
+        let first = windows.active_id();
+
+        windows.split(Direction::Stacked).unwrap();
+
+        windows.resize(Direction::SideBySide, 10);
+
+        assert_eq!(rect(&windows, first), rect_at(12, 1, 10, 80));
+    }
+
+    #[test]
+    fn moving_between_windows_follows_the_layout() {
+        let mut windows = windows();
+        let bottom = windows.active_id();
+
+        let top_right = windows.split(Direction::Stacked).unwrap();
+        let top_left = windows.split(Direction::SideBySide).unwrap();
+
+        assert_eq!(windows.ids(), [top_left, top_right, bottom]);
+
+        assert_eq!(windows.neighbour(Heading::Right), Some(top_right));
+        assert_eq!(windows.neighbour(Heading::Down), Some(bottom));
+        assert_eq!(windows.neighbour(Heading::Up), None);
+
+        windows.set_active(bottom);
+
+        // Of the windows above, the one nearest the top left corner
+        assert_eq!(windows.neighbour(Heading::Up), Some(top_left));
+
+        assert_eq!(windows.cycle(true), top_left);
+        assert_eq!(windows.cycle(false), top_right);
+    }
+}
@@ -0,0 +1,409 @@
+use std::ops::Range;
+
+// Lines longer than the document display window are wrapped onto the rows below. By default a line
+// is cut every time it reaches the width of the window, even in the middle of a word. With
+// linebreak on, a row instead ends just after the last character of breakat that fits on it, so
+// words are kept whole where possible. Every row but the first of a line can be started with the
+// showbreak marker, and with breakindent on it is indented as far as the line itself is.
+//
+// A tab character reaches to the next multiple of tabstop columns counted from the start of its row,
+// one that does not fit on the end of a row is cut short there. Every other character takes a single
+// column, the same as it takes a single cell of the screen, where characters a terminal would draw
+// wider or narrower are drawn as a placeholder. Only lines of nothing but one byte
+// characters other than tabs have their columns line up with their positions, the rows of any other
+// line have to be worked out from its text.
+
+/// The characters a row may end after while linebreak is on, the same ones vim uses by default
+pub const DEFAULT_BREAKAT: &str = " \t!@*-+;:,./?";
+
+/// How many columns apart tab stops are unless set otherwise
+pub const DEFAULT_TABSTOP: usize = 4;
+
+pub fn is_uneven(c: char) -> bool {
+    //! Whether the columns c takes do not match the bytes it takes
+    c == '\t' || !c.is_ascii()
+}
+
+pub fn columns(shown: &str, columns: Range<usize>) -> &str {
+    //! The part of a row as it is drawn that covers columns, counted from the start of the row
+
+    let byte_of = |column| {
+        shown
+            .char_indices()
+            .nth(column)
+            .map_or(shown.len(), |(ind, _)| ind)
+    };
+
+    &shown[byte_of(columns.start)..byte_of(columns.end)]
+}
+
+/// How lines are cut into rows while wrapping is on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrapStyle {
+    /// Whether rows end after one of the breakat characters rather than exactly at the width
+    pub linebreak: bool,
+    pub breakat: String,
+    /// Drawn at the start of every row but the first of a line
+    pub showbreak: String,
+    /// Whether every row but the first of a line is indented as far as the line is
+    pub breakindent: bool,
+    /// How many columns apart tab stops are, the same as the buffer's indent.tabstop
+    pub tabstop: usize,
+}
+
+impl Default for WrapStyle {
+    fn default() -> Self {
+        Self {
+            linebreak: false,
+            breakat: DEFAULT_BREAKAT.to_string(),
+            showbreak: String::new(),
+            breakindent: false,
+            tabstop: DEFAULT_TABSTOP,
+        }
+    }
+}
+
+impl WrapStyle {
+    pub fn is_plain(&self) -> bool {
+        //! Whether lines are cut exactly every width columns with nothing drawn before the rows they
+        //! wrap onto, in which case where a row of a line with no uneven characters starts can be
+        //! worked out from the width alone
+        !self.linebreak && self.showbreak.is_empty() && !self.breakindent
+    }
+
+    fn continuation_prefix(&self, line: &str, width: usize) -> String {
+        //! What is drawn before the text of every row but the first of line, the indent and the
+        //! showbreak marker are cut short so that at least half the width is left for the text
+
+        let indent = if self.breakindent {
+            line.bytes()
+                .take_while(|b| *b == b' ' || *b == b'\t')
+                .count()
+        } else {
+            0
+        };
+
+        " ".repeat(indent)
+            .chars()
+            .chain(self.showbreak.chars())
+            .take(width / 2)
+            .collect()
+    }
+
+    pub fn layout(&self, line: &str, width: usize) -> RowLayout {
+        //! Works out where each of the rows line spans starts when the rows are width columns wide
+        //!
+        //! The line is walked once, a row is ended at the first character that does not fit on it.
+        //! With linebreak on the characters after the last break on the row are carried over to the
+        //! next one, so no character is walked more than twice
+
+        let prefix = if self.is_plain() {
+            String::new()
+        } else {
+            self.continuation_prefix(line, width)
+        };
+
+        let mut layout = RowLayout {
+            starts: vec![0],
+            prefix,
+            width,
+            tabstop: self.tabstop,
+        };
+
+        if width == 0 {
+            return layout;
+        }
+
+        let mut ind = 0;
+        let mut col = 0;
+        let mut room = width;
+        // Just after the last breakat character on the current row
+        let mut last_break = None;
+
+        while let Some(c) = line[ind..].chars().next() {
+            let columns = layout.char_width(c, col, room);
+
+            if col + columns <= room {
+                col += columns;
+                ind += c.len_utf8();
+
+                if self.linebreak && self.breaks_at(c) {
+                    last_break = Some(ind);
+                }
+
+                continue;
+            }
+
+            // A blank that does not fit ends the row just before it rather than at the last break,
+            // as the word before it fits whole
+            let end = match last_break {
+                Some(end) if !(c.is_ascii_whitespace() && self.breaks_at(c)) => end,
+                _ => ind,
+            };
+
+            // Every row has room for at least one column, so it always takes at least one character
+            layout.starts.push(end);
+
+            ind = end;
+            col = 0;
+            room = width - layout.prefix.chars().count();
+            last_break = None;
+        }
+
+        layout
+    }
+
+    fn breaks_at(&self, c: char) -> bool {
+        c.is_ascii() && self.breakat.contains(c)
+    }
+}
+
+/// The rows a line is cut into, given by the position in the line that each row starts at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowLayout {
+    /// Always starts with 0, every line spans at least one row
+    pub starts: Vec<usize>,
+    /// Drawn before the text of every row but the first
+    pub prefix: String,
+    /// The columns the rows were cut to fit in, the prefix included
+    width: usize,
+    tabstop: usize,
+}
+
+impl RowLayout {
+    pub fn num_rows(&self) -> usize {
+        self.starts.len()
+    }
+
+    pub fn row_of(&self, pos: usize) -> usize {
+        //! The row of the line that the character at pos is drawn on, a position past the end of
+        //! the line is on the last row
+        self.starts.partition_point(|start| *start <= pos) - 1
+    }
+
+    pub fn prefix_width(&self, row: usize) -> usize {
+        //! The number of columns drawn before the text of the row
+        if row == 0 {
+            0
+        } else {
+            self.prefix.chars().count()
+        }
+    }
+
+    pub fn row_text<'a>(&self, line: &'a str, row: usize) -> &'a str {
+        let end = self.starts.get(row + 1).copied().unwrap_or(line.len());
+
+        &line[self.starts[row]..end]
+    }
+
+    pub fn row_display(&self, line: &str, row: usize) -> String {
+        //! The row as it is drawn, the prefix followed by its text with every tab turned into the
+        //! spaces it spans
+
+        let room = self.room(row);
+
+        let mut shown = if row == 0 {
+            String::new()
+        } else {
+            self.prefix.clone()
+        };
+
+        let mut col = 0;
+
+        for c in self.row_text(line, row).chars() {
+            let columns = self.char_width(c, col, room);
+
+            match c {
+                '\t' => shown.push_str(&" ".repeat(columns)),
+                _ => shown.push(c),
+            }
+
+            col += columns;
+        }
+
+        shown
+    }
+
+    pub fn column_of(&self, line: &str, pos: usize) -> usize {
+        //! The column, counted from the left of the document display window, that the character at
+        //! pos is drawn at, a position past the end of the line is just after its last character
+
+        let row = self.row_of(pos);
+
+        let mut end = pos.min(line.len());
+
+        // A position inside a character is drawn over the same columns as the character itself
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let before = &line[self.starts[row]..end];
+
+        self.prefix_width(row) + self.text_width(before, self.room(row))
+    }
+
+    pub fn pos_at_column(&self, line: &str, row: usize, column: usize) -> usize {
+        //! The position of the character of the row drawn over column, counted from the left of the
+        //! document display window, or the end of the row's text when nothing is drawn there
+
+        let column = column.saturating_sub(self.prefix_width(row));
+        let room = self.room(row);
+
+        let mut col = 0;
+
+        for (ind, c) in self.row_text(line, row).char_indices() {
+            col += self.char_width(c, col, room);
+
+            if col > column {
+                return self.starts[row] + ind;
+            }
+        }
+
+        self.starts[row] + self.row_text(line, row).len()
+    }
+
+    fn room(&self, row: usize) -> usize {
+        //! The columns left for the text of the row after its prefix
+        self.width.saturating_sub(self.prefix_width(row))
+    }
+
+    fn char_width(&self, c: char, col: usize, room: usize) -> usize {
+        //! The columns c takes drawn at col of a row with room columns for its text
+
+        match c {
+            '\t' => {
+                let tabstop = self.tabstop.max(1);
+
+                (tabstop - col % tabstop).min(room.saturating_sub(col).max(1))
+            }
+            _ => 1,
+        }
+    }
+
+    fn text_width(&self, text: &str, room: usize) -> usize {
+        //! The columns text takes drawn from the start of a row with room columns for its text
+
+        text.chars()
+            .fold(0, |col, c| col + self.char_width(c, col, room))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap_style(linebreak: bool, showbreak: &str, breakindent: bool) -> WrapStyle {
+        WrapStyle {
+            linebreak,
+            showbreak: showbreak.to_string(),
+            breakindent,
+            ..WrapStyle::default()
+        }
+    }
+
+    fn rows(style: &WrapStyle, line: &str, width: usize) -> Vec<String> {
+        let layout = style.layout(line, width);
+
+        (0..layout.num_rows())
+            .map(|row| layout.row_display(line, row))
+            .collect()
+    }
+
+    #[test]
+    fn plain_rows_are_cut_at_the_width() {
+        let style = WrapStyle::default();
+
+        assert_eq!(style.layout("abcdefghij", 4).starts, vec![0, 4, 8]);
+        assert_eq!(style.layout("abcdefgh", 4).starts, vec![0, 4]);
+        assert_eq!(style.layout("", 4).starts, vec![0]);
+        assert_eq!(style.layout("abc", 0).starts, vec![0]);
+
+        // Every character that is not a tab takes a column, however many bytes it is
+        assert_eq!(rows(&style, "héllo wörld", 4), vec!["héll", "o wö", "rld"]);
+    }
+
+    #[test]
+    fn linebreak_ends_rows_after_breakat_characters() {
+        let style = wrap_style(true, "", false);
+
+        assert_eq!(rows(&style, "one two three", 8), vec!["one two ", "three"]);
+        assert_eq!(rows(&style, "a-b-c-d", 4), vec!["a-b-", "c-d"]);
+
+        // A blank that does not fit starts the next row, the word before it is kept on this one
+        assert_eq!(rows(&style, "ab cd ef", 5), vec!["ab cd", " ef"]);
+
+        // A word longer than a row is cut where the row ends
+        assert_eq!(rows(&style, "abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn showbreak_and_breakindent_are_drawn_before_the_rows_after_the_first() {
+        let style = wrap_style(false, "> ", true);
+
+        assert_eq!(
+            rows(&style, "  abcdefghijkl", 8),
+            vec!["  abcdef", "  > ghij", "  > kl"]
+        );
+
+        // The prefix never takes more than half the width
+        let style = wrap_style(false, ">>>>>>", false);
+
+        assert_eq!(rows(&style, "abcdefgh", 6), vec!["abcdef", ">>>gh"]);
+    }
+
+    #[test]
+    fn tabs_reach_the_next_tab_stop_of_their_row() {
+        let style = WrapStyle {
+            tabstop: 4,
+            ..WrapStyle::default()
+        };
+
+        assert_eq!(rows(&style, "a\tb\tc", 20), vec!["a   b   c"]);
+
+        // A tab that does not fit on the end of a row is cut short there
+        assert_eq!(rows(&style, "abcdef\tg", 8), vec!["abcdef  ", "g"]);
+
+        let layout = style.layout("a\tb", 20);
+
+        assert_eq!(layout.column_of("a\tb", 1), 1);
+        assert_eq!(layout.column_of("a\tb", 2), 4);
+        assert_eq!(layout.pos_at_column("a\tb", 0, 2), 1);
+        assert_eq!(layout.pos_at_column("a\tb", 0, 4), 2);
+    }
+
+    #[test]
+    fn columns_and_positions_agree() {
+        let style = wrap_style(true, "+", false);
+        let line = "héllo wörld and more";
+
+        let layout = style.layout(line, 8);
+
+        assert_eq!(layout.starts, vec![0, 7, 14, 18]);
+
+        for (pos, _) in line.char_indices() {
+            let row = layout.row_of(pos);
+            let column = layout.column_of(line, pos);
+
+            assert_eq!(layout.pos_at_column(line, row, column), pos);
+        }
+
+        // The prefix is drawn before the text of the second row
+        assert_eq!(layout.column_of(line, 7), 1);
+        assert_eq!(layout.pos_at_column(line, 1, 0), 7);
+
+        // Past the end of a row's text is its end, past the end of the line is on the last row
+        assert_eq!(layout.pos_at_column(line, 3, 20), line.len());
+        assert_eq!(layout.row_of(line.len()), 3);
+        assert_eq!(layout.column_of(line, line.len()), 1 + "more".len());
+
+        // A position inside a character is drawn where the character is
+        assert_eq!(layout.column_of(line, 2), 1);
+    }
+
+    #[test]
+    fn columns_cut_rows_by_character() {
+        assert_eq!(columns("héllo", 1..3), "él");
+        assert_eq!(columns("héllo", 3..10), "lo");
+        assert!(is_uneven('\t') && is_uneven('é') && !is_uneven('a'));
+    }
+}